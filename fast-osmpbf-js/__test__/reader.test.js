@@ -0,0 +1,93 @@
+import { test } from "node:test";
+import assert from "node:assert/strict";
+import { join, dirname } from "path";
+import { fileURLToPath } from "url";
+import { OsmReader, getTags, getNodeIds, getRelationMembers } from "../index.js";
+
+// All files hold the same elements, written by the PbfFixture of fast-osmpbf with dense and plain nodes
+const fixtures = join(dirname(fileURLToPath(import.meta.url)), "fixtures");
+
+// The offset files store coordinates in units of 1e-6 degrees, shifted by offsets of
+// 123456700 and -98765300 nanodegrees, the others in the default units of 1e-7 degrees
+const pairs = [
+  { dense: "dense.osm.pbf", plain: "plain.osm.pbf", unit: 1e-7 },
+  { dense: "dense-offset.osm.pbf", plain: "plain-offset.osm.pbf", unit: 1e-6 },
+];
+
+const nodes = [
+  { id: 1n, lat: 53.075, lon: 8.8071, tags: [["amenity", "cafe"], ["name", "Café"]] },
+  { id: 2n, lat: -33.8688, lon: 151.2093, tags: [] },
+  { id: 5n, lat: 0, lon: -0.1276, tags: [["name", "Null Island"]] },
+];
+
+async function readBlocks(file, ...options) {
+  const iterator = new OsmReader(join(fixtures, file)).streamBlocks(...options);
+  const blocks = [];
+  let block;
+  while ((block = await iterator.next()) !== null) {
+    blocks.push(block);
+  }
+  return blocks;
+}
+
+function assertCoordinate(actual, expected, unit) {
+  // coordinates are rounded to the nearest unit they are stored in
+  assert.ok(Math.abs(actual - expected) < unit, `${actual} != ${expected}`);
+}
+
+async function readNodeBlock(file) {
+  const blocks = (await readBlocks(file)).filter((block) => block.elementType === "Node");
+  assert.equal(blocks.length, 1);
+  return blocks[0];
+}
+
+for (const { dense, plain, unit } of pairs) {
+  test(`decodes the same coordinates from the dense nodes of ${dense} and the plain nodes of ${plain}`, async () => {
+    const [denseBlock, plainBlock] = [await readNodeBlock(dense), await readNodeBlock(plain)];
+    assert.deepEqual([...plainBlock.latitudes], [...denseBlock.latitudes]);
+    assert.deepEqual([...plainBlock.longitudes], [...denseBlock.longitudes]);
+  });
+
+  for (const [file, tagsField] of [
+    [dense, "denseTags"],
+    [plain, "tags"],
+  ]) {
+    test(`reads the ids, coordinates and tags of the nodes of ${file}`, async () => {
+      const block = await readNodeBlock(file);
+      assert.ok(block[tagsField]);
+      assert.deepEqual([...block.ids], nodes.map((node) => node.id));
+      nodes.forEach((node, index) => {
+        assertCoordinate(block.latitudes[index], node.lat, unit);
+        assertCoordinate(block.longitudes[index], node.lon, unit);
+        assert.deepEqual(getTags(block, index), node.tags);
+      });
+    });
+  }
+}
+
+for (const file of ["dense.osm.pbf", "plain.osm.pbf"]) {
+  test(`reads the same nodes of ${file} with a raw string table`, async () => {
+    const [block] = await readBlocks(file, { nodes: true, ways: false, relations: false }, null, true);
+    assert.equal(block.elementType, "Node");
+    assert.equal(block.stringTable.length, 0);
+    assert.deepEqual([...block.ids], nodes.map((node) => node.id));
+    nodes.forEach((node, index) => assert.deepEqual(getTags(block, index), node.tags));
+  });
+}
+
+test("reads the node ids of ways and the members of relations", async () => {
+  const blocks = await readBlocks("dense.osm.pbf");
+  assert.deepEqual(
+    blocks.map((block) => block.elementType),
+    ["Node", "Way", "Relation"],
+  );
+  const [, ways, relations] = blocks;
+  assert.deepEqual([...ways.ids], [10n]);
+  assert.deepEqual(getNodeIds(ways, 0), [1n, 2n, 5n]);
+  assert.deepEqual(getTags(ways, 0), [["highway", "path"]]);
+  assert.deepEqual([...relations.ids], [20n]);
+  assert.deepEqual(getRelationMembers(relations, 0), [
+    { id: 10n, type: "Way", role: "outer" },
+    { id: 1n, type: "Node", role: "label" },
+  ]);
+});
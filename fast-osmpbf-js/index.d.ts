@@ -6,7 +6,7 @@ export declare class AsyncBlockIterator {
 
 export declare class OsmReader {
   constructor(path: string)
-  streamBlocks(elementFilter?: JsElementFilter | undefined | null, tagFilter?: Array<string> | undefined | null): AsyncBlockIterator
+  streamBlocks(elementFilter?: JsElementFilter | undefined | null, tagFilter?: Array<string> | undefined | null, rawStringTable?: boolean | undefined | null): AsyncBlockIterator
 }
 
 export interface JsElementBlock {
@@ -19,6 +19,8 @@ export interface JsElementBlock {
   denseTags?: [Uint32Array, Uint32Array, Uint32Array]
   tags?: [Uint32Array, Uint32Array, Uint32Array]
   stringTable: Array<string>
+  /** (bytes, offsets) of the string table in raw mode, `string_table` is empty then */
+  stringTableBytes?: [Uint8Array, Uint32Array]
 }
 
 export interface JsElementFilter {
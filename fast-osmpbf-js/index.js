@@ -1,5 +1,6 @@
 import { createRequire } from "module";
 import { platform, arch } from "process";
+import { existsSync } from "fs";
 import { join, dirname } from "path";
 import { fileURLToPath } from "url";
 
@@ -41,18 +42,30 @@ function getBindingPath() {
     throw new Error(`No binding available for ${platform}-${arch}`);
   }
 
-  return join(__dirname, bindingFolder, nodeFile);
+  const bindingPath = join(__dirname, bindingFolder, nodeFile);
+  // a local `npm run build` leaves the binding next to this file
+  return existsSync(bindingPath) ? bindingPath : join(__dirname, nodeFile);
 }
 
 const native = require(getBindingPath());
 
+const decoder = new TextDecoder();
+
+export function getString(block, id) {
+  if (!block.stringTableBytes) {
+    return block.stringTable[id];
+  }
+  const [bytes, offsets] = block.stringTableBytes;
+  return decoder.decode(bytes.subarray(offsets[id], offsets[id + 1]));
+}
+
 export function getTags(block, index) {
   let tags = block.denseTags || block.tags;
   const start = tags[2][index];
   const end = tags[2][index + 1];
   const result = [];
   for (let i = start; i < end; i++) {
-    result.push([block.stringTable[tags[0][i]], block.stringTable[tags[1][i]]]);
+    result.push([getString(block, tags[0][i]), getString(block, tags[1][i])]);
   }
   return result;
 }
@@ -81,7 +94,7 @@ export function getRelationMembers(block, index) {
     result.push({
       id: block.relationMembers[0][i],
       type: mapMemberType(block.relationMembers[1][i]),
-      role: block.stringTable[block.relationMembers[2][i]],
+      role: getString(block, block.relationMembers[2][i]),
     });
   }
   return result;
@@ -2,8 +2,31 @@ use fast_osmpbf::ElementBlock;
 use napi::bindgen_prelude::*;
 use napi_derive::napi;
 
-pub fn construct_js_block(block: ElementBlock) -> JsElementBlock {
-    let js_block = match block {
+pub fn construct_js_block(block: ElementBlock, raw_string_table: bool) -> JsElementBlock {
+    let (string_table, string_table_bytes) = match &block {
+        ElementBlock::DenseNodeBlock(block) => string_table(
+            raw_string_table,
+            || block.get_string_table(),
+            || block.get_raw_string_table(),
+        ),
+        ElementBlock::NodeBlock(block) => string_table(
+            raw_string_table,
+            || block.get_string_table(),
+            || block.get_raw_string_table(),
+        ),
+        ElementBlock::WayBlock(block) => string_table(
+            raw_string_table,
+            || block.get_string_table(),
+            || block.get_raw_string_table(),
+        ),
+        ElementBlock::RelationBlock(block) => string_table(
+            raw_string_table,
+            || block.get_string_table(),
+            || block.get_raw_string_table(),
+        ),
+    };
+
+    match block {
         ElementBlock::DenseNodeBlock(block) => {
             let (ids, latitudes, longitudes, tag_key_ids, tag_val_ids, tag_kv_offsets) =
                 block.get_raw_data();
@@ -20,7 +43,8 @@ pub fn construct_js_block(block: ElementBlock) -> JsElementBlock {
                 latitudes: Some(latitudes.into()),
                 longitudes: Some(longitudes.into()),
                 relation_members: None,
-                string_table: block.get_string_table(),
+                string_table,
+                string_table_bytes,
             }
         }
         ElementBlock::NodeBlock(block) => {
@@ -39,7 +63,8 @@ pub fn construct_js_block(block: ElementBlock) -> JsElementBlock {
                 latitudes: Some(latitudes.into()),
                 longitudes: Some(longitudes.into()),
                 relation_members: None,
-                string_table: block.get_string_table(),
+                string_table,
+                string_table_bytes,
             }
         }
         ElementBlock::WayBlock(block) => {
@@ -58,7 +83,8 @@ pub fn construct_js_block(block: ElementBlock) -> JsElementBlock {
                 latitudes: None,
                 longitudes: None,
                 relation_members: None,
-                string_table: block.get_string_table(),
+                string_table,
+                string_table_bytes,
             }
         }
         ElementBlock::RelationBlock(block) => {
@@ -90,12 +116,25 @@ pub fn construct_js_block(block: ElementBlock) -> JsElementBlock {
                     member_roles.into(),
                     member_offsets.into(),
                 )),
-                string_table: block.get_string_table(),
+                string_table,
+                string_table_bytes,
             }
         }
-    };
+    }
+}
 
-    js_block
+// Either the string table as strings or, in raw mode, as concatenated bytes plus offsets
+fn string_table(
+    raw_string_table: bool,
+    strings: impl FnOnce() -> Vec<String>,
+    raw: impl FnOnce() -> (Vec<u8>, Vec<u32>),
+) -> (Vec<String>, Option<(Uint8Array, Uint32Array)>) {
+    if raw_string_table {
+        let (bytes, offsets) = raw();
+        (Vec::new(), Some((bytes.into(), offsets.into())))
+    } else {
+        (strings(), None)
+    }
 }
 
 #[napi(object)]
@@ -109,4 +148,6 @@ pub struct JsElementBlock {
     pub dense_tags: Option<(Uint32Array, Uint32Array, Uint32Array)>,
     pub tags: Option<(Uint32Array, Uint32Array, Uint32Array)>,
     pub string_table: Vec<String>,
+    /// (bytes, offsets) of the string table in raw mode, `string_table` is empty then
+    pub string_table_bytes: Option<(Uint8Array, Uint32Array)>,
 }
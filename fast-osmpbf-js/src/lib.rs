@@ -31,7 +31,9 @@ impl OsmReader {
         &self,
         element_filter: Option<JsElementFilter>,
         tag_filter: Option<Vec<String>>,
+        raw_string_table: Option<bool>,
     ) -> AsyncBlockIterator {
+        let raw_string_table = raw_string_table.unwrap_or(false);
         let (tx, rx) = tokio::sync::mpsc::channel::<JsElementBlock>(MAX_BLOCK_THROUGHPUT);
 
         let path = self.path.clone();
@@ -39,9 +41,7 @@ impl OsmReader {
             let reader = fast_osmpbf::OsmReader::from_path(&path).expect("Failed to open file");
             if let Some(filter) = tag_filter {
                 reader
-                    .apply_tag_filter(
-                        &(filter.iter().map(|tag| tag.as_str()).collect::<Vec<&str>>()),
-                    )
+                    .apply_tag_filter(&filter)
                     .expect("Invalid tag filter");
             }
             if let Some(filter) = element_filter {
@@ -55,10 +55,8 @@ impl OsmReader {
             }
 
             reader.par_blocks().for_each(|block| {
-                let block = construct_js_block(block);
-                if tx.blocking_send(block).is_err() {
-                    return;
-                }
+                let block = construct_js_block(block, raw_string_table);
+                let _ = tx.blocking_send(block);
             })
         });
 
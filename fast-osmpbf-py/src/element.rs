@@ -0,0 +1,166 @@
+use fast_osmpbf::ElementBlock;
+use numpy::PyArray1;
+use pyo3::prelude::*;
+use pyo3::types::PyTuple;
+
+// (key_ids, val_ids, kv_offsets)
+type TagColumns = (Vec<u32>, Vec<u32>, Vec<u32>);
+// (member_ids, member_types, member_roles, member_offsets)
+type MemberColumns = (Vec<i64>, Vec<u8>, Vec<i32>, Vec<u32>);
+
+/// Columns of a block, decoded off the GIL by the rayon workers
+pub struct RawElementBlock {
+    element_type: &'static str,
+    ids: Vec<i64>,
+    node_ids: Option<(Vec<i64>, Vec<u32>)>,
+    latitudes: Option<Vec<f64>>,
+    longitudes: Option<Vec<f64>>,
+    relation_members: Option<MemberColumns>,
+    dense_tags: Option<TagColumns>,
+    tags: Option<TagColumns>,
+    string_table: Vec<String>,
+}
+
+pub fn construct_raw_block(block: ElementBlock) -> RawElementBlock {
+    match block {
+        ElementBlock::DenseNodeBlock(block) => {
+            let (ids, latitudes, longitudes, tag_key_ids, tag_val_ids, tag_kv_offsets) =
+                block.get_raw_data();
+            RawElementBlock {
+                element_type: "Node",
+                ids,
+                dense_tags: Some((tag_key_ids, tag_val_ids, tag_kv_offsets)),
+                tags: None,
+                node_ids: None,
+                latitudes: Some(latitudes),
+                longitudes: Some(longitudes),
+                relation_members: None,
+                string_table: block.get_string_table(),
+            }
+        }
+        ElementBlock::NodeBlock(block) => {
+            let (ids, latitudes, longitudes, tag_key_ids, tag_val_ids, tag_kv_offsets) =
+                block.get_raw_data();
+            RawElementBlock {
+                element_type: "Node",
+                ids,
+                dense_tags: None,
+                tags: Some((tag_key_ids, tag_val_ids, tag_kv_offsets)),
+                node_ids: None,
+                latitudes: Some(latitudes),
+                longitudes: Some(longitudes),
+                relation_members: None,
+                string_table: block.get_string_table(),
+            }
+        }
+        ElementBlock::WayBlock(block) => {
+            let (ids, tag_key_ids, tag_val_ids, tag_kv_offsets, node_ids, node_id_offsets) =
+                block.get_raw_data();
+            RawElementBlock {
+                element_type: "Way",
+                ids,
+                dense_tags: None,
+                tags: Some((tag_key_ids, tag_val_ids, tag_kv_offsets)),
+                node_ids: Some((node_ids, node_id_offsets)),
+                latitudes: None,
+                longitudes: None,
+                relation_members: None,
+                string_table: block.get_string_table(),
+            }
+        }
+        ElementBlock::RelationBlock(block) => {
+            let (
+                ids,
+                tag_key_ids,
+                tag_val_ids,
+                tag_kv_offsets,
+                member_ids,
+                member_types,
+                member_roles,
+                member_offsets,
+            ) = block.get_raw_data();
+            RawElementBlock {
+                element_type: "Relation",
+                ids,
+                dense_tags: None,
+                tags: Some((tag_key_ids, tag_val_ids, tag_kv_offsets)),
+                node_ids: None,
+                latitudes: None,
+                longitudes: None,
+                relation_members: Some((member_ids, member_types, member_roles, member_offsets)),
+                string_table: block.get_string_table(),
+            }
+        }
+    }
+}
+
+/// Moves the columns of a raw block into NumPy arrays without copying
+pub fn construct_py_block(py: Python<'_>, block: RawElementBlock) -> PyResult<PyElementBlock> {
+    let array_tuple3 = |(a, b, c): TagColumns| {
+        let tuple = (
+            PyArray1::from_vec(py, a),
+            PyArray1::from_vec(py, b),
+            PyArray1::from_vec(py, c),
+        );
+        tuple.into_pyobject(py).map(Bound::unbind)
+    };
+
+    Ok(PyElementBlock {
+        element_type: block.element_type,
+        ids: PyArray1::from_vec(py, block.ids).unbind(),
+        node_ids: block
+            .node_ids
+            .map(|(ids, offsets)| {
+                let tuple = (PyArray1::from_vec(py, ids), PyArray1::from_vec(py, offsets));
+                tuple.into_pyobject(py).map(Bound::unbind)
+            })
+            .transpose()?,
+        latitudes: block
+            .latitudes
+            .map(|lats| PyArray1::from_vec(py, lats).unbind()),
+        longitudes: block
+            .longitudes
+            .map(|lons| PyArray1::from_vec(py, lons).unbind()),
+        relation_members: block
+            .relation_members
+            .map(|(ids, types, roles, offsets)| {
+                let tuple = (
+                    PyArray1::from_vec(py, ids),
+                    PyArray1::from_vec(py, types),
+                    PyArray1::from_vec(py, roles),
+                    PyArray1::from_vec(py, offsets),
+                );
+                tuple.into_pyobject(py).map(Bound::unbind)
+            })
+            .transpose()?,
+        dense_tags: block.dense_tags.map(array_tuple3).transpose()?,
+        tags: block.tags.map(array_tuple3).transpose()?,
+        string_table: block.string_table,
+    })
+}
+
+#[pyclass(module = "fast_osmpbf_py", name = "ElementBlock", frozen)]
+pub struct PyElementBlock {
+    #[pyo3(get)]
+    pub element_type: &'static str,
+    #[pyo3(get)]
+    pub ids: Py<PyArray1<i64>>,
+    /// (node_ids, node_id_offsets)
+    #[pyo3(get)]
+    pub node_ids: Option<Py<PyTuple>>,
+    #[pyo3(get)]
+    pub latitudes: Option<Py<PyArray1<f64>>>,
+    #[pyo3(get)]
+    pub longitudes: Option<Py<PyArray1<f64>>>,
+    /// (member_ids, member_types, member_roles, member_offsets)
+    #[pyo3(get)]
+    pub relation_members: Option<Py<PyTuple>>,
+    /// (key_ids, val_ids, kv_offsets)
+    #[pyo3(get)]
+    pub dense_tags: Option<Py<PyTuple>>,
+    /// (key_ids, val_ids, kv_offsets)
+    #[pyo3(get)]
+    pub tags: Option<Py<PyTuple>>,
+    #[pyo3(get)]
+    pub string_table: Vec<String>,
+}
@@ -0,0 +1,105 @@
+use crate::element::{PyElementBlock, RawElementBlock, construct_py_block, construct_raw_block};
+use fast_osmpbf::{ElementFilter, prelude::*};
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+use std::thread;
+pub mod element;
+
+const MAX_BLOCK_THROUGHPUT: usize = 64;
+
+#[pyclass(module = "fast_osmpbf_py")]
+pub struct OsmReader {
+    path: String,
+}
+
+#[pyclass(module = "fast_osmpbf_py", name = "ElementFilter", from_py_object)]
+#[derive(Clone)]
+pub struct PyElementFilter {
+    #[pyo3(get, set)]
+    pub nodes: bool,
+    #[pyo3(get, set)]
+    pub ways: bool,
+    #[pyo3(get, set)]
+    pub relations: bool,
+}
+
+#[pymethods]
+impl PyElementFilter {
+    #[new]
+    #[pyo3(signature = (nodes = true, ways = true, relations = true))]
+    pub fn new(nodes: bool, ways: bool, relations: bool) -> Self {
+        Self {
+            nodes,
+            ways,
+            relations,
+        }
+    }
+}
+
+#[pymethods]
+impl OsmReader {
+    #[new]
+    pub fn new(path: String) -> Self {
+        Self { path }
+    }
+    #[pyo3(signature = (element_filter = None, tag_filter = None))]
+    pub fn stream_blocks(
+        &self,
+        element_filter: Option<PyElementFilter>,
+        tag_filter: Option<Vec<String>>,
+    ) -> PyResult<BlockIterator> {
+        let reader = fast_osmpbf::OsmReader::from_path(&self.path)
+            .map_err(|err| PyIOError::new_err(err.to_string()))?;
+        if let Some(filter) = tag_filter {
+            reader
+                .apply_tag_filter(&filter)
+                .map_err(PyValueError::new_err)?;
+        }
+        if let Some(filter) = element_filter {
+            reader
+                .apply_element_filter(ElementFilter {
+                    nodes: filter.nodes,
+                    ways: filter.ways,
+                    relations: filter.relations,
+                })
+                .map_err(PyValueError::new_err)?;
+        }
+
+        let (tx, rx) = bounded::<RawElementBlock>(MAX_BLOCK_THROUGHPUT);
+        thread::spawn(move || {
+            reader.par_blocks().for_each(|block| {
+                let _ = tx.send(construct_raw_block(block));
+            })
+        });
+
+        Ok(BlockIterator { rx })
+    }
+}
+
+#[pyclass(module = "fast_osmpbf_py")]
+pub struct BlockIterator {
+    rx: Receiver<RawElementBlock>,
+}
+
+#[pymethods]
+impl BlockIterator {
+    pub fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+    pub fn __next__(&self, py: Python<'_>) -> PyResult<Option<PyElementBlock>> {
+        // release the GIL while waiting so other Python threads keep running
+        match py.detach(|| self.rx.recv()) {
+            Ok(block) => construct_py_block(py, block).map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+#[pymodule]
+fn fast_osmpbf_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<OsmReader>()?;
+    m.add_class::<PyElementFilter>()?;
+    m.add_class::<BlockIterator>()?;
+    m.add_class::<PyElementBlock>()?;
+    Ok(())
+}
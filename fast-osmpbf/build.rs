@@ -19,8 +19,10 @@ fn main() {
     protos.push(format!("{}/{}", in_dir, "osmdata.proto"));
     protos.push(format!("{}/{}", in_dir, "osmformat.proto"));
 
-    let config = ConfigBuilder::new(&protos, None, Some(&&out_dir), &[in_dir])
-        .expect("could not generate pb-rs config");
+    // Generated code only relies on `alloc`, so it works with and without the `std` feature
+    let config = ConfigBuilder::new(&protos, None, Some(&out_dir), &[in_dir])
+        .expect("could not generate pb-rs config")
+        .nostd(true);
 
     let descriptor = config.build();
     FileDescriptor::run(&descriptor).expect("could not generate proto files");
@@ -1,18 +1,16 @@
 // Count the number of addresses in an .osm.pbf file by using the tag filter.
 
-use fast_osmpbf::prelude::*;
 use fast_osmpbf::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 pub fn main() {
     let arg = std::env::args_os()
         .nth(1)
         .expect("need a *.osm.pbf file as argument");
     let path = std::path::Path::new(&arg);
-    let reader = OsmReader::from_path(path).expect("Invalid file path");
-
-    // apply filter
-    reader
-        .apply_tag_filter(&[
+    let reader = OsmReader::from_path(path)
+        .expect("Invalid file path")
+        .with_tag_filter(&[
             "addr:city",
             "addr:postcode",
             "addr:street",
@@ -20,27 +18,13 @@ pub fn main() {
         ])
         .expect("Invalid filter applied");
 
-    // iterate using .par_blocks() (Parallelization happens on 2 decoding steps)
-    let address_counter: usize = reader
-        .par_blocks()
-        .map(|block| match block {
-            ElementBlock::DenseNodeBlock(block) => block
-                .iter()
-                .filter(|node| node.tags().has_all_filter_keys())
-                .count(),
-            ElementBlock::NodeBlock(block) => block
-                .iter()
-                .filter(|node| node.tags().has_all_filter_keys())
-                .count(),
-            ElementBlock::WayBlock(block) => block
-                .iter()
-                .filter(|way| way.tags().has_all_filter_keys())
-                .count(),
-            ElementBlock::RelationBlock(block) => block
-                .iter()
-                .filter(|rel| rel.tags().has_all_filter_keys())
-                .count(),
-        })
-        .sum();
+    // visit element refs in parallel with .par_for_each_element() (Parallelization happens on 2 decoding steps)
+    let address_counter = AtomicUsize::new(0);
+    reader.par_for_each_element(|element| {
+        if element.has_all_filter_keys() {
+            address_counter.fetch_add(1, Ordering::Relaxed);
+        }
+    });
+    let address_counter = address_counter.into_inner();
     println!("Addresses: {:?}", address_counter);
 }
@@ -7,11 +7,9 @@ fn main() {
         .nth(1)
         .expect("need a *.osm.pbf file as argument");
     let path = std::path::Path::new(&arg);
-    let reader = OsmReader::from_path(path).expect("Invalid file path");
-
-    // apply filter
-    reader
-        .apply_element_filter(ElementFilter {
+    let reader = OsmReader::from_path(path)
+        .expect("Invalid file path")
+        .with_element_filter(ElementFilter {
             nodes: false,
             relations: false,
             ways: true,
@@ -20,13 +18,12 @@ fn main() {
 
     // iterate using .blocks() (Parallelization happens, but only for one decoding step)
     let mut way_counter = 0;
-    reader.blocks().for_each(|block| match block {
-        ElementBlock::WayBlock(block) => {
+    reader.blocks().for_each(|block| {
+        if let Some(block) = block.as_way_block() {
             for _way in block.iter() {
                 way_counter += 1;
             }
         }
-        _ => (),
     });
     println!("Ways: {:?}", way_counter);
 }
@@ -0,0 +1,467 @@
+use crate::{
+    DenseNodeBlock, ElementBlock, ElementBlockIter, ElementFilter, NodeBlock, OsmReader,
+    RelationBlock, WayBlock,
+};
+use arrow_array::{
+    ArrayRef, Float64Array, Int64Array, ListArray, MapArray, RecordBatch, StringArray, StructArray,
+    UInt8Array,
+};
+use arrow_buffer::{OffsetBuffer, ScalarBuffer};
+use arrow_schema::{DataType, Field, FieldRef, Fields, Schema, SchemaRef};
+use std::{iter::FusedIterator, sync::Arc};
+
+/// Schema of the batches of [`DenseNodeBlock::to_record_batch`] and [`NodeBlock::to_record_batch`]:
+/// `id` (Int64), `lat` and `lon` in degrees (Float64) and `tags` (Map of Utf8 to Utf8)
+pub fn node_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("id", DataType::Int64, false),
+        Field::new("lat", DataType::Float64, false),
+        Field::new("lon", DataType::Float64, false),
+        tags_field(),
+    ]))
+}
+
+/// Schema of the batches of [`WayBlock::to_record_batch`]:
+/// `id` (Int64), `tags` (Map of Utf8 to Utf8) and `node_ids` (List of Int64)
+pub fn way_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("id", DataType::Int64, false),
+        tags_field(),
+        Field::new("node_ids", DataType::List(node_id_field()), false),
+    ]))
+}
+
+/// Schema of the batches of [`RelationBlock::to_record_batch`]: `id` (Int64), `tags` (Map of Utf8 to Utf8)
+/// and `members` (List of Struct of `id` (Int64), `type` (UInt8, the value of [`MemberType`](crate::MemberType),
+/// 0 for nodes, 1 for ways and 2 for relations) and `role` (Utf8))
+pub fn relation_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("id", DataType::Int64, false),
+        tags_field(),
+        Field::new("members", DataType::List(member_field()), false),
+    ]))
+}
+
+impl OsmReader {
+    /// Yields an Arrow [`RecordBatch`] of the nodes of every block (dense and plain), e.g. to feed
+    /// DataFusion or Polars. See [`node_schema`] for the columns.
+    /// Without an element filter only nodes are parsed. Like the bulk accessors of the blocks,
+    /// the bbox filter and the predicate only drop whole blocks, a batch holds all nodes of its block.
+    /// Tags are the ones kept by the tag filter. Broken blobs are skipped, see [`RecordBatchIter::finish`].
+    pub fn node_batches(self) -> RecordBatchIter {
+        let _ = self.apply_element_filter(ElementFilter {
+            nodes: true,
+            ways: false,
+            relations: false,
+        });
+        RecordBatchIter {
+            blocks: self.blocks(),
+            convert: |block| match block {
+                ElementBlock::DenseNodeBlock(block) => Some(block.to_record_batch()),
+                ElementBlock::NodeBlock(block) => Some(block.to_record_batch()),
+                _ => None,
+            },
+        }
+    }
+
+    /// Same as [`OsmReader::node_batches`] for ways, see [`way_schema`] for the columns
+    pub fn way_batches(self) -> RecordBatchIter {
+        let _ = self.apply_element_filter(ElementFilter {
+            nodes: false,
+            ways: true,
+            relations: false,
+        });
+        RecordBatchIter {
+            blocks: self.blocks(),
+            convert: |block| match block {
+                ElementBlock::WayBlock(block) => Some(block.to_record_batch()),
+                _ => None,
+            },
+        }
+    }
+
+    /// Same as [`OsmReader::node_batches`] for relations, see [`relation_schema`] for the columns
+    pub fn relation_batches(self) -> RecordBatchIter {
+        let _ = self.apply_element_filter(ElementFilter {
+            nodes: false,
+            ways: false,
+            relations: true,
+        });
+        RecordBatchIter {
+            blocks: self.blocks(),
+            convert: |block| match block {
+                ElementBlock::RelationBlock(block) => Some(block.to_record_batch()),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// Iterator over the [`RecordBatch`]es of [`OsmReader::node_batches`], [`OsmReader::way_batches`]
+/// and [`OsmReader::relation_batches`], converting one block at a time
+pub struct RecordBatchIter {
+    blocks: ElementBlockIter,
+    convert: fn(ElementBlock) -> Option<RecordBatch>,
+}
+
+impl RecordBatchIter {
+    /// Same as [`ElementBlockIter::finish`]: waits for the background threads and returns
+    /// the first error they ran into
+    pub fn finish(self) -> std::io::Result<()> {
+        self.blocks.finish()
+    }
+}
+
+impl Iterator for RecordBatchIter {
+    type Item = RecordBatch;
+
+    fn next(&mut self) -> Option<RecordBatch> {
+        self.blocks.by_ref().find_map(self.convert)
+    }
+}
+
+impl FusedIterator for RecordBatchIter {}
+
+impl DenseNodeBlock {
+    /// Converts all nodes of the block into a [`RecordBatch`] of the [`node_schema`]
+    pub fn to_record_batch(&self) -> RecordBatch {
+        let (ids, lats, lons, key_ids, val_ids, kv_offsets) = self.get_raw_data();
+        let tags = tag_map(&self.table, &key_ids, &val_ids, &kv_offsets);
+        node_batch(ids, lats, lons, tags)
+    }
+}
+
+impl NodeBlock {
+    /// Converts all nodes of the block into a [`RecordBatch`] of the [`node_schema`]
+    pub fn to_record_batch(&self) -> RecordBatch {
+        let (ids, lats, lons, key_ids, val_ids, kv_offsets) = self.get_raw_data();
+        let tags = tag_map(&self.table, &key_ids, &val_ids, &kv_offsets);
+        node_batch(ids, lats, lons, tags)
+    }
+}
+
+impl WayBlock {
+    /// Converts all ways of the block into a [`RecordBatch`] of the [`way_schema`]
+    pub fn to_record_batch(&self) -> RecordBatch {
+        let (ids, key_ids, val_ids, kv_offsets, node_ids, node_offsets) = self.get_raw_data();
+        let tags = tag_map(&self.table, &key_ids, &val_ids, &kv_offsets);
+        let node_ids = ListArray::new(
+            node_id_field(),
+            offsets(&node_offsets),
+            Arc::new(Int64Array::from(node_ids)),
+            None,
+        );
+        let columns: Vec<ArrayRef> =
+            vec![Arc::new(Int64Array::from(ids)), tags, Arc::new(node_ids)];
+        RecordBatch::try_new(way_schema(), columns).expect("the columns match the way schema")
+    }
+}
+
+impl RelationBlock {
+    /// Converts all relations of the block into a [`RecordBatch`] of the [`relation_schema`]
+    pub fn to_record_batch(&self) -> RecordBatch {
+        let (ids, key_ids, val_ids, kv_offsets, member_ids, types, roles, member_offsets) =
+            self.get_raw_data();
+        let tags = tag_map(&self.table, &key_ids, &val_ids, &kv_offsets);
+        let roles: Vec<u32> = roles.into_iter().map(|role| role as u32).collect();
+        let DataType::Struct(member_fields) = member_field().data_type().clone() else {
+            unreachable!("members are structs")
+        };
+        let members = StructArray::new(
+            member_fields,
+            vec![
+                Arc::new(Int64Array::from(member_ids)),
+                Arc::new(UInt8Array::from(types)),
+                Arc::new(strings(&self.table, &roles)),
+            ],
+            None,
+        );
+        let members = ListArray::new(
+            member_field(),
+            offsets(&member_offsets),
+            Arc::new(members),
+            None,
+        );
+        let columns: Vec<ArrayRef> = vec![Arc::new(Int64Array::from(ids)), tags, Arc::new(members)];
+        RecordBatch::try_new(relation_schema(), columns)
+            .expect("the columns match the relation schema")
+    }
+}
+
+fn node_batch(ids: Vec<i64>, lats: Vec<f64>, lons: Vec<f64>, tags: ArrayRef) -> RecordBatch {
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(Int64Array::from(ids)),
+        Arc::new(Float64Array::from(lats)),
+        Arc::new(Float64Array::from(lons)),
+        tags,
+    ];
+    RecordBatch::try_new(node_schema(), columns).expect("the columns match the node schema")
+}
+
+fn tags_field() -> Field {
+    Field::new("tags", DataType::Map(tag_entries_field(), false), false)
+}
+
+fn tag_entries_field() -> FieldRef {
+    Arc::new(Field::new(
+        "entries",
+        DataType::Struct(tag_entry_fields()),
+        false,
+    ))
+}
+
+fn tag_entry_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("keys", DataType::Utf8, false),
+        Field::new("values", DataType::Utf8, false),
+    ])
+}
+
+fn node_id_field() -> FieldRef {
+    Arc::new(Field::new("item", DataType::Int64, false))
+}
+
+fn member_field() -> FieldRef {
+    let fields = Fields::from(vec![
+        Field::new("id", DataType::Int64, false),
+        Field::new("type", DataType::UInt8, false),
+        Field::new("role", DataType::Utf8, false),
+    ]);
+    Arc::new(Field::new("item", DataType::Struct(fields), false))
+}
+
+// The tags of every element as a map, kv_offsets holds where the tags of every element start and the end
+fn tag_map(table: &[Arc<[u8]>], key_ids: &[u32], val_ids: &[u32], kv_offsets: &[u32]) -> ArrayRef {
+    let entries = StructArray::new(
+        tag_entry_fields(),
+        vec![
+            Arc::new(strings(table, key_ids)),
+            Arc::new(strings(table, val_ids)),
+        ],
+        None,
+    );
+    Arc::new(MapArray::new(
+        tag_entries_field(),
+        offsets(kv_offsets),
+        entries,
+        None,
+        false,
+    ))
+}
+
+// Looks up string ids in the string table, invalid UTF-8 is replaced
+fn strings(table: &[Arc<[u8]>], ids: &[u32]) -> StringArray {
+    StringArray::from_iter_values(ids.iter().map(|&id| {
+        let bytes = table.get(id as usize).map_or(&[][..], |s| &s[..]);
+        String::from_utf8_lossy(bytes)
+    }))
+}
+
+fn offsets(offsets: &[u32]) -> OffsetBuffer<i32> {
+    let offsets: Vec<i32> = offsets.iter().map(|&offset| offset as i32).collect();
+    OffsetBuffer::new(ScalarBuffer::from(offsets))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        decode_blob, DenseNodes, MemberType, Node, PrimitiveBlock, PrimitiveGroup, Relation,
+        StringTable, Way,
+    };
+    use arrow_array::{
+        cast::AsArray,
+        types::{Float64Type, Int64Type, UInt8Type},
+        Array,
+    };
+    use quick_protobuf::{MessageWrite, Writer};
+    use std::borrow::Cow;
+
+    const STRINGS: [&str; 10] = [
+        "",
+        "amenity",
+        "cafe",
+        "name",
+        "A",
+        "highway",
+        "path",
+        "type",
+        "multipolygon",
+        "outer",
+    ];
+
+    // Encodes a PrimitiveBlock holding the group and parses its only block
+    fn decode(group: PrimitiveGroup) -> ElementBlock {
+        let block = PrimitiveBlock {
+            stringtable: StringTable {
+                s: STRINGS
+                    .iter()
+                    .map(|s| Cow::Borrowed(s.as_bytes()))
+                    .collect(),
+            },
+            primitivegroup: vec![group],
+            granularity: 100,
+            date_granularity: 1000,
+            ..PrimitiveBlock::default()
+        };
+        let mut bytes = Vec::new();
+        block.write_message(&mut Writer::new(&mut bytes)).unwrap();
+        let mut blocks = decode_blob(&bytes).unwrap();
+        assert_eq!(blocks.len(), 1);
+        blocks.pop().unwrap()
+    }
+
+    // Node 1 at (52.5, 13.25) tagged amenity=cafe and name=A, node 2 at (-33.5, -70.75) without tags
+    fn dense_nodes() -> DenseNodeBlock {
+        let group = PrimitiveGroup {
+            dense: Some(DenseNodes {
+                id: vec![1, 1],
+                lat: vec![525_000_000, -860_000_000],
+                lon: vec![132_500_000, -840_000_000],
+                keys_vals: vec![1, 2, 3, 4, 0, 0],
+                ..DenseNodes::default()
+            }),
+            ..PrimitiveGroup::default()
+        };
+        match decode(group) {
+            ElementBlock::DenseNodeBlock(block) => block,
+            block => panic!("{block:?}"),
+        }
+    }
+
+    fn plain_nodes() -> NodeBlock {
+        let group = PrimitiveGroup {
+            nodes: vec![
+                Node {
+                    id: 1,
+                    keys: vec![1, 3],
+                    vals: vec![2, 4],
+                    ..Node::default()
+                },
+                Node {
+                    id: 2,
+                    ..Node::default()
+                },
+            ],
+            ..PrimitiveGroup::default()
+        };
+        match decode(group) {
+            ElementBlock::NodeBlock(block) => block,
+            block => panic!("{block:?}"),
+        }
+    }
+
+    fn ids(batch: &RecordBatch) -> Vec<i64> {
+        batch
+            .column(0)
+            .as_primitive::<Int64Type>()
+            .values()
+            .to_vec()
+    }
+
+    // The (key, value) pairs of every row of the tags column
+    fn tags(batch: &RecordBatch) -> Vec<Vec<(String, String)>> {
+        let tags = batch.column_by_name("tags").unwrap().as_map();
+        (0..tags.len())
+            .map(|row| {
+                let entries = tags.value(row);
+                let keys = entries.column(0).as_string::<i32>();
+                let values = entries.column(1).as_string::<i32>();
+                keys.iter()
+                    .zip(values.iter())
+                    .map(|(k, v)| (k.unwrap().to_owned(), v.unwrap().to_owned()))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn dense_node_batches_have_ids_coordinates_and_tags() {
+        let batch = dense_nodes().to_record_batch();
+        assert_eq!(batch.schema(), node_schema());
+        assert_eq!(ids(&batch), [1, 2]);
+        let lat = batch.column(1).as_primitive::<Float64Type>();
+        let lon = batch.column(2).as_primitive::<Float64Type>();
+        assert_eq!(lat.values().to_vec(), [52.5, -33.5]);
+        assert_eq!(lon.values().to_vec(), [13.25, -70.75]);
+        assert_eq!(
+            tags(&batch),
+            [pairs(&[("amenity", "cafe"), ("name", "A")]), vec![]]
+        );
+    }
+
+    #[test]
+    fn plain_node_batches_have_ids_and_tags() {
+        let batch = plain_nodes().to_record_batch();
+        assert_eq!(batch.schema(), node_schema());
+        assert_eq!(ids(&batch), [1, 2]);
+        assert_eq!(
+            tags(&batch),
+            [pairs(&[("amenity", "cafe"), ("name", "A")]), vec![]]
+        );
+    }
+
+    #[test]
+    fn way_batches_have_node_ids() {
+        let group = PrimitiveGroup {
+            ways: vec![Way {
+                id: 10,
+                keys: vec![5],
+                vals: vec![6],
+                refs: vec![1, 1],
+                ..Way::default()
+            }],
+            ..PrimitiveGroup::default()
+        };
+        let ElementBlock::WayBlock(block) = decode(group) else {
+            panic!("not a way block")
+        };
+        let batch = block.to_record_batch();
+        assert_eq!(batch.schema(), way_schema());
+        assert_eq!(ids(&batch), [10]);
+        assert_eq!(tags(&batch), [pairs(&[("highway", "path")])]);
+        let node_ids = batch.column(2).as_list::<i32>().value(0);
+        assert_eq!(
+            node_ids.as_primitive::<Int64Type>().values().to_vec(),
+            [1, 2]
+        );
+    }
+
+    #[test]
+    fn relation_batches_have_members() {
+        let group = PrimitiveGroup {
+            relations: vec![Relation {
+                id: 20,
+                keys: vec![7],
+                vals: vec![8],
+                roles_sid: vec![9, 0],
+                memids: vec![10, -9],
+                types: vec![MemberType::WAY, MemberType::NODE],
+                ..Relation::default()
+            }],
+            ..PrimitiveGroup::default()
+        };
+        let ElementBlock::RelationBlock(block) = decode(group) else {
+            panic!("not a relation block")
+        };
+        let batch = block.to_record_batch();
+        assert_eq!(batch.schema(), relation_schema());
+        assert_eq!(ids(&batch), [20]);
+        assert_eq!(tags(&batch), [pairs(&[("type", "multipolygon")])]);
+        let members = batch.column(2).as_list::<i32>().value(0);
+        let members = members.as_struct();
+        let member_ids = members.column(0).as_primitive::<Int64Type>();
+        let types = members.column(1).as_primitive::<UInt8Type>();
+        let roles = members.column(2).as_string::<i32>();
+        assert_eq!(member_ids.values().to_vec(), [10, 1]);
+        assert_eq!(types.values().to_vec(), [1, 0]);
+        assert_eq!(roles.iter().flatten().collect::<Vec<_>>(), ["outer", ""]);
+    }
+}
@@ -0,0 +1,148 @@
+use crate::{
+    parser::{OsmParser, ParseOptions},
+    reader::{BlobType, MAX_HEADER_SIZE},
+    BlobDecompressor, DefaultDecompressor, ElementBlock, OsmError, OsmHeader, OsmReader,
+};
+use std::{future::poll_fn, io::ErrorKind, pin::Pin, sync::Arc};
+use tokio::io::{AsyncRead, ReadBuf};
+
+/// Async counterpart of [`OsmReader`] that reads blobs from any [`AsyncRead`],
+/// e.g. a `tokio::fs::File` or a socket, without dedicating a blocking thread to the I/O.
+///
+/// The work is split by its kind: blobs are read on the calling task with async I/O,
+/// while decompressing and parsing a blob is CPU-bound and runs on the blocking pool of tokio
+/// via [`spawn_blocking`](tokio::task::spawn_blocking), where the groups of a blob are parsed
+/// in parallel with rayon like in [`OsmReader::blocks`]. A blocking thread is only held while a
+/// blob is decoded, never while waiting for data. Blobs are decoded one at a time in file order,
+/// filters of the sync reader are not available.
+pub struct AsyncOsmReader<R> {
+    reader: R,
+    header: Vec<u8>,
+    blob: Vec<u8>,
+    // decompressed blob, moved to the blocking pool and back so it is reused
+    decompressed: Vec<u8>,
+    osm_header: Option<OsmHeader>,
+    // the first data blob if the file does not start with an OSMHeader
+    pending: Option<Arc<[u8]>>,
+    // byte offset of the next blob in the stream
+    offset: u64,
+    decompressor: Arc<dyn BlobDecompressor>,
+}
+
+impl<R: AsyncRead + Unpin> AsyncOsmReader<R> {
+    /// Creates a reader and reads the OSMHeader, if the stream starts with one
+    pub async fn new(reader: R) -> std::io::Result<Self> {
+        Self::new_with_decompressor(reader, DefaultDecompressor).await
+    }
+
+    /// Same as [`AsyncOsmReader::new`], but decompresses blobs with a custom [`BlobDecompressor`]
+    pub async fn new_with_decompressor<D>(reader: R, decompressor: D) -> std::io::Result<Self>
+    where
+        D: BlobDecompressor + 'static,
+    {
+        let mut reader = Self {
+            reader,
+            header: vec![0; MAX_HEADER_SIZE],
+            blob: Vec::new(),
+            decompressed: Vec::new(),
+            osm_header: None,
+            pending: None,
+            offset: 0,
+            decompressor: Arc::new(decompressor),
+        };
+        // without seeking, a first blob that is no OSMHeader is kept for next_blocks
+        match reader.next_any_blob().await? {
+            Some((BlobType::Header, blob)) => {
+                let header = OsmParser::decompress_blob(&blob, &*reader.decompressor)?;
+                let header = OsmHeader::from_bytes(&header)
+                    .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e))?;
+                reader.osm_header = Some(header);
+            }
+            Some((BlobType::Data, blob)) => reader.pending = Some(blob),
+            _ => (),
+        }
+        Ok(reader)
+    }
+
+    /// Get the OSMHeader of the stream, if it starts with one
+    pub fn header(&self) -> Option<&OsmHeader> {
+        self.osm_header.as_ref()
+    }
+
+    /// Reads the next data blob and decodes it on the blocking pool, [`None`] at the end of the stream.
+    /// The blocks of a blob are ordered dense nodes, nodes, ways, relations.
+    /// A stream ending inside of a blob yields an [`OsmError::Truncated`].
+    pub async fn next_blocks(&mut self) -> std::io::Result<Option<Vec<ElementBlock>>> {
+        let blob = match self.pending.take() {
+            Some(blob) => blob,
+            None => loop {
+                match self.next_any_blob().await? {
+                    Some((BlobType::Data, blob)) => break blob,
+                    Some(_) => continue, // not relevant data
+                    None => return Ok(None),
+                }
+            },
+        };
+
+        let decompressor = Arc::clone(&self.decompressor);
+        let mut decompressed = std::mem::take(&mut self.decompressed);
+        let (blocks, decompressed) = tokio::task::spawn_blocking(move || {
+            let options = ParseOptions::default();
+            let blocks =
+                OsmParser::deserialize_blob(&blob, &*decompressor, &options, &mut decompressed);
+            (blocks, decompressed)
+        })
+        .await
+        .map_err(std::io::Error::other)?;
+        self.decompressed = decompressed;
+        blocks.map(Some)
+    }
+
+    // Reads the length prefix, BlobHeader and blob of any type
+    async fn next_any_blob(&mut self) -> std::io::Result<Option<(BlobType, Arc<[u8]>)>> {
+        let offset = self.offset;
+        let mut prefix = [0u8; 4];
+        // the stream may only end right before a length prefix
+        match read_until_eof(&mut self.reader, &mut prefix).await? {
+            0 => return Ok(None),
+            4 => (),
+            _ => return Err(OsmError::Truncated { offset }.into()),
+        }
+        let header_size = u32::from_be_bytes(prefix) as usize;
+        if header_size > MAX_HEADER_SIZE {
+            let size = header_size;
+            return Err(OsmError::HeaderTooLarge { offset, size }.into());
+        }
+        if read_until_eof(&mut self.reader, &mut self.header[..header_size]).await? < header_size {
+            return Err(OsmError::Truncated { offset }.into());
+        }
+        let header = OsmReader::parse_blob_header(&self.header[..header_size])?;
+        let (blob_type, blob_size) = (BlobType::of(&header), header.datasize as usize);
+
+        if self.blob.len() < blob_size {
+            self.blob.resize(blob_size, 0);
+        }
+        if read_until_eof(&mut self.reader, &mut self.blob[..blob_size]).await? < blob_size {
+            return Err(OsmError::Truncated { offset }.into());
+        }
+        self.offset += 4 + header_size as u64 + blob_size as u64;
+        Ok(Some((blob_type, Arc::from(&self.blob[..blob_size]))))
+    }
+}
+
+// Fills buf, returns how many bytes were read before reaching EOF
+async fn read_until_eof<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    buf: &mut [u8],
+) -> std::io::Result<usize> {
+    let mut read = 0;
+    while read < buf.len() {
+        let mut read_buf = ReadBuf::new(&mut buf[read..]);
+        poll_fn(|cx| Pin::new(&mut *reader).poll_read(cx, &mut read_buf)).await?;
+        match read_buf.filled().len() {
+            0 => break,
+            n => read += n,
+        }
+    }
+    Ok(read)
+}
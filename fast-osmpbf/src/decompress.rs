@@ -0,0 +1,104 @@
+use crate::{Blob, OsmError};
+use std::io::Read;
+
+/// Decompresses the data of a [`Blob`].
+/// Pass your own implementation to [`OsmReader::from_path_with_decompressor`](crate::OsmReader::from_path_with_decompressor)
+/// to plug in codecs the crate does not ship (e.g. zstd) or faster implementations of the built-in ones.
+pub trait BlobDecompressor: Send + Sync + std::fmt::Debug {
+    /// Appends the decompressed bytes of `blob` to `out`.
+    /// `out` already has capacity for `blob.raw_size` bytes if the file provides it.
+    fn decompress(&self, blob: &Blob<'_>, out: &mut Vec<u8>) -> std::io::Result<()>;
+}
+
+/// The built-in codecs: uncompressed, zlib and lzma.
+/// Wrap it in your own [`BlobDecompressor`] to only replace some codecs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultDecompressor;
+
+impl BlobDecompressor for DefaultDecompressor {
+    fn decompress(&self, blob: &Blob<'_>, out: &mut Vec<u8>) -> std::io::Result<()> {
+        if let Some(raw) = &blob.raw {
+            out.extend_from_slice(raw);
+        } else if let Some(zlib) = &blob.zlib_data {
+            let mut decoder = flate2::read::ZlibDecoder::new(&zlib[..]);
+            decoder.read_to_end(out)?;
+        } else if let Some(lzma) = &blob.lzma_data {
+            let mut decoder = xz2::read::XzDecoder::new(&lzma[..]);
+            decoder.read_to_end(out)?;
+        } else if let Some(codec) = unsupported_codec(blob) {
+            return Err(OsmError::UnsupportedCompression { codec }.into());
+        } else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "Empty OSMData blob",
+            ));
+        }
+        Ok(())
+    }
+}
+
+// Name of the codec of a blob without built-in support
+fn unsupported_codec(blob: &Blob<'_>) -> Option<&'static str> {
+    [
+        ("lz4", &blob.lz4_data),
+        ("zstd", &blob.zstd_data),
+        ("bzip2", &blob.bzip2_data),
+    ]
+    .into_iter()
+    .find_map(|(codec, data)| data.is_some().then_some(codec))
+}
+
+/// Compression codec of a [`Blob`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Compression {
+    /// Stored uncompressed
+    Raw,
+    /// ZLIB
+    Zlib,
+    /// LZMA
+    Lzma,
+    /// LZ4
+    Lz4,
+    /// ZSTD
+    Zstd,
+    /// BZIP2, obsolete
+    Bzip2,
+}
+
+impl Compression {
+    /// Get the codec of a blob and the length of its (compressed) data, [`None`] if the blob is empty
+    pub fn of(blob: &Blob<'_>) -> Option<(Self, usize)> {
+        [
+            (Compression::Raw, &blob.raw),
+            (Compression::Zlib, &blob.zlib_data),
+            (Compression::Lzma, &blob.lzma_data),
+            (Compression::Lz4, &blob.lz4_data),
+            (Compression::Zstd, &blob.zstd_data),
+            (Compression::Bzip2, &blob.bzip2_data),
+        ]
+        .into_iter()
+        .find_map(|(compression, data)| data.as_ref().map(|data| (compression, data.len())))
+    }
+}
+
+/// Size and codec of one decompressed blob, see [`OsmReader::with_blob_stats`](crate::OsmReader::with_blob_stats)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlobStats {
+    /// Byte offset of the blob in the file
+    pub offset: u64,
+    /// Codec of the blob
+    pub compression: Compression,
+    /// Length of the stored data
+    pub compressed_len: usize,
+    /// Size after decompression as announced by the file, if it does
+    pub raw_size: Option<usize>,
+    /// Actual size after decompression
+    pub decompressed_len: usize,
+}
+
+impl BlobStats {
+    /// Get the ratio of decompressed to compressed size (1.0 for raw blobs)
+    pub fn compression_ratio(&self) -> f64 {
+        self.decompressed_len as f64 / self.compressed_len.max(1) as f64
+    }
+}
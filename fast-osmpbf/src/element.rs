@@ -1,14 +1,156 @@
-use crate::{DenseNodes, MemberType, Node, Relation, Way};
+use crate::info::DenseInfoState;
+use crate::owned::owned_tags;
+#[cfg(feature = "std")]
+use crate::reader::{InFlightBudget, SkippedBlobs};
+use crate::simd;
+#[cfg(feature = "stats")]
+use crate::stats::{ParseStats, StatsCollector};
+#[cfg(feature = "std")]
+use crate::Compression;
+use crate::{
+    DenseNodes, Element, ElementInfo, ElementMeta, LazyMember, MemberType, Node, OsmError,
+    OwnedNode, OwnedRelation, OwnedRelationMember, OwnedWay, Relation, ResolvedMember, Way,
+};
+use alloc::{
+    borrow::{Cow, ToOwned},
+    string::String,
+    sync::Arc,
+    vec::Vec,
+};
+use core::{fmt, iter::FusedIterator, ops::Range};
+#[cfg(feature = "std")]
 use crossbeam_channel::Receiver;
-use std::sync::OnceLock;
-use std::{borrow::Cow, sync::Arc};
-
-pub(crate) static TAG_KEYS_FILTER: OnceLock<Box<[&'static str]>> = OnceLock::new();
-pub(crate) static TAG_KEYS_FILTER_COUNT: OnceLock<usize> = OnceLock::new();
+#[cfg(feature = "std")]
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicBool, Ordering},
+    thread::JoinHandle,
+};
+
+// Predicate of OsmReader::with_predicate
+#[cfg(feature = "std")]
+pub(crate) type ElementPredicate = dyn Fn(&ElementRef<'_>) -> bool + Send + Sync;
+
+/// Columns of the nodes of a block returned by `get_raw_data` for the bindings:
+/// ids, latitudes, longitudes, key ids, value ids and the offsets of the tags of every node
+#[cfg(any(
+    feature = "node_bindings",
+    feature = "python_bindings",
+    feature = "arrow"
+))]
+pub type RawNodeData = (Vec<i64>, Vec<f64>, Vec<f64>, Vec<u32>, Vec<u32>, Vec<u32>);
+
+/// Columns of the ways of a block returned by [`WayBlock::get_raw_data`] for the bindings:
+/// ids, key ids, value ids, tag offsets, node ids and the offsets of the node ids of every way
+#[cfg(any(
+    feature = "node_bindings",
+    feature = "python_bindings",
+    feature = "arrow"
+))]
+pub type RawWayData = (Vec<i64>, Vec<u32>, Vec<u32>, Vec<u32>, Vec<i64>, Vec<u32>);
+
+/// Columns of the relations of a block returned by [`RelationBlock::get_raw_data`] for the bindings:
+/// ids, key ids, value ids, tag offsets, member ids, member types, member role ids and the offsets
+/// of the members of every relation
+#[cfg(any(
+    feature = "node_bindings",
+    feature = "python_bindings",
+    feature = "arrow"
+))]
+pub type RawRelationData = (
+    Vec<i64>,
+    Vec<u32>,
+    Vec<u32>,
+    Vec<u32>,
+    Vec<i64>,
+    Vec<u8>,
+    Vec<i32>,
+    Vec<u32>,
+);
+
+// Ids of the tag filter keys in the string table of a block
+#[derive(Debug, Default)]
+pub(crate) struct CachedTagIds {
+    // None if the reader has no tag filter, then every tag is kept
+    pub(crate) ids: Option<Vec<u32>>,
+    pub(crate) key_count: usize,
+}
 
-pub(crate) static ELEMENT_FILTER: OnceLock<ElementFilter> = OnceLock::new();
+impl CachedTagIds {
+    // Check if a tag with this key id passes the tag filter
+    #[inline]
+    pub(crate) fn keeps(&self, key: u32) -> bool {
+        self.ids.as_ref().is_none_or(|ids| ids.contains(&key))
+    }
+    // String table ids of keys among the filter keys, None for a key outside of the filter.
+    // None if the reader has no tag filter, then every string of the table could be a key.
+    fn resolve(&self, table: &[Arc<[u8]>], keys: &[&str]) -> Option<Vec<Option<u32>>> {
+        let ids = self.ids.as_ref()?;
+        let id_of = |key: &str| {
+            ids.iter().copied().find(|&id| {
+                table
+                    .get(id as usize)
+                    .is_some_and(|s| **s == *key.as_bytes())
+            })
+        };
+        Some(keys.iter().map(|key| id_of(key)).collect())
+    }
+    // Appends the tags passing the tag filter, compared in bulk by the SIMD kernel
+    #[cfg(any(
+        feature = "node_bindings",
+        feature = "python_bindings",
+        feature = "arrow"
+    ))]
+    fn extend_kept(
+        &self,
+        keys: &[u32],
+        vals: &[u32],
+        key_ids: &mut Vec<u32>,
+        val_ids: &mut Vec<u32>,
+    ) {
+        match &self.ids {
+            Some(ids) => simd::retain_tags(keys, vals, ids, key_ids, val_ids),
+            None => {
+                let len = keys.len().min(vals.len());
+                key_ids.extend_from_slice(&keys[..len]);
+                val_ids.extend_from_slice(&vals[..len]);
+            }
+        }
+    }
+    // Same as extend_kept for the interleaved keys and values of DenseNodes
+    #[cfg(any(
+        feature = "node_bindings",
+        feature = "python_bindings",
+        feature = "arrow"
+    ))]
+    fn extend_kept_interleaved(
+        &self,
+        keys_vals: &[i32],
+        key_ids: &mut Vec<u32>,
+        val_ids: &mut Vec<u32>,
+    ) {
+        match &self.ids {
+            Some(ids) => simd::retain_tags_interleaved(keys_vals, ids, key_ids, val_ids),
+            None => {
+                for pair in keys_vals.chunks_exact(2) {
+                    key_ids.push(pair[0] as u32);
+                    val_ids.push(pair[1] as u32);
+                }
+            }
+        }
+    }
+    // Number of tags with these key ids passing the tag filter
+    #[inline]
+    fn count_kept(&self, keys: &[u32]) -> usize {
+        match &self.ids {
+            Some(_) => keys.iter().filter(|&&key| self.keeps(key)).count(),
+            None => keys.len(),
+        }
+    }
+}
 
 /// An optional filter you can apply that speeds up computation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ElementFilter {
     /// Whether [`Node`] and [`DenseNodes`] should be parsed
     pub nodes: bool,
@@ -18,11 +160,235 @@ pub struct ElementFilter {
     pub relations: bool,
 }
 
+/// Which elements a tag filter keeps, see
+/// [`OsmReader::apply_tag_filter_with_mode`](crate::OsmReader::apply_tag_filter_with_mode)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FilterMode {
+    /// Keeps elements that have at least one of the filter keys
+    Any,
+    /// Keeps elements that have every filter key (e.g. all elements with a full address),
+    /// like [`TagIter::has_all_filter_keys`]
+    All,
+}
+
+impl FilterMode {
+    /// Check if the element passes the mode, its tags are already reduced to the filter keys
+    #[inline]
+    pub fn matches(&self, element: &ElementRef<'_>) -> bool {
+        match self {
+            FilterMode::Any => element.tags_count() > 0,
+            FilterMode::All => element.has_all_filter_keys(),
+        }
+    }
+}
+
+/// A bounding box in degrees
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BBox {
+    /// Minimum longitude
+    pub left: f64,
+    /// Minimum latitude
+    pub bottom: f64,
+    /// Maximum longitude
+    pub right: f64,
+    /// Maximum latitude
+    pub top: f64,
+}
+impl BBox {
+    /// Check if the coordinate lies inside the bounding box (borders included)
+    #[inline]
+    pub fn contains(&self, lat: f64, lon: f64) -> bool {
+        lat >= self.bottom && lat <= self.top && lon >= self.left && lon <= self.right
+    }
+    /// Check if both boxes share at least one coordinate (borders included)
+    #[inline]
+    pub fn intersects(&self, other: &BBox) -> bool {
+        self.left <= other.right
+            && other.left <= self.right
+            && self.bottom <= other.top
+            && other.bottom <= self.top
+    }
+    /// Get the smallest bounding box containing both boxes, e.g. for the parent nodes of an R-tree
+    #[inline]
+    pub fn union(&self, other: &BBox) -> BBox {
+        BBox {
+            left: self.left.min(other.left),
+            bottom: self.bottom.min(other.bottom),
+            right: self.right.max(other.right),
+            top: self.top.max(other.top),
+        }
+    }
+}
+
+/// The bounding box of a way or relation, see [`WayRef::bbox`] and [`RelationRef::bbox`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ElementBBox {
+    /// Bounding box of all resolved nodes
+    pub bbox: BBox,
+    /// True if some nodes or members could not be resolved and are missing from `bbox`
+    pub partial: bool,
+}
+
+// Grows a bounding box node by node and remembers nodes that could not be resolved
+#[derive(Default)]
+struct BBoxBuilder {
+    bbox: Option<BBox>,
+    partial: bool,
+}
+
+impl BBoxBuilder {
+    fn add(&mut self, location: Option<(f64, f64)>) {
+        let Some((lon, lat)) = location else {
+            self.partial = true;
+            return;
+        };
+        let point = BBox {
+            left: lon,
+            bottom: lat,
+            right: lon,
+            top: lat,
+        };
+        self.bbox = Some(self.bbox.map_or(point, |bbox| bbox.union(&point)));
+    }
+
+    fn finish(self) -> Option<ElementBBox> {
+        Some(ElementBBox {
+            bbox: self.bbox?,
+            partial: self.partial,
+        })
+    }
+}
+
+// Converts nanodegrees to units of 1 / scale degrees, i128 keeps large scales from overflowing
+#[inline]
+fn scale_nanodegrees(nanodegrees: i64, scale: i64, round: bool) -> i64 {
+    const NANO: i128 = 1_000_000_000;
+    let scaled = nanodegrees as i128 * scale as i128;
+    let scaled = if round {
+        (scaled + scaled.signum() * NANO / 2) / NANO
+    } else {
+        scaled / NANO
+    };
+    scaled as i64
+}
+
+// Mean earth radius in meters (IUGG)
+#[cfg(feature = "std")]
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+// Projects coordinates to meters east and north of the origin with the equirectangular approximation
+#[cfg(feature = "std")]
+fn relative_coords(
+    latitudes: Vec<f64>,
+    longitudes: Vec<f64>,
+    lat0: f64,
+    lon0: f64,
+) -> Vec<(f64, f64)> {
+    let x_scale = EARTH_RADIUS * lat0.to_radians().cos();
+    latitudes
+        .into_iter()
+        .zip(longitudes)
+        .map(|(lat, lon)| {
+            let x = (lon - lon0).to_radians() * x_scale;
+            let y = (lat - lat0).to_radians() * EARTH_RADIUS;
+            (x, y)
+        })
+        .collect()
+}
+
+/// Which ids references of ways and relations may have, other ids are reported as [`OsmError::Corrupt`].
+/// OSM ids are positive, only files of editors (JOSM, osmChange) use negative ids for new elements.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IdPolicy {
+    /// Accept every id
+    #[default]
+    Lenient,
+    /// Reject 0, accept negative ids of new elements in editor files
+    AllowNegative,
+    /// Reject ids that are not positive, use this for untrusted input
+    Strict,
+}
+impl IdPolicy {
+    /// Check if the policy accepts the id
+    #[inline]
+    pub fn accepts(&self, id: i64) -> bool {
+        match self {
+            Self::Lenient => true,
+            Self::AllowNegative => id != 0,
+            Self::Strict => id > 0,
+        }
+    }
+}
+
+// A string table as concatenated bytes, string i is bytes[offsets[i]..offsets[i + 1]]
+#[derive(Debug)]
+pub(crate) struct RawStringTable {
+    bytes: Vec<u8>,
+    offsets: Vec<usize>,
+}
+
+impl RawStringTable {
+    pub(crate) fn new(table: &[Arc<[u8]>]) -> Self {
+        let mut bytes = Vec::with_capacity(table.iter().map(|s| s.len()).sum());
+        let mut offsets = Vec::with_capacity(table.len() + 1);
+        offsets.push(0);
+        for s in table {
+            bytes.extend_from_slice(s);
+            offsets.push(bytes.len());
+        }
+        Self { bytes, offsets }
+    }
+}
+
+// Concatenates the strings of a string table, the offsets hold start and end of every string
+#[cfg(any(feature = "node_bindings", feature = "python_bindings"))]
+fn raw_string_table(table: &[Arc<[u8]>]) -> (Vec<u8>, Vec<u32>) {
+    let mut bytes = Vec::with_capacity(table.iter().map(|s| s.len()).sum());
+    let mut offsets = Vec::with_capacity(table.len() + 1);
+    offsets.push(0);
+    for s in table {
+        bytes.extend_from_slice(s);
+        offsets.push(bytes.len() as u32);
+    }
+    (bytes, offsets)
+}
+
+// Resolves (key, value) pairs to ids of the string table of a block.
+// Pairs missing from the table or with a key outside of the tag filter can never match and are left out
+fn resolve_tag_pairs(
+    table: &[Arc<[u8]>],
+    cached_tag_ids: &CachedTagIds,
+    tags: &[(&str, &str)],
+) -> Vec<(u32, u32)> {
+    let id_of = |s: &str| {
+        let id = table
+            .iter()
+            .position(|entry| entry.as_ref() == s.as_bytes())?;
+        Some(id as u32)
+    };
+    tags.iter()
+        .filter_map(|(key, val)| Some((id_of(key)?, id_of(val)?)))
+        .filter(|(key, _)| cached_tag_ids.keeps(*key))
+        .collect()
+}
+
+// Check if one of the (key, value) ids of an element is part of the resolved pairs
+#[inline]
+fn has_any_tag_pair(keys: &[u32], vals: &[u32], pairs: &[(u32, u32)]) -> bool {
+    keys.iter()
+        .zip(vals)
+        .any(|(key, val)| pairs.contains(&(*key, *val)))
+}
+
 /// An ElementBlock is an enum that holds variants where each block variant
 /// is a wrapper around multiple elements ([`DenseNodes`], [`Node`], [`Way`] or [`Relation`]).
 ///
+/// The blocks of one blob are always emitted in the order dense nodes, nodes, ways, relations.
+/// When reading in parallel, blocks of other blobs may come in between, depending on which worker finishes first.
+///
+/// Blocks format as a short summary of type and element count, e.g. `WayBlock(8000 ways)`.
+///
 /// For more details on OSM elements, see the [OSM wiki](https://wiki.openstreetmap.org/wiki/Elements).
-#[derive(Debug)]
 pub enum ElementBlock {
     /// Block of [`DenseNodes`]
     DenseNodeBlock(DenseNodeBlock),
@@ -34,94 +400,699 @@ pub enum ElementBlock {
     RelationBlock(RelationBlock),
 }
 
+impl fmt::Display for ElementBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ElementBlock::DenseNodeBlock(block) => fmt::Display::fmt(block, f),
+            ElementBlock::NodeBlock(block) => fmt::Display::fmt(block, f),
+            ElementBlock::WayBlock(block) => fmt::Display::fmt(block, f),
+            ElementBlock::RelationBlock(block) => fmt::Display::fmt(block, f),
+        }
+    }
+}
+
+// Same as Display, dumping the decoded columns of a block is unreadable
+impl fmt::Debug for ElementBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// The variant of an [`ElementBlock`], see [`ElementBlock::kind`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ElementKind {
+    /// [`ElementBlock::DenseNodeBlock`]
+    DenseNode,
+    /// [`ElementBlock::NodeBlock`]
+    Node,
+    /// [`ElementBlock::WayBlock`]
+    Way,
+    /// [`ElementBlock::RelationBlock`]
+    Relation,
+}
+
+impl ElementBlock {
+    /// Get the kind of the block without matching on it, e.g. to count how often a file
+    /// uses dense nodes and plain nodes
+    #[inline]
+    pub fn kind(&self) -> ElementKind {
+        match self {
+            ElementBlock::DenseNodeBlock(_) => ElementKind::DenseNode,
+            ElementBlock::NodeBlock(_) => ElementKind::Node,
+            ElementBlock::WayBlock(_) => ElementKind::Way,
+            ElementBlock::RelationBlock(_) => ElementKind::Relation,
+        }
+    }
+    /// Get the inner [`DenseNodeBlock`] if the block is one, shorter than a match for a single block type
+    #[inline]
+    pub fn as_dense_node_block(&self) -> Option<&DenseNodeBlock> {
+        match self {
+            ElementBlock::DenseNodeBlock(block) => Some(block),
+            _ => None,
+        }
+    }
+    /// Same as [`ElementBlock::as_dense_node_block`], but takes ownership of the block
+    #[inline]
+    pub fn into_dense_node_block(self) -> Option<DenseNodeBlock> {
+        match self {
+            ElementBlock::DenseNodeBlock(block) => Some(block),
+            _ => None,
+        }
+    }
+    /// Get the inner [`NodeBlock`] if the block is one, shorter than a match for a single block type
+    #[inline]
+    pub fn as_node_block(&self) -> Option<&NodeBlock> {
+        match self {
+            ElementBlock::NodeBlock(block) => Some(block),
+            _ => None,
+        }
+    }
+    /// Same as [`ElementBlock::as_node_block`], but takes ownership of the block
+    #[inline]
+    pub fn into_node_block(self) -> Option<NodeBlock> {
+        match self {
+            ElementBlock::NodeBlock(block) => Some(block),
+            _ => None,
+        }
+    }
+    /// Get the inner [`WayBlock`] if the block is one, shorter than a match for a single block type
+    #[inline]
+    pub fn as_way_block(&self) -> Option<&WayBlock> {
+        match self {
+            ElementBlock::WayBlock(block) => Some(block),
+            _ => None,
+        }
+    }
+    /// Same as [`ElementBlock::as_way_block`], but takes ownership of the block
+    #[inline]
+    pub fn into_way_block(self) -> Option<WayBlock> {
+        match self {
+            ElementBlock::WayBlock(block) => Some(block),
+            _ => None,
+        }
+    }
+    /// Get the inner [`RelationBlock`] if the block is one, shorter than a match for a single block type
+    #[inline]
+    pub fn as_relation_block(&self) -> Option<&RelationBlock> {
+        match self {
+            ElementBlock::RelationBlock(block) => Some(block),
+            _ => None,
+        }
+    }
+    /// Same as [`ElementBlock::as_relation_block`], but takes ownership of the block
+    #[inline]
+    pub fn into_relation_block(self) -> Option<RelationBlock> {
+        match self {
+            ElementBlock::RelationBlock(block) => Some(block),
+            _ => None,
+        }
+    }
+    /// Creates an iterator over the nodes of a [`ElementBlock::DenseNodeBlock`] or a
+    /// [`ElementBlock::NodeBlock`] as [`NodeView`], so both are handled by the same code.
+    /// Yields nothing for ways and relations.
+    pub fn nodes(&self) -> impl FusedIterator<Item = NodeView<'_>> {
+        let dense = match self {
+            ElementBlock::DenseNodeBlock(block) => Some(block.iter()),
+            _ => None,
+        };
+        let plain = match self {
+            ElementBlock::NodeBlock(block) => Some(block.iter()),
+            _ => None,
+        };
+        let dense = dense.into_iter().flatten().map(NodeView::Dense);
+        dense.chain(plain.into_iter().flatten().map(NodeView::Plain))
+    }
+    /// Get the codec of the blob this block was read from, e.g. to re-encode it with the same one.
+    /// Blocks parsed with [`decode_blob`](crate::decode_blob) report [`Compression::Raw`],
+    /// as their bytes were decompressed already.
+    #[cfg(feature = "std")]
+    pub fn source_compression(&self) -> Compression {
+        match self {
+            ElementBlock::DenseNodeBlock(block) => block.compression,
+            ElementBlock::NodeBlock(block) => block.compression,
+            ElementBlock::WayBlock(block) => block.compression,
+            ElementBlock::RelationBlock(block) => block.compression,
+        }
+    }
+    /// Get the bounding box the file stores for the nodes of the blob this block was read from.
+    /// The format has no field for it, so this is only [`Some`] if the `indexdata` of the blob header
+    /// holds exactly an encoded `HeaderBBox` of a valid box, which most writers never write.
+    /// Where present, the bbox filter drops blobs outside of it without decoding their nodes.
+    #[cfg(feature = "std")]
+    pub fn bbox(&self) -> Option<BBox> {
+        match self {
+            ElementBlock::DenseNodeBlock(block) => block.bbox_hint,
+            ElementBlock::NodeBlock(block) => block.bbox_hint,
+            ElementBlock::WayBlock(block) => block.bbox_hint,
+            ElementBlock::RelationBlock(block) => block.bbox_hint,
+        }
+    }
+    // Marks the elements passing the predicate, which only sees elements inside of the bbox filter.
+    // Returns if any element passes
+    #[cfg(feature = "std")]
+    pub(crate) fn apply_predicate(&mut self, predicate: &ElementPredicate) -> bool {
+        let (slot, matches) = match self {
+            ElementBlock::DenseNodeBlock(block) => {
+                let mut matches = vec![false; block.len()];
+                let mut nodes = block.dense_iter(false);
+                while let Some(node) = nodes.next() {
+                    matches[nodes.index - 1] = predicate(&ElementRef::DenseNode(node));
+                }
+                (&mut block.matches, matches)
+            }
+            ElementBlock::NodeBlock(block) => {
+                let mut matches = vec![false; block.len()];
+                for (index, node) in block.iter_indexed() {
+                    matches[index] = predicate(&ElementRef::Node(node));
+                }
+                (&mut block.matches, matches)
+            }
+            ElementBlock::WayBlock(block) => {
+                let matches = block.iter().map(|way| predicate(&ElementRef::Way(way)));
+                let matches = matches.collect();
+                (&mut block.matches, matches)
+            }
+            ElementBlock::RelationBlock(block) => {
+                let matches = block
+                    .iter()
+                    .map(|relation| predicate(&ElementRef::Relation(relation)));
+                let matches = matches.collect();
+                (&mut block.matches, matches)
+            }
+        };
+        let any = matches.contains(&true);
+        *slot = Some(matches);
+        any
+    }
+    fn matches(&self) -> Option<&[bool]> {
+        match self {
+            ElementBlock::DenseNodeBlock(block) => block.matches.as_deref(),
+            ElementBlock::NodeBlock(block) => block.matches.as_deref(),
+            ElementBlock::WayBlock(block) => block.matches.as_deref(),
+            ElementBlock::RelationBlock(block) => block.matches.as_deref(),
+        }
+    }
+    #[cfg(feature = "std")]
+    pub(crate) fn set_source_compression(&mut self, compression: Compression) {
+        match self {
+            ElementBlock::DenseNodeBlock(block) => block.compression = compression,
+            ElementBlock::NodeBlock(block) => block.compression = compression,
+            ElementBlock::WayBlock(block) => block.compression = compression,
+            ElementBlock::RelationBlock(block) => block.compression = compression,
+        }
+    }
+    #[cfg(feature = "std")]
+    pub(crate) fn set_bbox_hint(&mut self, bbox_hint: Option<BBox>) {
+        match self {
+            ElementBlock::DenseNodeBlock(block) => block.bbox_hint = bbox_hint,
+            ElementBlock::NodeBlock(block) => block.bbox_hint = bbox_hint,
+            ElementBlock::WayBlock(block) => block.bbox_hint = bbox_hint,
+            ElementBlock::RelationBlock(block) => block.bbox_hint = bbox_hint,
+        }
+    }
+    /// Get the string table of the block as concatenated bytes and the offsets of every string in it,
+    /// string `i` is `bytes[offsets[i]..offsets[i + 1]]`, so there is one offset more than strings.
+    /// The bytes are neither validated as UTF-8 nor copied, e.g. to blit the table into the dictionary
+    /// of a columnar store. [`None`] unless the reader was created with
+    /// [`OsmReader::with_raw_string_table`](crate::OsmReader::with_raw_string_table).
+    pub fn string_table_raw(&self) -> Option<(&[u8], &[usize])> {
+        let raw_table = match self {
+            ElementBlock::DenseNodeBlock(block) => &block.raw_table,
+            ElementBlock::NodeBlock(block) => &block.raw_table,
+            ElementBlock::WayBlock(block) => &block.raw_table,
+            ElementBlock::RelationBlock(block) => &block.raw_table,
+        };
+        let raw_table = raw_table.as_deref()?;
+        Some((&raw_table.bytes, &raw_table.offsets))
+    }
+    /// Get the keys of the tag filter that occur in the string table of this block, [`None`] without a tag filter.
+    /// Elements of a block missing a filter key can never pass [`TagIter::has_all_filter_keys`],
+    /// so this helps to find out why a filter matches nothing (e.g. a misspelled key).
+    pub fn filter_keys_present_in_block(&self) -> Option<Vec<&str>> {
+        let (table, cached_tag_ids) = match self {
+            ElementBlock::DenseNodeBlock(block) => (&block.table, &block.cached_tag_ids),
+            ElementBlock::NodeBlock(block) => (&block.table, &block.cached_tag_ids),
+            ElementBlock::WayBlock(block) => (&block.table, &block.cached_tag_ids),
+            ElementBlock::RelationBlock(block) => (&block.table, &block.cached_tag_ids),
+        };
+        let ids = cached_tag_ids.ids.as_ref()?;
+        Some(
+            ids.iter()
+                .map(|&id| unsafe { core::str::from_utf8_unchecked(&table[id as usize]) })
+                .collect(),
+        )
+    }
+    /// Create an iter over the [`ElementMeta`] of all elements that have metadata.
+    /// Tags are never resolved and coordinates of nodes are only decoded for the bbox filter,
+    /// combine it with [`OsmReader::with_metadata_only`](crate::OsmReader::with_metadata_only) to also skip them while parsing.
+    pub fn metadata(&self) -> impl FusedIterator<Item = ElementMeta<'_>> {
+        ElementMetaIter {
+            block: self,
+            index: 0,
+            prev_id: 0,
+            prev_lat: 0,
+            prev_lon: 0,
+            prev_info: DenseInfoState::default(),
+        }
+    }
+}
+
+struct ElementMetaIter<'a> {
+    block: &'a ElementBlock,
+    index: usize,
+    prev_id: i64,
+    prev_lat: i64,
+    prev_lon: i64,
+    prev_info: DenseInfoState,
+}
+
+impl<'a> Iterator for ElementMetaIter<'a> {
+    type Item = ElementMeta<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let index = self.index;
+            self.index += 1;
+            let (element_type, id, info, table) = match self.block {
+                ElementBlock::DenseNodeBlock(block) => {
+                    let nodes = &block.nodes;
+                    let info = nodes.denseinfo.as_ref()?;
+                    self.prev_id += *nodes.id.get(index)?;
+                    let meta = self.prev_info.info(info, index, block.date_granularity);
+                    self.prev_info.advance(info, index);
+                    if let Some(bbox) = &block.bbox {
+                        self.prev_lat += nodes.lat[index];
+                        self.prev_lon += nodes.lon[index];
+                        let lat = (self.prev_lat * block.granularity + block.lat_offset) as f64;
+                        let lon = (self.prev_lon * block.granularity + block.lon_offset) as f64;
+                        if !bbox.contains(lat * 1e-9, lon * 1e-9) {
+                            continue;
+                        }
+                    }
+                    (MemberType::NODE, self.prev_id, Some(meta), &block.table)
+                }
+                ElementBlock::NodeBlock(block) => {
+                    let node = block.nodes.get(index)?;
+                    if let Some(bbox) = &block.bbox {
+                        let lat = (node.lat * block.granularity + block.lat_offset) as f64;
+                        let lon = (node.lon * block.granularity + block.lon_offset) as f64;
+                        if !bbox.contains(lat * 1e-9, lon * 1e-9) {
+                            continue;
+                        }
+                    }
+                    let info = node.info.as_ref();
+                    let meta =
+                        info.map(|info| ElementInfo::from_info(info, block.date_granularity));
+                    (MemberType::NODE, node.id, meta, &block.table)
+                }
+                ElementBlock::WayBlock(block) => {
+                    let way = block.ways.get(index)?;
+                    let info = way.info.as_ref();
+                    let meta =
+                        info.map(|info| ElementInfo::from_info(info, block.date_granularity));
+                    (MemberType::WAY, way.id, meta, &block.table)
+                }
+                ElementBlock::RelationBlock(block) => {
+                    let relation = block.relations.get(index)?;
+                    let info = relation.info.as_ref();
+                    let meta =
+                        info.map(|info| ElementInfo::from_info(info, block.date_granularity));
+                    (MemberType::RELATION, relation.id, meta, &block.table)
+                }
+            };
+
+            let Some(info) = info else {
+                continue;
+            };
+            if self.block.matches().is_some_and(|matches| !matches[index]) {
+                continue;
+            }
+            // the empty string at index 0 is used for elements without a user name,
+            // a name that is not valid UTF-8 is treated as unknown as well
+            let user = table
+                .get(info.user_sid as usize)
+                .and_then(|user| core::str::from_utf8(user).ok())
+                .unwrap_or("");
+            return Some(ElementMeta {
+                element_type,
+                id,
+                info,
+                user,
+            });
+        }
+    }
+}
+
+// index only grows, so every further call runs out of elements again
+impl FusedIterator for ElementMetaIter<'_> {}
+
+/// A Reference to an element of any type, see [`OsmReader::par_for_each_element`](crate::OsmReader::par_for_each_element).
+/// Match on it to get the full API of the element type.
+#[derive(Debug)]
+pub enum ElementRef<'a> {
+    /// Reference to a DenseNode
+    DenseNode(DenseNodeRef<'a>),
+    /// Reference to a [`Node`]
+    Node(NodeRef<'a>),
+    /// Reference to a [`Way`]
+    Way(WayRef<'a>),
+    /// Reference to a [`Relation`]
+    Relation(RelationRef<'a>),
+}
+
+impl<'a> ElementRef<'a> {
+    /// Get element type ([`Node`], [`Way`] or [`Relation`])
+    #[inline]
+    pub fn element_type(&self) -> MemberType {
+        match self {
+            ElementRef::DenseNode(_) | ElementRef::Node(_) => MemberType::NODE,
+            ElementRef::Way(_) => MemberType::WAY,
+            ElementRef::Relation(_) => MemberType::RELATION,
+        }
+    }
+    /// Get ID
+    #[inline]
+    pub fn id(&self) -> i64 {
+        match self {
+            ElementRef::DenseNode(node) => node.prev_id + node.block.nodes.id[node.index],
+            ElementRef::Node(node) => node.id(),
+            ElementRef::Way(way) => way.id(),
+            ElementRef::Relation(relation) => relation.id(),
+        }
+    }
+    /// Get metadata (version, timestamp, changeset, user), if the file contains it
+    #[inline]
+    pub fn info(&self) -> Option<ElementInfo> {
+        match self {
+            ElementRef::DenseNode(node) => node.info(),
+            ElementRef::Node(node) => node.info(),
+            ElementRef::Way(way) => way.info(),
+            ElementRef::Relation(relation) => relation.info(),
+        }
+    }
+    /// Get the number of tags passing the tag filter, see [`WayRef::tags_count`]
+    #[inline]
+    pub fn tags_count(&self) -> usize {
+        match self {
+            ElementRef::DenseNode(node) => node.tags_count(),
+            ElementRef::Node(node) => node.tags_count(),
+            ElementRef::Way(way) => way.tags_count(),
+            ElementRef::Relation(relation) => relation.tags_count(),
+        }
+    }
+    /// Check if all applied filter keys are present in the tags of the element
+    #[inline]
+    pub fn has_all_filter_keys(&self) -> bool {
+        match self {
+            ElementRef::DenseNode(node) => node.tags().has_all_filter_keys(),
+            ElementRef::Node(node) => node.tags().has_all_filter_keys(),
+            ElementRef::Way(way) => way.tags().has_all_filter_keys(),
+            ElementRef::Relation(relation) => relation.tags().has_all_filter_keys(),
+        }
+    }
+    /// Convert into an owned [`Element`]
+    pub fn to_owned(&self) -> Element {
+        match self {
+            ElementRef::DenseNode(node) => node.to_owned().into(),
+            ElementRef::Node(node) => node.to_owned().into(),
+            ElementRef::Way(way) => way.to_owned().into(),
+            ElementRef::Relation(relation) => relation.to_owned().into(),
+        }
+    }
+}
+
+/// A Reference to a node that hides whether it is stored as a DenseNode or as a plain [`Node`],
+/// see [`ElementBlock::nodes`]. Unlike the refs it wraps, the accessors do not advance the ref,
+/// so they can be called in any order and more than once.
+#[derive(Debug)]
+pub enum NodeView<'a> {
+    /// Reference to a DenseNode
+    Dense(DenseNodeRef<'a>),
+    /// Reference to a [`Node`]
+    Plain(NodeRef<'a>),
+}
+
+impl<'a> NodeView<'a> {
+    /// Get ID
+    #[inline]
+    pub fn id(&self) -> i64 {
+        match self {
+            NodeView::Dense(node) => node.prev_id + node.block.nodes.id[node.index],
+            NodeView::Plain(node) => node.id(),
+        }
+    }
+    /// Get Latitude
+    #[inline]
+    pub fn lat(&self) -> f64 {
+        match self {
+            NodeView::Dense(node) => node.lat_nanodegrees() as f64 * 1e-9,
+            NodeView::Plain(node) => node.lat_nanodegrees() as f64 * 1e-9,
+        }
+    }
+    /// Get Longitude
+    #[inline]
+    pub fn lon(&self) -> f64 {
+        match self {
+            NodeView::Dense(node) => node.lon_nanodegrees() as f64 * 1e-9,
+            NodeView::Plain(node) => node.lon_nanodegrees() as f64 * 1e-9,
+        }
+    }
+    /// Get Iterator over (key, value) pairs
+    #[inline]
+    pub fn tags(&self) -> NodeTagIter<'_> {
+        match self {
+            NodeView::Dense(node) => NodeTagIter::Dense(node.tags()),
+            NodeView::Plain(node) => NodeTagIter::Plain(node.tags()),
+        }
+    }
+    /// Same as [`tags`](Self::tags), but replaces invalid UTF-8, see [`WayRef::tags_lossy`]
+    #[inline]
+    pub fn tags_lossy(&self) -> impl FusedIterator<Item = (Cow<'_, str>, Cow<'_, str>)> {
+        let mut tags = self.tags();
+        lossy_tags(move || tags.next_raw())
+    }
+    /// Get metadata (version, timestamp, changeset, user), if the file contains it
+    #[inline]
+    pub fn info(&self) -> Option<ElementInfo> {
+        match self {
+            NodeView::Dense(node) => node.info(),
+            NodeView::Plain(node) => node.info(),
+        }
+    }
+    /// Convert into an owned [`OwnedNode`]
+    pub fn to_owned(&self) -> OwnedNode {
+        match self {
+            NodeView::Dense(node) => node.to_owned(),
+            NodeView::Plain(node) => node.to_owned(),
+        }
+    }
+}
+
+/// An iterator that yields the (key, value) tag pairs of a [`NodeView`]
+#[derive(Clone)]
+pub enum NodeTagIter<'a> {
+    /// Tags of a DenseNode
+    Dense(DenseNodeTagIter<'a>),
+    /// Tags of a [`Node`]
+    Plain(TagIter<'a>),
+}
+
+impl<'a> Iterator for NodeTagIter<'a> {
+    type Item = (&'a str, &'a str);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            NodeTagIter::Dense(tags) => tags.next(),
+            NodeTagIter::Plain(tags) => tags.next(),
+        }
+    }
+}
+
+impl FusedIterator for NodeTagIter<'_> {}
+impl<'a> NodeTagIter<'a> {
+    /// Check if all applied filter keys are present in the iterator, see [`TagIter::has_all_filter_keys`]
+    #[inline]
+    pub fn has_all_filter_keys(self) -> bool {
+        match self {
+            NodeTagIter::Dense(tags) => tags.has_all_filter_keys(),
+            NodeTagIter::Plain(tags) => tags.has_all_filter_keys(),
+        }
+    }
+    #[inline]
+    fn next_raw(&mut self) -> Option<(&'a [u8], &'a [u8])> {
+        match self {
+            NodeTagIter::Dense(tags) => tags.next_raw(),
+            NodeTagIter::Plain(tags) => tags.next_raw(),
+        }
+    }
+}
+
 // --------------------------- DENSE_NODE ---------------------------
 // --------------------------- DENSE_NODE ---------------------------
 // --------------------------- DENSE_NODE ---------------------------
 
 /// A Wrapper to hold DenseNodes which gets lazy decoded via iterator.
 /// Main use is for performance reasons. Use .iter() on it to iterate over it.
-#[derive(Debug)]
 pub struct DenseNodeBlock {
     pub(crate) nodes: Arc<DenseNodes>,
-    pub(crate) table: Arc<Vec<Cow<'static, [u8]>>>,
-    pub(crate) cached_tag_ids: Arc<Vec<u32>>,
+    pub(crate) table: Arc<Vec<Arc<[u8]>>>,
+    // the table flattened for zero-copy exports, see OsmReader::with_raw_string_table
+    pub(crate) raw_table: Option<Arc<RawStringTable>>,
+    pub(crate) cached_tag_ids: Arc<CachedTagIds>,
     pub(crate) granularity: i64,
     pub(crate) lat_offset: i64,
     pub(crate) lon_offset: i64,
+    pub(crate) date_granularity: i64,
+    // start of the tags of every node in keys_vals and the end, empty if the block has no tags at all
     pub(crate) kv_offsets: Vec<usize>,
+    pub(crate) bbox: Option<BBox>,
+    // elements passing the predicate of the reader by index, None without one
+    pub(crate) matches: Option<Vec<bool>>,
+    #[cfg(feature = "std")]
+    pub(crate) compression: Compression,
+    // bounding box the blob of the block claims for its nodes, see ElementBlock::bbox
+    #[cfg(feature = "std")]
+    pub(crate) bbox_hint: Option<BBox>,
 }
 impl DenseNodeBlock {
+    // Range of the tags of the node at index in keys_vals, empty for every node of a tag-free block
+    #[inline]
+    pub(crate) fn kv_range(&self, index: usize) -> Range<usize> {
+        if self.kv_offsets.is_empty() {
+            return 0..0;
+        }
+        self.kv_offsets[index]..self.kv_offsets[index + 1]
+    }
     /// Create an iter over [`DenseNodeRef`]
-    pub fn iter(&self) -> impl Iterator<Item = DenseNodeRef<'_>> {
+    pub fn iter(&self) -> impl FusedIterator<Item = DenseNodeRef<'_>> {
+        self.dense_iter(false)
+    }
+    /// Create an iter over the [`DenseNodeRef`] that have at least one tag (e.g. to extract POIs).
+    /// Untagged nodes are skipped by their tag offsets, before their coordinates are decoded
+    /// for the bbox filter. The check happens before the tag filter, so `tags()` of a node
+    /// can still be empty if none of its keys is part of the filter.
+    pub fn iter_with_tags(&self) -> impl FusedIterator<Item = DenseNodeRef<'_>> {
+        self.dense_iter(true)
+    }
+    fn dense_iter(&self, tagged_only: bool) -> DenseNodeIter<'_> {
         DenseNodeIter {
             block: self,
             cached_tag_ids: &self.cached_tag_ids,
             index: 0,
-            len: self.nodes.id.len(),
+            // a tag-free block has no node to yield, so nothing is decoded
+            len: if tagged_only && self.kv_offsets.is_empty() {
+                0
+            } else {
+                self.nodes.id.len()
+            },
             prev_id: 0,
             prev_lat: 0,
             prev_lon: 0,
+            prev_info: DenseInfoState::default(),
+            bbox: self.bbox.as_ref(),
+            tagged_only,
         }
     }
     /// Get the number of [`DenseNodeRef`]
     pub fn len(&self) -> usize {
         self.nodes.id.len()
     }
-    /// Helper method for node bindings.
-    #[cfg(feature = "node_bindings")]
+    /// Check if there are no [`DenseNodeRef`]
+    pub fn is_empty(&self) -> bool {
+        self.nodes.id.is_empty()
+    }
+    /// Classifies all nodes at once by whether they have any of the `(key, value)` tags,
+    /// see [`WayBlock::has_any_tag`]. Runs over the tag column of the block
+    pub fn has_any_tag(&self, tags: &[(&str, &str)]) -> Vec<bool> {
+        let pairs = resolve_tag_pairs(&self.table, &self.cached_tag_ids, tags);
+        if pairs.is_empty() || self.kv_offsets.is_empty() {
+            return alloc::vec![false; self.len()];
+        }
+        let keys_vals = &self.nodes.keys_vals;
+        self.kv_offsets
+            .windows(2)
+            .map(|range| {
+                // the terminating zero is left over by chunks_exact
+                keys_vals[range[0]..range[1]]
+                    .chunks_exact(2)
+                    .any(|kv| pairs.contains(&(kv[0] as u32, kv[1] as u32)))
+            })
+            .collect()
+    }
+    // Check if any node is left after applying the bbox filter
+    #[cfg(feature = "std")]
+    pub(crate) fn has_filtered_nodes(&self) -> bool {
+        self.iter().next().is_some()
+    }
+    /// Get all latitudes of the block, decoded in bulk
+    pub fn latitudes(&self) -> Vec<f64> {
+        simd::decode_coords(&self.nodes.lat, self.granularity, self.lat_offset)
+    }
+    /// Get all longitudes of the block, decoded in bulk
+    pub fn longitudes(&self) -> Vec<f64> {
+        simd::decode_coords(&self.nodes.lon, self.granularity, self.lon_offset)
+    }
+    /// Get all coordinates of the block as `(lat, lon)`, decoded in bulk.
+    /// Like the other bulk accessors, this ignores the bbox filter
+    pub fn coords(&self) -> Vec<(f64, f64)> {
+        self.latitudes()
+            .into_iter()
+            .zip(self.longitudes())
+            .collect()
+    }
+    /// Get all coordinates of the block as `(x, y)` in meters east and north of the origin `(lat0, lon0)`
+    /// (a local tangent plane), decoded in bulk and ignoring the bbox filter like [`coords`](Self::coords).
+    ///
+    /// Uses the equirectangular approximation on a sphere: the error stays below 0.1% within about 5km of
+    /// the origin and below 1% within about 50km at mid latitudes, so it is meant for city scale analysis.
+    /// It grows toward the poles, and longitudes are not wrapped at the antimeridian.
+    #[cfg(feature = "std")]
+    pub fn coords_relative_to(&self, lat0: f64, lon0: f64) -> Vec<(f64, f64)> {
+        relative_coords(self.latitudes(), self.longitudes(), lat0, lon0)
+    }
+    /// Get the ids, latitudes and longitudes of all nodes as columns, decoded in bulk
+    pub fn ids_and_coords(&self) -> (Vec<i64>, Vec<f64>, Vec<f64>) {
+        let ids = simd::delta_decode_i64(&self.nodes.id);
+        (ids, self.latitudes(), self.longitudes())
+    }
+    /// Helper method for node and python bindings.
+    #[cfg(any(feature = "node_bindings", feature = "python_bindings"))]
     pub fn get_string_table(&self) -> Vec<String> {
         self.table
             .iter()
-            .map(|cow| unsafe { std::str::from_utf8_unchecked(cow.as_ref()) }.to_owned())
+            .map(|cow| unsafe { core::str::from_utf8_unchecked(cow.as_ref()) }.to_owned())
             .collect()
     }
-    /// Helper method for node bindings.
-    #[cfg(feature = "node_bindings")]
-    pub fn get_raw_data(&self) -> (Vec<i64>, Vec<f64>, Vec<f64>, Vec<u32>, Vec<u32>, Vec<u32>) {
+    /// Helper method for node and python bindings.
+    /// Returns the string table as concatenated bytes and the offsets of every string in it.
+    #[cfg(any(feature = "node_bindings", feature = "python_bindings"))]
+    pub fn get_raw_string_table(&self) -> (Vec<u8>, Vec<u32>) {
+        raw_string_table(&self.table)
+    }
+    /// Helper method for the node and python bindings and the Arrow conversion.
+    #[cfg(any(
+        feature = "node_bindings",
+        feature = "python_bindings",
+        feature = "arrow"
+    ))]
+    pub fn get_raw_data(&self) -> RawNodeData {
         let len = self.nodes.id.len();
-        let mut ids = Vec::with_capacity(len);
-        let mut latitudes = Vec::with_capacity(len);
-        let mut longitudes = Vec::with_capacity(len);
+
+        // delta decode ids and coordinates in bulk
+        let ids = simd::delta_decode_i64(&self.nodes.id);
+        let latitudes = self.latitudes();
+        let longitudes = self.longitudes();
 
         let mut key_ids = Vec::with_capacity(self.nodes.keys_vals.len() / 2);
         let mut val_ids = Vec::with_capacity(self.nodes.keys_vals.len() / 2);
-        let mut kv_offsets = Vec::with_capacity(self.kv_offsets.len() + 1);
+        let mut kv_offsets = Vec::with_capacity(len + 1);
         kv_offsets.push(0);
 
-        // accumulators for delta decoding
-        let mut last_id = 0i64;
-        let mut last_lat = 0i64;
-        let mut last_lon = 0i64;
-
-        let use_cache = TAG_KEYS_FILTER.get().is_some();
-
         for node_idx in 0..len {
-            // delta decode
-            last_id += self.nodes.id[node_idx];
-            last_lat += self.nodes.lat[node_idx];
-            last_lon += self.nodes.lon[node_idx];
-
-            ids.push(last_id);
-            latitudes.push((last_lat * self.granularity + self.lat_offset) as f64 * 1e-9);
-            longitudes.push((last_lon * self.granularity + self.lon_offset) as f64 * 1e-9);
-
-            let start = self.kv_offsets[node_idx];
-            let end = self
-                .kv_offsets
-                .get(node_idx + 1)
-                .copied()
-                .unwrap_or(self.nodes.keys_vals.len());
-
-            let mut i = start;
-            while i + 1 < end {
-                let key = self.nodes.keys_vals[i] as u32;
-                let val = self.nodes.keys_vals[i + 1] as u32;
-                if !use_cache || self.cached_tag_ids.contains(&key) {
-                    key_ids.push(key);
-                    val_ids.push(val);
-                }
-                i += 2;
-            }
+            let keys_vals = &self.nodes.keys_vals[self.kv_range(node_idx)];
+            self.cached_tag_ids
+                .extend_kept_interleaved(keys_vals, &mut key_ids, &mut val_ids);
 
             kv_offsets.push(key_ids.len() as u32);
         }
@@ -130,15 +1101,28 @@ impl DenseNodeBlock {
     }
 }
 
+impl fmt::Display for DenseNodeBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "DenseNodeBlock({} nodes)", self.len())
+    }
+}
+
+impl fmt::Debug for DenseNodeBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
 /// A Reference to a DenseNode
 #[derive(Debug)]
 pub struct DenseNodeRef<'a> {
     pub(crate) block: &'a DenseNodeBlock,
-    pub(crate) cached_tag_ids: &'a [u32],
+    pub(crate) cached_tag_ids: &'a CachedTagIds,
     pub(crate) index: usize,
     pub(crate) prev_id: i64,
     pub(crate) prev_lat: i64,
     pub(crate) prev_lon: i64,
+    pub(crate) prev_info: DenseInfoState,
 }
 
 impl<'a> DenseNodeRef<'a> {
@@ -148,7 +1132,10 @@ impl<'a> DenseNodeRef<'a> {
         self.prev_id += self.block.nodes.id[self.index];
         self.prev_id
     }
-    /// Get Latitude
+    /// Get Latitude, decoded with the granularity and offsets of the block.
+    /// Exact to the granularity of the file (100 nanodegrees by default, 1 to 1000 occur in the wild)
+    /// up to the f64 rounding of the conversion from nanodegrees. `lat_scaled(10_000_000)` gives the
+    /// same 1e-7 degree value as osmium
     #[inline]
     pub fn lat(&mut self) -> f64 {
         self.prev_lat += self.block.nodes.lat[self.index];
@@ -160,13 +1147,41 @@ impl<'a> DenseNodeRef<'a> {
         self.prev_lon += self.block.nodes.lon[self.index];
         ((self.prev_lon * self.block.granularity + self.block.lon_offset) as f64) * 1e-9
     }
+    /// Get Latitude in units of `1 / scale` degrees (e.g. 1_000_000 for microdegrees), truncated toward zero.
+    /// Computed from the nanodegrees of the file without a float round trip and without advancing like `lat()`
+    #[inline]
+    pub fn lat_scaled(&self, scale: i64) -> i64 {
+        scale_nanodegrees(self.lat_nanodegrees(), scale, false)
+    }
+    /// Same as [`DenseNodeRef::lat_scaled`], but rounded half away from zero
+    #[inline]
+    pub fn lat_scaled_rounded(&self, scale: i64) -> i64 {
+        scale_nanodegrees(self.lat_nanodegrees(), scale, true)
+    }
+    /// Get Longitude in units of `1 / scale` degrees, truncated toward zero, see [`DenseNodeRef::lat_scaled`]
+    #[inline]
+    pub fn lon_scaled(&self, scale: i64) -> i64 {
+        scale_nanodegrees(self.lon_nanodegrees(), scale, false)
+    }
+    /// Same as [`DenseNodeRef::lon_scaled`], but rounded half away from zero
+    #[inline]
+    pub fn lon_scaled_rounded(&self, scale: i64) -> i64 {
+        scale_nanodegrees(self.lon_nanodegrees(), scale, true)
+    }
+    #[inline]
+    fn lat_nanodegrees(&self) -> i64 {
+        let lat = self.prev_lat + self.block.nodes.lat[self.index];
+        lat * self.block.granularity + self.block.lat_offset
+    }
+    #[inline]
+    fn lon_nanodegrees(&self) -> i64 {
+        let lon = self.prev_lon + self.block.nodes.lon[self.index];
+        lon * self.block.granularity + self.block.lon_offset
+    }
     /// Get Iterator of (key, value) pairs
     #[inline]
     pub fn tags(&self) -> DenseNodeTagIter<'_> {
-        let start = self.block.kv_offsets[self.index];
-        let end = self.block.kv_offsets[self.index + 1];
-
-        let slice = &self.block.nodes.keys_vals[start..end];
+        let slice = &self.block.nodes.keys_vals[self.block.kv_range(self.index)];
         let table = &self.block.table;
 
         DenseNodeTagIter {
@@ -174,18 +1189,58 @@ impl<'a> DenseNodeRef<'a> {
             table,
             pos: 0,
             cached_tag_ids: self.cached_tag_ids,
-            use_cache: TAG_KEYS_FILTER.get().is_some(),
+        }
+    }
+    /// Same as [`tags`](Self::tags), but replaces invalid UTF-8, see [`WayRef::tags_lossy`]
+    #[inline]
+    pub fn tags_lossy(&self) -> impl FusedIterator<Item = (Cow<'_, str>, Cow<'_, str>)> {
+        let mut tags = self.tags();
+        lossy_tags(move || tags.next_raw())
+    }
+    /// Get the number of tags passing the tag filter without consuming an iterator,
+    /// so you can count before iterating over [`tags`](Self::tags)
+    #[inline]
+    pub fn tags_count(&self) -> usize {
+        self.block.nodes.keys_vals[self.block.kv_range(self.index)]
+            .chunks_exact(2)
+            .take_while(|pair| pair[0] != 0)
+            .filter(|pair| self.cached_tag_ids.keeps(pair[0] as u32))
+            .count()
+    }
+    /// Get metadata (version, timestamp, changeset, user), if the file contains it
+    #[inline]
+    pub fn info(&self) -> Option<ElementInfo> {
+        let info = self.block.nodes.denseinfo.as_ref()?;
+        Some(
+            self.prev_info
+                .info(info, self.index, self.block.date_granularity),
+        )
+    }
+    /// Creates an [`OwnedNode`] that does not borrow from the block
+    pub fn to_owned(&self) -> OwnedNode {
+        let block = self.block;
+        let lat = self.prev_lat + block.nodes.lat[self.index];
+        let lon = self.prev_lon + block.nodes.lon[self.index];
+        OwnedNode {
+            id: self.prev_id + block.nodes.id[self.index],
+            lat: ((lat * block.granularity + block.lat_offset) as f64) * 1e-9,
+            lon: ((lon * block.granularity + block.lon_offset) as f64) * 1e-9,
+            tags: owned_tags(self.tags()),
         }
     }
 }
 struct DenseNodeIter<'a> {
     block: &'a DenseNodeBlock,
-    cached_tag_ids: &'a [u32],
+    cached_tag_ids: &'a CachedTagIds,
     index: usize,
     len: usize,
     prev_id: i64,
     prev_lat: i64,
     prev_lon: i64,
+    prev_info: DenseInfoState,
+    bbox: Option<&'a BBox>,
+    // skip nodes without tags, see DenseNodeBlock::iter_with_tags
+    tagged_only: bool,
 }
 
 impl<'a> Iterator for DenseNodeIter<'a> {
@@ -193,126 +1248,239 @@ impl<'a> Iterator for DenseNodeIter<'a> {
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
-        if self.index == self.len {
-            return None;
-        }
+        loop {
+            if self.index == self.len {
+                return None;
+            }
 
-        let node = &self.block.nodes;
+            let node = &self.block.nodes;
+
+            // Create the DenseNodeRef with current previous values
+            let out = DenseNodeRef {
+                block: self.block,
+                index: self.index,
+                cached_tag_ids: self.cached_tag_ids,
+                prev_id: self.prev_id,
+                prev_lat: self.prev_lat,
+                prev_lon: self.prev_lon,
+                prev_info: self.prev_info,
+            };
 
-        // Create the DenseNodeRef with current previous values
-        let out = DenseNodeRef {
-            block: self.block,
-            index: self.index,
-            cached_tag_ids: self.cached_tag_ids,
-            prev_id: self.prev_id,
-            prev_lat: self.prev_lat,
-            prev_lon: self.prev_lon,
-        };
+            // Update the accumulators for the next node
+            self.prev_id += node.id[self.index];
+            self.prev_lat += node.lat[self.index];
+            self.prev_lon += node.lon[self.index];
+            if let Some(info) = &node.denseinfo {
+                self.prev_info.advance(info, self.index);
+            }
 
-        // Update the accumulators for the next node
-        self.prev_id += node.id[self.index];
-        self.prev_lat += node.lat[self.index];
-        self.prev_lon += node.lon[self.index];
+            self.index += 1;
 
-        self.index += 1;
-        Some(out)
+            // Skip untagged nodes, whose range is at most the terminating zero.
+            // The accumulators already advanced past them
+            if self.tagged_only && self.block.kv_range(self.index - 1).len() < 2 {
+                continue;
+            }
+            let matches = self.block.matches.as_ref();
+            if matches.is_some_and(|matches| !matches[self.index - 1]) {
+                continue;
+            }
+
+            // Skip nodes outside of the bbox filter, the accumulators now hold their coordinates
+            if let Some(bbox) = self.bbox {
+                let block = self.block;
+                let lat = ((self.prev_lat * block.granularity + block.lat_offset) as f64) * 1e-9;
+                let lon = ((self.prev_lon * block.granularity + block.lon_offset) as f64) * 1e-9;
+                if !bbox.contains(lat, lon) {
+                    continue;
+                }
+            }
+            return Some(out);
+        }
     }
 }
 
+impl FusedIterator for DenseNodeIter<'_> {}
+
 // --------------------------- NODE ---------------------------
 // --------------------------- NODE ---------------------------
 // --------------------------- NODE ---------------------------
 
 /// A block of [`Node`].
 /// Use .iter() on it to iterate over nodes.
-#[derive(Debug)]
 pub struct NodeBlock {
     pub(crate) nodes: Arc<Vec<Node>>,
-    pub(crate) cached_tag_ids: Arc<Vec<u32>>,
-    pub(crate) table: Arc<Vec<Cow<'static, [u8]>>>,
+    pub(crate) cached_tag_ids: Arc<CachedTagIds>,
+    pub(crate) table: Arc<Vec<Arc<[u8]>>>,
+    pub(crate) raw_table: Option<Arc<RawStringTable>>,
+    pub(crate) granularity: i64,
+    pub(crate) lat_offset: i64,
+    pub(crate) lon_offset: i64,
+    pub(crate) date_granularity: i64,
+    pub(crate) bbox: Option<BBox>,
+    pub(crate) matches: Option<Vec<bool>>,
+    #[cfg(feature = "std")]
+    pub(crate) compression: Compression,
+    // bounding box the blob of the block claims for its nodes, see ElementBlock::bbox
+    #[cfg(feature = "std")]
+    pub(crate) bbox_hint: Option<BBox>,
 }
 impl NodeBlock {
     /// Creates an iterator over [`NodeRef`]
-    pub fn iter(&self) -> impl Iterator<Item = NodeRef<'_>> {
-        let mut prev_lat = 0i64;
-        let mut prev_lon = 0i64;
-
-        self.nodes.iter().map(move |node| {
-            let node_ref = NodeRef {
-                node,
-                cached_tag_ids: &self.cached_tag_ids,
-                table: &self.table,
-                prev_lat,
-                prev_lon,
-            };
-
-            prev_lat += node.lat;
-            prev_lon += node.lon;
+    pub fn iter(&self) -> impl FusedIterator<Item = NodeRef<'_>> {
+        self.iter_indexed().map(|(_, node)| node)
+    }
+    // Iterates the nodes passing the filters together with their index in the block
+    fn iter_indexed(&self) -> impl FusedIterator<Item = (usize, NodeRef<'_>)> {
+        let bbox = self.bbox;
 
-            node_ref
-        })
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter_map(move |(index, node)| {
+                let node_ref = NodeRef {
+                    node,
+                    cached_tag_ids: &self.cached_tag_ids,
+                    table: &self.table,
+                    granularity: self.granularity,
+                    lat_offset: self.lat_offset,
+                    lon_offset: self.lon_offset,
+                    date_granularity: self.date_granularity,
+                };
+
+                if self.matches.as_ref().is_some_and(|matches| !matches[index]) {
+                    return None;
+                }
+                // Skip nodes outside of the bbox filter
+                match bbox {
+                    Some(bbox) if !bbox.contains(node_ref.lat(), node_ref.lon()) => None,
+                    _ => Some((index, node_ref)),
+                }
+            })
+    }
+    // Check if any node is left after applying the bbox filter
+    #[cfg(feature = "std")]
+    pub(crate) fn has_filtered_nodes(&self) -> bool {
+        self.iter().next().is_some()
+    }
+    /// Get the number of [`NodeRef`]
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+    /// Check if there are no [`NodeRef`]
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+    /// Classifies all nodes at once by whether they have any of the `(key, value)` tags,
+    /// see [`WayBlock::has_any_tag`]
+    pub fn has_any_tag(&self, tags: &[(&str, &str)]) -> Vec<bool> {
+        let pairs = resolve_tag_pairs(&self.table, &self.cached_tag_ids, tags);
+        self.nodes
+            .iter()
+            .map(|node| has_any_tag_pair(&node.keys, &node.vals, &pairs))
+            .collect()
+    }
+    /// Get all latitudes of the block, decoded in bulk like [`NodeRef::lat`]
+    pub fn latitudes(&self) -> Vec<f64> {
+        self.nodes
+            .iter()
+            .map(|node| (node.lat * self.granularity + self.lat_offset) as f64 * 1e-9)
+            .collect()
+    }
+    /// Get all longitudes of the block, decoded in bulk like [`NodeRef::lon`]
+    pub fn longitudes(&self) -> Vec<f64> {
+        self.nodes
+            .iter()
+            .map(|node| (node.lon * self.granularity + self.lon_offset) as f64 * 1e-9)
+            .collect()
     }
-    /// Get the number of [`NodeRef`]
-    pub fn len(&self) -> usize {
-        self.nodes.len()
+    /// Get all coordinates of the block as `(lat, lon)`, decoded in bulk.
+    /// Like the other bulk accessors, this ignores the bbox filter
+    pub fn coords(&self) -> Vec<(f64, f64)> {
+        self.latitudes()
+            .into_iter()
+            .zip(self.longitudes())
+            .collect()
+    }
+    /// Get all coordinates of the block as `(x, y)` in meters east and north of the origin `(lat0, lon0)`
+    /// (a local tangent plane), decoded in bulk and ignoring the bbox filter like [`coords`](Self::coords).
+    ///
+    /// Uses the equirectangular approximation on a sphere: the error stays below 0.1% within about 5km of
+    /// the origin and below 1% within about 50km at mid latitudes, so it is meant for city scale analysis.
+    /// It grows toward the poles, and longitudes are not wrapped at the antimeridian.
+    #[cfg(feature = "std")]
+    pub fn coords_relative_to(&self, lat0: f64, lon0: f64) -> Vec<(f64, f64)> {
+        relative_coords(self.latitudes(), self.longitudes(), lat0, lon0)
+    }
+    /// Get the ids, latitudes and longitudes of all nodes as columns, decoded in bulk
+    pub fn ids_and_coords(&self) -> (Vec<i64>, Vec<f64>, Vec<f64>) {
+        let ids = self.nodes.iter().map(|node| node.id).collect();
+        (ids, self.latitudes(), self.longitudes())
     }
-    /// Helper method for node bindings.
-    #[cfg(feature = "node_bindings")]
+    /// Helper method for node and python bindings.
+    #[cfg(any(feature = "node_bindings", feature = "python_bindings"))]
     pub fn get_string_table(&self) -> Vec<String> {
         self.table
             .iter()
-            .map(|cow| unsafe { std::str::from_utf8_unchecked(cow.as_ref()) }.to_owned())
+            .map(|cow| unsafe { core::str::from_utf8_unchecked(cow.as_ref()) }.to_owned())
             .collect()
     }
-    /// Helper method for node bindings.
-    #[cfg(feature = "node_bindings")]
-    pub fn get_raw_data(&self) -> (Vec<i64>, Vec<f64>, Vec<f64>, Vec<u32>, Vec<u32>, Vec<u32>) {
+    /// Helper method for node and python bindings.
+    /// Returns the string table as concatenated bytes and the offsets of every string in it.
+    #[cfg(any(feature = "node_bindings", feature = "python_bindings"))]
+    pub fn get_raw_string_table(&self) -> (Vec<u8>, Vec<u32>) {
+        raw_string_table(&self.table)
+    }
+    /// Helper method for the node and python bindings and the Arrow conversion.
+    #[cfg(any(
+        feature = "node_bindings",
+        feature = "python_bindings",
+        feature = "arrow"
+    ))]
+    pub fn get_raw_data(&self) -> RawNodeData {
         let len = self.nodes.len();
 
-        let mut ids = Vec::with_capacity(len);
-        let mut lats = Vec::with_capacity(len);
-        let mut lons = Vec::with_capacity(len);
+        // decoded in bulk with granularity and offsets, exactly like the coordinates of dense nodes
+        let (ids, lats, lons) = self.ids_and_coords();
 
         let mut key_ids = Vec::new();
         let mut val_ids = Vec::new();
         let mut kv_offsets = Vec::with_capacity(len + 1);
         kv_offsets.push(0);
 
-        // accumulators for delta decoding
-        let mut last_lat = 0i64;
-        let mut last_lon = 0i64;
-
-        let use_cache = TAG_KEYS_FILTER.get().is_some();
-
         for node in self.nodes.iter() {
-            ids.push(node.id);
-
-            last_lat += node.lat;
-            last_lon += node.lon;
-            lats.push(last_lat as f64 * 1e-9);
-            lons.push(last_lon as f64 * 1e-9);
-
             // append all tags for this node
-            for (k, v) in node.keys.iter().zip(node.vals.iter()) {
-                if !use_cache || self.cached_tag_ids.contains(k) {
-                    key_ids.push(*k);
-                    val_ids.push(*v);
-                }
-            }
+            self.cached_tag_ids
+                .extend_kept(&node.keys, &node.vals, &mut key_ids, &mut val_ids);
             kv_offsets.push(key_ids.len() as u32);
         }
 
         (ids, lats, lons, key_ids, val_ids, kv_offsets)
     }
 }
+
+impl fmt::Display for NodeBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NodeBlock({} nodes)", self.len())
+    }
+}
+
+impl fmt::Debug for NodeBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
 /// A Reference to a [`Node`]
 #[derive(Debug)]
 pub struct NodeRef<'a> {
-    node: &'a Node,
-    cached_tag_ids: &'a [u32],
-    table: &'a [Cow<'static, [u8]>],
-    prev_lat: i64,
-    prev_lon: i64,
+    pub(crate) node: &'a Node,
+    cached_tag_ids: &'a CachedTagIds,
+    table: &'a [Arc<[u8]>],
+    granularity: i64,
+    lat_offset: i64,
+    lon_offset: i64,
+    date_granularity: i64,
 }
 impl<'a> NodeRef<'a> {
     /// Get ID
@@ -320,17 +1488,44 @@ impl<'a> NodeRef<'a> {
     pub fn id(&self) -> i64 {
         self.node.id
     }
-    /// Get Latitude
+    /// Get Latitude, decoded with the granularity and offsets of the block, see [`DenseNodeRef::lat`]
     #[inline]
-    pub fn lat(&mut self) -> f64 {
-        self.prev_lat += self.node.lat;
-        self.prev_lat as f64 * 1e-9
+    pub fn lat(&self) -> f64 {
+        self.lat_nanodegrees() as f64 * 1e-9
     }
     /// Get Longitude
     #[inline]
-    pub fn lon(&mut self) -> f64 {
-        self.prev_lon += self.node.lon;
-        self.prev_lon as f64 * 1e-9
+    pub fn lon(&self) -> f64 {
+        self.lon_nanodegrees() as f64 * 1e-9
+    }
+    /// Get Latitude in units of `1 / scale` degrees (e.g. 1_000_000 for microdegrees), truncated toward zero.
+    /// Computed from the nanodegrees of the file without a float round trip
+    #[inline]
+    pub fn lat_scaled(&self, scale: i64) -> i64 {
+        scale_nanodegrees(self.lat_nanodegrees(), scale, false)
+    }
+    /// Same as [`NodeRef::lat_scaled`], but rounded half away from zero
+    #[inline]
+    pub fn lat_scaled_rounded(&self, scale: i64) -> i64 {
+        scale_nanodegrees(self.lat_nanodegrees(), scale, true)
+    }
+    /// Get Longitude in units of `1 / scale` degrees, truncated toward zero, see [`NodeRef::lat_scaled`]
+    #[inline]
+    pub fn lon_scaled(&self, scale: i64) -> i64 {
+        scale_nanodegrees(self.lon_nanodegrees(), scale, false)
+    }
+    /// Same as [`NodeRef::lon_scaled`], but rounded half away from zero
+    #[inline]
+    pub fn lon_scaled_rounded(&self, scale: i64) -> i64 {
+        scale_nanodegrees(self.lon_nanodegrees(), scale, true)
+    }
+    #[inline]
+    fn lat_nanodegrees(&self) -> i64 {
+        self.node.lat * self.granularity + self.lat_offset
+    }
+    #[inline]
+    fn lon_nanodegrees(&self) -> i64 {
+        self.node.lon * self.granularity + self.lon_offset
     }
     /// Get Iterator over (key, value) pairs
     #[inline]
@@ -341,7 +1536,35 @@ impl<'a> NodeRef<'a> {
             table: self.table,
             pos: 0,
             cached_tag_ids: self.cached_tag_ids,
-            use_cache: TAG_KEYS_FILTER.get().is_some(),
+        }
+    }
+    /// Same as [`tags`](Self::tags), but replaces invalid UTF-8, see [`WayRef::tags_lossy`]
+    #[inline]
+    pub fn tags_lossy(&self) -> impl FusedIterator<Item = (Cow<'_, str>, Cow<'_, str>)> {
+        let mut tags = self.tags();
+        lossy_tags(move || tags.next_raw())
+    }
+    /// Get the number of tags passing the tag filter without consuming an iterator,
+    /// so you can count before iterating over [`tags`](Self::tags)
+    #[inline]
+    pub fn tags_count(&self) -> usize {
+        self.cached_tag_ids.count_kept(&self.node.keys)
+    }
+    /// Get metadata (version, timestamp, changeset, user), if the file contains it
+    #[inline]
+    pub fn info(&self) -> Option<ElementInfo> {
+        self.node
+            .info
+            .as_ref()
+            .map(|info| ElementInfo::from_info(info, self.date_granularity))
+    }
+    /// Creates an [`OwnedNode`] that does not borrow from the block
+    pub fn to_owned(&self) -> OwnedNode {
+        OwnedNode {
+            id: self.node.id,
+            lat: self.lat_nanodegrees() as f64 * 1e-9,
+            lon: self.lon_nanodegrees() as f64 * 1e-9,
+            tags: owned_tags(self.tags()),
         }
     }
 }
@@ -352,36 +1575,75 @@ impl<'a> NodeRef<'a> {
 
 /// A block of [`Way`].
 /// Use .iter() on it to iterate over ways.
-#[derive(Debug)]
 pub struct WayBlock {
     pub(crate) ways: Arc<Vec<Way>>,
-    pub(crate) cached_tag_ids: Arc<Vec<u32>>,
-    pub(crate) table: Arc<Vec<Cow<'static, [u8]>>>,
+    pub(crate) cached_tag_ids: Arc<CachedTagIds>,
+    pub(crate) table: Arc<Vec<Arc<[u8]>>>,
+    pub(crate) raw_table: Option<Arc<RawStringTable>>,
+    pub(crate) date_granularity: i64,
+    pub(crate) matches: Option<Vec<bool>>,
+    #[cfg(feature = "std")]
+    pub(crate) compression: Compression,
+    // bounding box the blob of the block claims for its nodes, see ElementBlock::bbox
+    #[cfg(feature = "std")]
+    pub(crate) bbox_hint: Option<BBox>,
 }
 impl WayBlock {
     /// Creates an iterator over [`WayRef`]
-    pub fn iter(&self) -> impl Iterator<Item = WayRef<'_>> {
-        self.ways.iter().map(move |way| WayRef {
-            way,
-            cached_tag_ids: &self.cached_tag_ids,
-            table: &self.table,
-        })
+    pub fn iter(&self) -> impl FusedIterator<Item = WayRef<'_>> {
+        let matches = self.matches.as_deref();
+        self.ways
+            .iter()
+            .enumerate()
+            .filter(move |(index, _)| matches.is_none_or(|matches| matches[*index]))
+            .map(move |(_, way)| WayRef {
+                way,
+                cached_tag_ids: &self.cached_tag_ids,
+                table: &self.table,
+                date_granularity: self.date_granularity,
+            })
     }
     /// Get the number of [`WayRef`]
     pub fn len(&self) -> usize {
         self.ways.len()
     }
-    /// Helper method for node bindings.
-    #[cfg(feature = "node_bindings")]
+    /// Check if there are no [`WayRef`]
+    pub fn is_empty(&self) -> bool {
+        self.ways.is_empty()
+    }
+    /// Classifies all ways at once by whether they have any of the `(key, value)` tags,
+    /// e.g. `("highway", "primary")` for routing. The tags are looked up in the string table
+    /// once per block, so every way only compares ids instead of resolving its tags.
+    /// Keys outside of the tag filter never match. The result holds one entry per way
+    /// by index like the bulk accessors, regardless of the predicate of the reader.
+    pub fn has_any_tag(&self, tags: &[(&str, &str)]) -> Vec<bool> {
+        let pairs = resolve_tag_pairs(&self.table, &self.cached_tag_ids, tags);
+        self.ways
+            .iter()
+            .map(|way| has_any_tag_pair(&way.keys, &way.vals, &pairs))
+            .collect()
+    }
+    /// Helper method for node and python bindings.
+    #[cfg(any(feature = "node_bindings", feature = "python_bindings"))]
     pub fn get_string_table(&self) -> Vec<String> {
         self.table
             .iter()
-            .map(|cow| unsafe { std::str::from_utf8_unchecked(cow.as_ref()) }.to_owned())
+            .map(|cow| unsafe { core::str::from_utf8_unchecked(cow.as_ref()) }.to_owned())
             .collect()
     }
-    /// Helper method for node bindings.
-    #[cfg(feature = "node_bindings")]
-    pub fn get_raw_data(&self) -> (Vec<i64>, Vec<u32>, Vec<u32>, Vec<u32>, Vec<i64>, Vec<u32>) {
+    /// Helper method for node and python bindings.
+    /// Returns the string table as concatenated bytes and the offsets of every string in it.
+    #[cfg(any(feature = "node_bindings", feature = "python_bindings"))]
+    pub fn get_raw_string_table(&self) -> (Vec<u8>, Vec<u32>) {
+        raw_string_table(&self.table)
+    }
+    /// Helper method for the node and python bindings and the Arrow conversion.
+    #[cfg(any(
+        feature = "node_bindings",
+        feature = "python_bindings",
+        feature = "arrow"
+    ))]
+    pub fn get_raw_data(&self) -> RawWayData {
         let len = self.ways.len();
         let mut ids = Vec::with_capacity(len);
 
@@ -394,18 +1656,12 @@ impl WayBlock {
         let mut node_offsets = Vec::with_capacity(len + 1);
         node_offsets.push(0);
 
-        let use_cache = TAG_KEYS_FILTER.get().is_some();
-
         for way in self.ways.iter() {
             ids.push(way.id);
 
             // append all tags for this node
-            for (k, v) in way.keys.iter().zip(way.vals.iter()) {
-                if !use_cache || self.cached_tag_ids.contains(k) {
-                    key_ids.push(*k);
-                    val_ids.push(*v);
-                }
-            }
+            self.cached_tag_ids
+                .extend_kept(&way.keys, &way.vals, &mut key_ids, &mut val_ids);
             kv_offsets.push(key_ids.len() as u32);
 
             // node_ids are delta encoded
@@ -420,12 +1676,26 @@ impl WayBlock {
         (ids, key_ids, val_ids, kv_offsets, node_ids, node_offsets)
     }
 }
+
+impl fmt::Display for WayBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "WayBlock({} ways)", self.len())
+    }
+}
+
+impl fmt::Debug for WayBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
 /// A Reference to a [`Way`]
 #[derive(Debug)]
 pub struct WayRef<'a> {
     way: &'a Way,
-    cached_tag_ids: &'a [u32],
-    table: &'a [Cow<'static, [u8]>],
+    cached_tag_ids: &'a CachedTagIds,
+    table: &'a [Arc<[u8]>],
+    date_granularity: i64,
 }
 impl<'a> WayRef<'a> {
     /// Get ID
@@ -435,13 +1705,100 @@ impl<'a> WayRef<'a> {
     }
     /// Get Iterator over node_ids
     #[inline]
-    pub fn node_ids(&self) -> impl Iterator<Item = i64> + '_ {
+    pub fn node_ids(&self) -> impl FusedIterator<Item = i64> + '_ {
         let mut last_id = 0i64;
         self.way.refs.iter().map(move |delta| {
             last_id += *delta;
             last_id
         })
     }
+    /// Check that all node_ids are accepted by the policy, returns the first rejected one otherwise
+    pub fn validate_node_ids(&self, policy: IdPolicy) -> Result<(), OsmError> {
+        match self.node_ids().find(|id| !policy.accepts(*id)) {
+            Some(ref_id) => Err(OsmError::Corrupt {
+                element_type: MemberType::WAY,
+                element_id: self.id(),
+                ref_id,
+            }),
+            None => Ok(()),
+        }
+    }
+    /// Get the delta encoded node_ids as stored in the file, for your own bulk decoding
+    #[inline]
+    pub fn node_ids_raw(&self) -> &[i64] {
+        &self.way.refs
+    }
+    /// Get node_ids, decoded in bulk
+    #[inline]
+    pub fn node_ids_vec(&self) -> Vec<i64> {
+        simd::delta_decode_i64(&self.way.refs)
+    }
+    /// Check if the way has at least 2 node references, the minimum of a line.
+    /// Ways with less are data errors that break geometry assembly.
+    /// A closed way needs at least 4 references (the first one repeated last) to be a polygon.
+    #[inline]
+    pub fn is_valid_geometry(&self) -> bool {
+        self.way.refs.len() >= 2
+    }
+    /// Check if the way ends at the node it starts with, like the outline of a building.
+    /// Ways with less than 2 node references are never closed.
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        // the last id is the sum of all deltas, so the deltas after the first one sum up to 0
+        self.way.refs.len() >= 2 && self.way.refs[1..].iter().sum::<i64>() == 0
+    }
+    /// Get the area enclosed by a closed way by the shoelace formula, in square degrees of
+    /// (lon, lat), positive if the nodes run counter-clockwise and negative if clockwise.
+    /// Only the sign is meaningful on the globe, the size shrinks towards the poles.
+    /// Returns [`None`] if the way is not closed or `node_resolver` does not know a node.
+    ///
+    /// `node_resolver` returns the (lon, lat) of a node, like the resolvers of the `geometry` feature.
+    pub fn signed_area<N>(&self, node_resolver: N) -> Option<f64>
+    where
+        N: Fn(i64) -> Option<(f64, f64)>,
+    {
+        if !self.is_closed() {
+            return None;
+        }
+        let mut node_ids = self.node_ids();
+        // relative to the first node, so large coordinates do not cancel out the digits
+        let (lon0, lat0) = node_resolver(node_ids.next()?)?;
+        let mut prev = (0.0, 0.0);
+        let mut twice_area = 0.0;
+        for node_id in node_ids {
+            let (lon, lat) = node_resolver(node_id)?;
+            let point = (lon - lon0, lat - lat0);
+            twice_area += prev.0 * point.1 - point.0 * prev.1;
+            prev = point;
+        }
+        Some(twice_area / 2.0)
+    }
+    /// Check if the nodes of a closed way run clockwise, see [`signed_area`](Self::signed_area).
+    /// By convention outer rings of polygons run counter-clockwise and inner rings clockwise.
+    /// Returns [`None`] if the area is unknown or zero (e.g. all nodes on one line).
+    pub fn is_clockwise<N>(&self, node_resolver: N) -> Option<bool>
+    where
+        N: Fn(i64) -> Option<(f64, f64)>,
+    {
+        let area = self.signed_area(node_resolver)?;
+        (area != 0.0).then_some(area < 0.0)
+    }
+    /// Get the bounding box of the nodes of the way, e.g. to insert the way into an R-tree.
+    /// Nodes unknown to `node_resolver` are left out and the result is flagged as partial,
+    /// [`None`] is returned if no node could be resolved.
+    /// Ways crossing the antimeridian get a box spanning the whole longitude range between their nodes.
+    ///
+    /// `node_resolver` returns the (lon, lat) of a node, like the resolvers of the `geometry` feature.
+    pub fn bbox<N>(&self, node_resolver: N) -> Option<ElementBBox>
+    where
+        N: Fn(i64) -> Option<(f64, f64)>,
+    {
+        let mut builder = BBoxBuilder::default();
+        for node_id in self.node_ids() {
+            builder.add(node_resolver(node_id));
+        }
+        builder.finish()
+    }
     /// Get Iterator over (key, value) pairs
     #[inline]
     pub fn tags(&self) -> TagIter<'_> {
@@ -451,7 +1808,40 @@ impl<'a> WayRef<'a> {
             table: self.table,
             pos: 0,
             cached_tag_ids: self.cached_tag_ids,
-            use_cache: TAG_KEYS_FILTER.get().is_some(),
+        }
+    }
+    /// Get Iterator over (key, value) pairs like [`tags`](Self::tags), but invalid UTF-8 sequences are
+    /// replaced with U+FFFD instead of being assumed away. The strings are borrowed unless a replacement
+    /// was needed. Every key and value is validated, so this is slower than [`tags`](Self::tags).
+    ///
+    /// [`tags`](Self::tags), [`RelationRef::members`] and the `to_owned` conversions assume the string
+    /// table of the block is valid UTF-8. Only this iterator, [`TagIter::get_all`] and
+    /// [`RelationRef::members_vec`] are safe for files from untrusted writers.
+    #[inline]
+    pub fn tags_lossy(&self) -> impl FusedIterator<Item = (Cow<'_, str>, Cow<'_, str>)> {
+        let mut tags = self.tags();
+        lossy_tags(move || tags.next_raw())
+    }
+    /// Get the number of tags passing the tag filter without consuming an iterator,
+    /// so you can count before iterating over [`tags`](Self::tags)
+    #[inline]
+    pub fn tags_count(&self) -> usize {
+        self.cached_tag_ids.count_kept(&self.way.keys)
+    }
+    /// Get metadata (version, timestamp, changeset, user), if the file contains it
+    #[inline]
+    pub fn info(&self) -> Option<ElementInfo> {
+        self.way
+            .info
+            .as_ref()
+            .map(|info| ElementInfo::from_info(info, self.date_granularity))
+    }
+    /// Creates an [`OwnedWay`] that does not borrow from the block
+    pub fn to_owned(&self) -> OwnedWay {
+        OwnedWay {
+            id: self.way.id,
+            node_ids: self.node_ids_vec(),
+            tags: owned_tags(self.tags()),
         }
     }
 }
@@ -462,47 +1852,72 @@ impl<'a> WayRef<'a> {
 
 /// A block of [`Relation`].
 /// Use .iter() on it to iterate over relations.
-#[derive(Debug)]
 pub struct RelationBlock {
     pub(crate) relations: Arc<Vec<Relation>>,
-    pub(crate) cached_tag_ids: Arc<Vec<u32>>,
-    pub(crate) table: Arc<Vec<Cow<'static, [u8]>>>,
+    pub(crate) cached_tag_ids: Arc<CachedTagIds>,
+    pub(crate) table: Arc<Vec<Arc<[u8]>>>,
+    pub(crate) raw_table: Option<Arc<RawStringTable>>,
+    pub(crate) date_granularity: i64,
+    pub(crate) matches: Option<Vec<bool>>,
+    #[cfg(feature = "std")]
+    pub(crate) compression: Compression,
+    // bounding box the blob of the block claims for its nodes, see ElementBlock::bbox
+    #[cfg(feature = "std")]
+    pub(crate) bbox_hint: Option<BBox>,
 }
 impl RelationBlock {
     /// Creates an iterator over [`RelationRef`]
-    pub fn iter(&self) -> impl Iterator<Item = RelationRef<'_>> {
-        self.relations.iter().map(move |relation| RelationRef {
-            relation,
-            cached_tag_ids: &self.cached_tag_ids,
-            table: &self.table,
-        })
+    pub fn iter(&self) -> impl FusedIterator<Item = RelationRef<'_>> {
+        let matches = self.matches.as_deref();
+        self.relations
+            .iter()
+            .enumerate()
+            .filter(move |(index, _)| matches.is_none_or(|matches| matches[*index]))
+            .map(move |(_, relation)| RelationRef {
+                relation,
+                cached_tag_ids: &self.cached_tag_ids,
+                table: &self.table,
+                date_granularity: self.date_granularity,
+            })
     }
     /// Get the number of [`RelationRef`]
     pub fn len(&self) -> usize {
         self.relations.len()
     }
-    /// Helper method for node bindings.
-    #[cfg(feature = "node_bindings")]
+    /// Check if there are no [`RelationRef`]
+    pub fn is_empty(&self) -> bool {
+        self.relations.is_empty()
+    }
+    /// Classifies all relations at once by whether they have any of the `(key, value)` tags,
+    /// see [`WayBlock::has_any_tag`]
+    pub fn has_any_tag(&self, tags: &[(&str, &str)]) -> Vec<bool> {
+        let pairs = resolve_tag_pairs(&self.table, &self.cached_tag_ids, tags);
+        self.relations
+            .iter()
+            .map(|relation| has_any_tag_pair(&relation.keys, &relation.vals, &pairs))
+            .collect()
+    }
+    /// Helper method for node and python bindings.
+    #[cfg(any(feature = "node_bindings", feature = "python_bindings"))]
     pub fn get_string_table(&self) -> Vec<String> {
         self.table
             .iter()
-            .map(|cow| unsafe { std::str::from_utf8_unchecked(cow.as_ref()) }.to_owned())
+            .map(|cow| unsafe { core::str::from_utf8_unchecked(cow.as_ref()) }.to_owned())
             .collect()
     }
-    /// Helper method for node bindings.
-    #[cfg(feature = "node_bindings")]
-    pub fn get_raw_data(
-        &self,
-    ) -> (
-        Vec<i64>, // relation ids
-        Vec<u32>, // key ids
-        Vec<u32>, // val ids
-        Vec<u32>, // kv offsets
-        Vec<i64>, // member ids
-        Vec<u8>,  // member types
-        Vec<i32>, // member roles
-        Vec<u32>, // member offsets
-    ) {
+    /// Helper method for node and python bindings.
+    /// Returns the string table as concatenated bytes and the offsets of every string in it.
+    #[cfg(any(feature = "node_bindings", feature = "python_bindings"))]
+    pub fn get_raw_string_table(&self) -> (Vec<u8>, Vec<u32>) {
+        raw_string_table(&self.table)
+    }
+    /// Helper method for the node and python bindings and the Arrow conversion.
+    #[cfg(any(
+        feature = "node_bindings",
+        feature = "python_bindings",
+        feature = "arrow"
+    ))]
+    pub fn get_raw_data(&self) -> RawRelationData {
         let len = self.relations.len();
         let mut ids = Vec::with_capacity(len);
 
@@ -517,28 +1932,29 @@ impl RelationBlock {
         let mut member_offsets = Vec::with_capacity(len + 1);
         member_offsets.push(0);
 
-        let use_cache = TAG_KEYS_FILTER.get().is_some();
-
         for rel in self.relations.iter() {
             ids.push(rel.id);
 
             // append all tags for this node
-            for (k, v) in rel.keys.iter().zip(rel.vals.iter()) {
-                if !use_cache || self.cached_tag_ids.contains(k) {
-                    key_ids.push(*k);
-                    val_ids.push(*v);
-                }
-            }
+            self.cached_tag_ids
+                .extend_kept(&rel.keys, &rel.vals, &mut key_ids, &mut val_ids);
             kv_offsets.push(key_ids.len() as u32);
 
+            // the member columns stay aligned if a broken relation has fewer types or roles than ids
+            let member_count = rel
+                .memids
+                .len()
+                .min(rel.types.len())
+                .min(rel.roles_sid.len());
+
             // member_ids are delta encoded
             let mut last_member_id = 0i64;
-            for d in rel.memids.iter() {
+            for d in rel.memids[..member_count].iter() {
                 last_member_id += *d;
                 member_ids.push(last_member_id);
             }
-            member_types.extend(rel.types.iter().map(|t| *t as u8));
-            member_roles.extend_from_slice(&rel.roles_sid);
+            member_types.extend(rel.types[..member_count].iter().map(|t| *t as u8));
+            member_roles.extend_from_slice(&rel.roles_sid[..member_count]);
             member_offsets.push(member_ids.len() as u32);
         }
 
@@ -555,12 +1971,25 @@ impl RelationBlock {
     }
 }
 
+impl fmt::Display for RelationBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RelationBlock({} relations)", self.len())
+    }
+}
+
+impl fmt::Debug for RelationBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
 /// A Reference to a [`Relation`]
 #[derive(Debug)]
 pub struct RelationRef<'a> {
     relation: &'a Relation,
-    cached_tag_ids: &'a [u32],
-    table: &'a [Cow<'static, [u8]>],
+    cached_tag_ids: &'a CachedTagIds,
+    table: &'a [Arc<[u8]>],
+    date_granularity: i64,
 }
 impl<'a> RelationRef<'a> {
     /// Get ID
@@ -570,16 +1999,91 @@ impl<'a> RelationRef<'a> {
     }
     /// Get Iterator over [`RelationMember`]
     #[inline]
-    pub fn members(&self) -> impl Iterator<Item = RelationMember<'_>> {
+    pub fn members(&self) -> impl FusedIterator<Item = RelationMember<'_>> {
         RelationMemberIter {
             memids: &self.relation.memids,
             roles: &self.relation.roles_sid,
             types: &self.relation.types,
-            table: &self.table,
+            table: self.table,
             index: 0,
             prev_memid: 0,
         }
     }
+    /// Get Iterator over the members that fetches their elements only on demand, e.g. to load the geometry
+    /// of the `outer` ways of a large boundary without loading all members up front.
+    /// `resolver` returns the element of a type and id, e.g. backed by
+    /// [`OsmReader::get_way`](crate::OsmReader::get_way) (inside a `RefCell`, lookups need `&mut`)
+    /// or by a location store. A member that is the relation itself is yielded as [`ResolvedMember::Cycle`],
+    /// a member the resolver does not know resolves to [`None`].
+    pub fn resolved_members<'r, R>(
+        &'r self,
+        resolver: &'r R,
+    ) -> impl FusedIterator<Item = ResolvedMember<'r, R>>
+    where
+        R: Fn(MemberType, i64) -> Option<Element>,
+    {
+        let id = self.id();
+        self.members().map(move |member| {
+            if member.member_type() == MemberType::RELATION && member.id() == id {
+                ResolvedMember::Cycle(member)
+            } else {
+                ResolvedMember::Lazy(LazyMember::new(member, resolver))
+            }
+        })
+    }
+    /// Get the bounding box of the node members and the nodes of the way members, see [`WayRef::bbox`].
+    /// Unresolvable members and nodes as well as relation members (not followed, they may form cycles)
+    /// are left out and flag the result as partial, [`None`] is returned if nothing could be resolved.
+    ///
+    /// `way_resolver` returns the node ids of a way, `node_resolver` returns the (lon, lat) of a node,
+    /// like the resolvers of the `geometry` feature.
+    pub fn bbox<W, N>(&self, way_resolver: W, node_resolver: N) -> Option<ElementBBox>
+    where
+        W: Fn(i64) -> Option<Vec<i64>>,
+        N: Fn(i64) -> Option<(f64, f64)>,
+    {
+        let mut builder = BBoxBuilder::default();
+        for member in self.members() {
+            match member.member_type() {
+                MemberType::NODE => builder.add(node_resolver(member.id())),
+                MemberType::WAY => match way_resolver(member.id()) {
+                    Some(node_ids) => {
+                        for node_id in node_ids {
+                            builder.add(node_resolver(node_id));
+                        }
+                    }
+                    None => builder.partial = true,
+                },
+                MemberType::RELATION => builder.partial = true,
+            }
+        }
+        builder.finish()
+    }
+    /// Get all members as (id, type, role), ids decoded in bulk (e.g. for large route relations).
+    /// Roles are validated, one that is not valid UTF-8 is empty like a missing role.
+    pub fn members_vec(&self) -> Vec<(i64, MemberType, &str)> {
+        simd::delta_decode_i64(&self.relation.memids)
+            .into_iter()
+            .zip(self.relation.types.iter().copied())
+            .zip(self.relation.roles_sid.iter())
+            .map(|((id, member_type), &role_sid)| {
+                // role_sid are uint32 in reality, see RelationMemberIter
+                let role = table_str(self.table, role_sid as u32).unwrap_or("");
+                (id, member_type, role)
+            })
+            .collect()
+    }
+    /// Check that the ids of all members are accepted by the policy, returns the first rejected one otherwise
+    pub fn validate_members(&self, policy: IdPolicy) -> Result<(), OsmError> {
+        match self.members().find(|member| !policy.accepts(member.id())) {
+            Some(member) => Err(OsmError::Corrupt {
+                element_type: MemberType::RELATION,
+                element_id: self.id(),
+                ref_id: member.id(),
+            }),
+            None => Ok(()),
+        }
+    }
     /// Get Iterator over (key, value) pairs
     #[inline]
     pub fn tags(&self) -> TagIter<'_> {
@@ -589,7 +2093,41 @@ impl<'a> RelationRef<'a> {
             table: self.table,
             pos: 0,
             cached_tag_ids: self.cached_tag_ids,
-            use_cache: TAG_KEYS_FILTER.get().is_some(),
+        }
+    }
+    /// Same as [`tags`](Self::tags), but replaces invalid UTF-8, see [`WayRef::tags_lossy`]
+    #[inline]
+    pub fn tags_lossy(&self) -> impl FusedIterator<Item = (Cow<'_, str>, Cow<'_, str>)> {
+        let mut tags = self.tags();
+        lossy_tags(move || tags.next_raw())
+    }
+    /// Get the number of tags passing the tag filter without consuming an iterator,
+    /// so you can count before iterating over [`tags`](Self::tags)
+    #[inline]
+    pub fn tags_count(&self) -> usize {
+        self.cached_tag_ids.count_kept(&self.relation.keys)
+    }
+    /// Get metadata (version, timestamp, changeset, user), if the file contains it
+    #[inline]
+    pub fn info(&self) -> Option<ElementInfo> {
+        self.relation
+            .info
+            .as_ref()
+            .map(|info| ElementInfo::from_info(info, self.date_granularity))
+    }
+    /// Creates an [`OwnedRelation`] that does not borrow from the block
+    pub fn to_owned(&self) -> OwnedRelation {
+        OwnedRelation {
+            id: self.relation.id,
+            members: self
+                .members()
+                .map(|member| OwnedRelationMember {
+                    id: member.id(),
+                    member_type: member.member_type(),
+                    role: member.role().to_owned(),
+                })
+                .collect(),
+            tags: owned_tags(self.tags()),
         }
     }
 }
@@ -627,7 +2165,7 @@ struct RelationMemberIter<'a> {
     memids: &'a [i64],
     roles: &'a [i32],
     types: &'a [MemberType],
-    table: &'a [Cow<'static, [u8]>],
+    table: &'a [Arc<[u8]>],
     index: usize,
     prev_memid: i64,
 }
@@ -644,7 +2182,7 @@ impl<'a> Iterator for RelationMemberIter<'a> {
 
         // scary scary i32 as usize conversion in unsafe block - but role_sid are uint32 in reality,
         // just a mistake when proto format was defined
-        let role = unsafe { std::str::from_utf8_unchecked(&self.table[role_sid as usize]) };
+        let role = unsafe { core::str::from_utf8_unchecked(&self.table[role_sid as usize]) };
 
         Some(RelationMember {
             memid: self.prev_memid,
@@ -654,38 +2192,66 @@ impl<'a> Iterator for RelationMemberIter<'a> {
     }
 }
 
+impl FusedIterator for RelationMemberIter<'_> {}
+
 // --------------------------- TAGS_ITER ---------------------------
 // --------------------------- TAGS_ITER ---------------------------
 // --------------------------- TAGS_ITER ---------------------------
 
+// Turns the raw tags of a tag iterator into strings with invalid UTF-8 replaced, see WayRef::tags_lossy
+#[inline]
+fn lossy_tags<'a>(
+    mut next_raw: impl FnMut() -> Option<(&'a [u8], &'a [u8])>,
+) -> impl FusedIterator<Item = (Cow<'a, str>, Cow<'a, str>)> {
+    core::iter::from_fn(move || {
+        let (k, v) = next_raw()?;
+        Some((String::from_utf8_lossy(k), String::from_utf8_lossy(v)))
+    })
+    .fuse()
+}
+
 /// An iterator that yields (key, value) tag pair
+#[derive(Clone)]
 pub struct DenseNodeTagIter<'a> {
     slice: &'a [i32],
-    table: &'a [Cow<'static, [u8]>],
+    table: &'a [Arc<[u8]>],
     pos: usize,
-    cached_tag_ids: &'a [u32],
-    use_cache: bool,
+    cached_tag_ids: &'a CachedTagIds,
 }
 impl<'a> Iterator for DenseNodeTagIter<'a> {
     type Item = (&'a str, &'a str);
 
+    #[inline]
     fn next(&mut self) -> Option<Self::Item> {
-        while self.pos < self.slice.len() && self.slice[self.pos] != 0 {
-            let k = self.slice[self.pos] as usize;
-            let v = self.slice[self.pos + 1] as usize;
+        let (k, v) = self.next_raw()?;
+        let k = unsafe { core::str::from_utf8_unchecked(k) };
+        let v = unsafe { core::str::from_utf8_unchecked(v) };
+        Some((k, v))
+    }
+}
+
+impl FusedIterator for DenseNodeTagIter<'_> {}
+impl<'a> DenseNodeTagIter<'a> {
+    // The string table ids of the next tag passing the tag filter
+    #[inline]
+    fn next_ids(&mut self) -> Option<(u32, u32)> {
+        while self.pos + 1 < self.slice.len() && self.slice[self.pos] != 0 {
+            let k = self.slice[self.pos] as u32;
+            let v = self.slice[self.pos + 1] as u32;
             self.pos += 2;
-            if self.use_cache && !self.cached_tag_ids.contains(&(k as u32)) {
+            if !self.cached_tag_ids.keeps(k) {
                 continue;
             }
-            return Some((
-                unsafe { std::str::from_utf8_unchecked(&self.table[k]) },
-                unsafe { std::str::from_utf8_unchecked(&self.table[v]) },
-            ));
+            return Some((k, v));
         }
         None
     }
-}
-impl<'a> DenseNodeTagIter<'a> {
+    // The bytes of the next tag passing the tag filter
+    #[inline]
+    fn next_raw(&mut self) -> Option<(&'a [u8], &'a [u8])> {
+        let (k, v) = self.next_ids()?;
+        Some((&self.table[k as usize], &self.table[v as usize]))
+    }
     /// Get the number of tag pairs
     #[inline]
     pub fn len(mut self) -> usize {
@@ -695,44 +2261,110 @@ impl<'a> DenseNodeTagIter<'a> {
         }
         count
     }
+    /// Check if there are no tag pairs
+    #[inline]
+    pub fn is_empty(mut self) -> bool {
+        self.next().is_none()
+    }
     /// Check if all applied filter keys are present in the iterator.
-    /// We assume the same key cannot appear more than once.
-    /// Essentially the same as calling .len() == <FILTER_COUNT_APPLIED>
+    /// We assume the same key cannot appear more than once, so the scan stops as soon as
+    /// <FILTER_COUNT_APPLIED> tags passed the filter instead of counting every remaining tag.
     #[inline]
     pub fn has_all_filter_keys(self) -> bool {
-        Some(&self.len()) == TAG_KEYS_FILTER_COUNT.get()
+        let cached_tag_ids = self.cached_tag_ids;
+        let key_count = cached_tag_ids.key_count;
+        cached_tag_ids.ids.is_some() && self.take(key_count).count() == key_count
+    }
+    /// Get the values of `keys` in the same order ([`None`] for missing keys) by scanning the tags once,
+    /// a key listed more than once gets its value at every position. With a tag filter, `keys` are
+    /// resolved to string table ids once and tags are matched by id without comparing strings.
+    /// Values are validated, so one that is not valid UTF-8 is [`None`] as well.
+    #[inline]
+    pub fn get_all(mut self, keys: &[&str]) -> Vec<Option<&'a str>> {
+        let mut values = alloc::vec![None; keys.len()];
+        let table = self.table;
+        match self.cached_tag_ids.resolve(table, keys) {
+            Some(key_ids) => {
+                while let Some((k, v)) = self.next_ids() {
+                    let value = || table_str(table, v);
+                    fill_values(&mut values, &key_ids, |&id| id == Some(k), value);
+                }
+            }
+            None => {
+                while let Some((k, v)) = self.next_raw() {
+                    let value = || core::str::from_utf8(v).ok();
+                    fill_values(&mut values, keys, |key| key.as_bytes() == k, value);
+                }
+            }
+        }
+        values
+    }
+}
+
+// Sets value at every position of keys that matches, see TagIter::get_all
+#[inline]
+fn fill_values<'a, K>(
+    values: &mut [Option<&'a str>],
+    keys: &[K],
+    matches: impl Fn(&K) -> bool,
+    value: impl Fn() -> Option<&'a str>,
+) {
+    for (slot, k) in values.iter_mut().zip(keys) {
+        if matches(k) {
+            *slot = value();
+        }
     }
 }
 
+// The string at id of a string table, None if it is missing or not valid UTF-8
+#[inline]
+fn table_str(table: &[Arc<[u8]>], id: u32) -> Option<&str> {
+    core::str::from_utf8(table.get(id as usize)?).ok()
+}
+
 /// An iterator that yields (key, value) tag pair
+#[derive(Clone)]
 pub struct TagIter<'a> {
     keys: &'a [u32],
     vals: &'a [u32],
-    table: &'a [Cow<'static, [u8]>],
+    table: &'a [Arc<[u8]>],
     pos: usize,
-    cached_tag_ids: &'a [u32],
-    use_cache: bool,
+    cached_tag_ids: &'a CachedTagIds,
 }
 impl<'a> Iterator for TagIter<'a> {
     type Item = (&'a str, &'a str);
 
+    #[inline]
     fn next(&mut self) -> Option<Self::Item> {
+        let (k, v) = self.next_raw()?;
+        let k = unsafe { core::str::from_utf8_unchecked(k) };
+        let v = unsafe { core::str::from_utf8_unchecked(v) };
+        Some((k, v))
+    }
+}
+
+impl FusedIterator for TagIter<'_> {}
+impl<'a> TagIter<'a> {
+    // The string table ids of the next tag passing the tag filter
+    #[inline]
+    fn next_ids(&mut self) -> Option<(u32, u32)> {
         while self.pos < self.keys.len() {
-            let k = self.keys[self.pos] as usize;
-            let v = self.vals[self.pos] as usize;
+            let k = self.keys[self.pos];
+            let v = self.vals[self.pos];
             self.pos += 1;
-            if self.use_cache && !self.cached_tag_ids.contains(&(k as u32)) {
+            if !self.cached_tag_ids.keeps(k) {
                 continue;
             }
-            return Some((
-                unsafe { std::str::from_utf8_unchecked(&self.table[k]) },
-                unsafe { std::str::from_utf8_unchecked(&self.table[v]) },
-            ));
+            return Some((k, v));
         }
         None
     }
-}
-impl<'a> TagIter<'a> {
+    // The bytes of the next tag passing the tag filter
+    #[inline]
+    fn next_raw(&mut self) -> Option<(&'a [u8], &'a [u8])> {
+        let (k, v) = self.next_ids()?;
+        Some((&self.table[k as usize], &self.table[v as usize]))
+    }
     /// Get the number of tag pairs
     #[inline]
     pub fn len(mut self) -> usize {
@@ -742,24 +2374,418 @@ impl<'a> TagIter<'a> {
         }
         count
     }
+    /// Check if there are no tag pairs
+    #[inline]
+    pub fn is_empty(mut self) -> bool {
+        self.next().is_none()
+    }
     /// Check if all applied filter keys are present in the iterator.
-    /// We assume the same key cannot appear more than once.
-    /// Essentially the same as calling .len() == <FILTER_COUNT_APPLIED>
+    /// We assume the same key cannot appear more than once, so the scan stops as soon as
+    /// <FILTER_COUNT_APPLIED> tags passed the filter instead of counting every remaining tag.
     #[inline]
     pub fn has_all_filter_keys(self) -> bool {
-        Some(&self.len()) == TAG_KEYS_FILTER_COUNT.get()
+        let cached_tag_ids = self.cached_tag_ids;
+        let key_count = cached_tag_ids.key_count;
+        cached_tag_ids.ids.is_some() && self.take(key_count).count() == key_count
     }
+    /// Get the values of `keys` in the same order ([`None`] for missing keys) by scanning the tags once,
+    /// a key listed more than once gets its value at every position. With a tag filter, `keys` are
+    /// resolved to string table ids once and tags are matched by id without comparing strings.
+    /// Values are validated, so one that is not valid UTF-8 is [`None`] as well.
+    #[inline]
+    pub fn get_all(mut self, keys: &[&str]) -> Vec<Option<&'a str>> {
+        let mut values = alloc::vec![None; keys.len()];
+        let table = self.table;
+        match self.cached_tag_ids.resolve(table, keys) {
+            Some(key_ids) => {
+                while let Some((k, v)) = self.next_ids() {
+                    let value = || table_str(table, v);
+                    fill_values(&mut values, &key_ids, |&id| id == Some(k), value);
+                }
+            }
+            None => {
+                while let Some((k, v)) = self.next_raw() {
+                    let value = || core::str::from_utf8(v).ok();
+                    fill_values(&mut values, keys, |key| key.as_bytes() == k, value);
+                }
+            }
+        }
+        values
+    }
+}
+
+/// An Iterator that yields [`ElementBlock`], or what [`OsmReader::map_blocks`](crate::OsmReader::map_blocks)
+/// turns them into
+#[cfg(feature = "std")]
+pub struct ElementBlockIter<T = ElementBlock> {
+    // every block comes with the bytes it holds of the in flight budget
+    pub(crate) rx: Receiver<(T, usize)>,
+    pub(crate) budget: Option<Arc<InFlightBudget>>,
+    // tells the background threads to stop before the end of the file
+    pub(crate) stop: Arc<AtomicBool>,
+    // set once the reader stopped after the region of the bbox filter, see with_region_early_exit
+    pub(crate) stopped_after_region: Arc<AtomicBool>,
+    pub(crate) reader_thread: Option<JoinHandle<std::io::Result<()>>>,
+    pub(crate) parser_thread: Option<JoinHandle<std::io::Result<()>>>,
+    pub(crate) skipped_blobs: SkippedBlobs,
+    #[cfg(feature = "stats")]
+    pub(crate) stats: Arc<StatsCollector>,
 }
 
-/// An Iterator that yields [`ElementBlock`]
-pub struct ElementBlockIter {
-    pub(crate) rx: Receiver<ElementBlock>,
+#[cfg(feature = "std")]
+impl<T> ElementBlockIter<T> {
+    /// Waits for the background threads and returns the first error they ran into, which the
+    /// iterator itself skips: a read error (e.g. [`OsmError::Truncated`]) ends the stream early,
+    /// a blob that cannot be decompressed, parsed or passes no [`IdPolicy`](crate::IdPolicy) is left out.
+    /// Call it after the last block to make sure the whole file was read. Called earlier, reading stops,
+    /// the remaining blocks are dropped and only the errors up to then are returned.
+    pub fn finish(mut self) -> std::io::Result<()> {
+        self.stop();
+        let reader = Self::join(self.reader_thread.take());
+        let parser = Self::join(self.parser_thread.take());
+        reader.and(parser)
+    }
+
+    /// Get the number of blobs the reader skipped so far because they hold no OSMData, per blob type,
+    /// see [`OsmReader::skipped_blob_types`](crate::OsmReader::skipped_blob_types).
+    /// The counts are complete once the iterator is exhausted.
+    pub fn skipped_blob_types(&self) -> HashMap<String, u64> {
+        self.skipped_blobs.lock().unwrap().clone()
+    }
+
+    /// Check if reading stopped before the end of the file because it passed the region of the bbox
+    /// filter, see [`OsmReader::with_region_early_exit`](crate::OsmReader::with_region_early_exit).
+    /// Blobs after the region were not read then, nodes inside the box among them are missing.
+    /// The flag is final once the iterator is exhausted.
+    pub fn stopped_after_region(&self) -> bool {
+        self.stopped_after_region.load(Ordering::Relaxed)
+    }
+
+    /// Get the blob counts, sizes and timings of the run so far, see [`ParseStats`].
+    /// They are complete once the iterator is exhausted, before that they cover the blobs parsed so far.
+    /// Read them before [`ElementBlockIter::finish`], which consumes the iterator.
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> ParseStats {
+        self.stats.snapshot()
+    }
+
+    // Disconnects the channel and unblocks the workers, so the threads end soon
+    fn stop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.rx = crossbeam_channel::never();
+        if let Some(budget) = &self.budget {
+            budget.close();
+        }
+    }
+
+    fn join(thread: Option<JoinHandle<std::io::Result<()>>>) -> std::io::Result<()> {
+        match thread.map(|thread| thread.join()) {
+            Some(Ok(result)) => result,
+            Some(Err(_)) => Err(std::io::Error::other("A background thread panicked")),
+            None => Ok(()),
+        }
+    }
 }
 
-impl Iterator for ElementBlockIter {
-    type Item = ElementBlock;
+#[cfg(feature = "std")]
+impl<T> Iterator for ElementBlockIter<T> {
+    type Item = T;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.rx.recv().ok()
+        let (block, size) = self.rx.recv().ok()?;
+        if let Some(budget) = &self.budget {
+            budget.release(size);
+        }
+        Some(block)
+    }
+}
+
+// recv fails for good once all senders are dropped and the channel is drained
+#[cfg(feature = "std")]
+impl<T> FusedIterator for ElementBlockIter<T> {}
+
+#[cfg(feature = "std")]
+impl<T> Drop for ElementBlockIter<T> {
+    // Stops and joins the threads, so the reader and its filters are freed once the iterator is.
+    // On a rayon worker the threads are left to end on their own,
+    // as blocking the worker could starve the pool the parsing runs on.
+    fn drop(&mut self) {
+        self.stop();
+        if rayon::current_thread_index().is_none() {
+            let _ = Self::join(self.reader_thread.take());
+            let _ = Self::join(self.parser_thread.take());
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::encode::encode_message;
+    use crate::{
+        decode_blob, ElementBlock, ElementInfo, FixtureInfo, MemberType, OsmReader, OwnedNode,
+        PbfFixture, PrimitiveBlock, PrimitiveGroup, Relation, StringTable, Way,
+    };
+    use std::borrow::Cow;
+
+    fn tagged_node() -> OwnedNode {
+        let tags = [("amenity", "cafe"), ("name", "A"), ("addr:street", "S")];
+        OwnedNode {
+            id: 1,
+            lat: 0.0,
+            lon: 0.0,
+            tags: tags.map(|(k, v)| (k.to_owned(), v.to_owned())).to_vec(),
+        }
+    }
+
+    // get_all of the first node, read from dense or plain nodes with an optional tag filter
+    fn get_all(plain: bool, tag_filter: Option<&[&str]>, keys: &[&str]) -> Vec<Option<String>> {
+        let mut fixture = PbfFixture::new([tagged_node().into()]);
+        if plain {
+            fixture = fixture.with_plain_nodes();
+        }
+        let file = fixture.to_temp_file(&format!("get-all-{plain}-{}", tag_filter.is_some()));
+        let mut reader = OsmReader::from_path(&*file).unwrap();
+        if let Some(tags) = tag_filter {
+            reader = reader.with_tag_filter(tags).unwrap();
+        }
+        let block = reader.blocks().next().unwrap();
+        let owned =
+            |values: Vec<Option<&str>>| values.into_iter().map(|v| v.map(str::to_owned)).collect();
+        match &block {
+            ElementBlock::DenseNodeBlock(block) => {
+                let node = block.iter().next().unwrap();
+                owned(node.tags().get_all(keys))
+            }
+            ElementBlock::NodeBlock(block) => {
+                let node = block.iter().next().unwrap();
+                owned(node.tags().get_all(keys))
+            }
+            _ => unreachable!("the fixture only holds a node"),
+        }
+    }
+
+    #[test]
+    fn get_all_fills_every_position_of_a_key() {
+        let keys = ["addr:city", "name", "addr:street", "name", "amenity"];
+        let filter: &[&str] = &["name", "addr:street", "addr:city"];
+        let some = |v: &str| Some(v.to_owned());
+        for plain in [false, true] {
+            // keys outside of the tag filter are never found
+            assert_eq!(
+                get_all(plain, Some(filter), &keys),
+                [None, some("A"), some("S"), some("A"), None]
+            );
+            assert_eq!(
+                get_all(plain, None, &keys),
+                [None, some("A"), some("S"), some("A"), some("cafe")]
+            );
+        }
+    }
+
+    // Decodes a block of group whose string table holds strings, which need not be valid UTF-8
+    fn decode_group(group: PrimitiveGroup, strings: &[&'static [u8]]) -> ElementBlock {
+        let block = PrimitiveBlock {
+            stringtable: StringTable {
+                s: strings.iter().map(|&s| Cow::Borrowed(s)).collect(),
+            },
+            primitivegroup: vec![group],
+            ..PrimitiveBlock::default()
+        };
+        decode_blob(&encode_message(&block)).unwrap().pop().unwrap()
+    }
+
+    #[test]
+    fn get_all_skips_values_that_are_not_utf8() {
+        let way = Way {
+            id: 1,
+            keys: vec![1, 2],
+            vals: vec![3, 4],
+            ..Way::default()
+        };
+        let group = PrimitiveGroup {
+            ways: vec![way],
+            ..PrimitiveGroup::default()
+        };
+        let block = decode_group(group, &[b"", b"name", b"note", b"\xffA", b"ok"]);
+        let ElementBlock::WayBlock(block) = &block else {
+            unreachable!("the block only holds a way");
+        };
+        let way = block.iter().next().unwrap();
+        assert_eq!(way.tags().get_all(&["name", "note"]), [None, Some("ok")]);
+    }
+
+    #[test]
+    fn members_vec_empties_roles_that_are_not_utf8() {
+        let relation = Relation {
+            id: 1,
+            roles_sid: vec![1, 2],
+            memids: vec![10, 1],
+            types: vec![MemberType::WAY, MemberType::WAY],
+            ..Relation::default()
+        };
+        let group = PrimitiveGroup {
+            relations: vec![relation],
+            ..PrimitiveGroup::default()
+        };
+        let block = decode_group(group, &[b"", b"\xffouter", b"inner"]);
+        let ElementBlock::RelationBlock(block) = &block else {
+            unreachable!("the block only holds a relation");
+        };
+        let relation = block.iter().next().unwrap();
+        assert_eq!(
+            relation.members_vec(),
+            [(10, MemberType::WAY, ""), (11, MemberType::WAY, "inner")]
+        );
+    }
+
+    // (lat, lon) of the nodes of the granularity fixture
+    const COORDINATES: [(f64, f64); 2] =
+        [(52.123456789, -13.987654321), (-0.00000014, 179.999999999)];
+
+    // The (lat, lon) in nanodegrees the nodes decode to at a granularity, rounded to multiples of it
+    fn expected_nanodegrees(granularity: i64) -> [(i64, i64); 2] {
+        match granularity {
+            1 => [(52_123_456_789, -13_987_654_321), (-140, 179_999_999_999)],
+            100 => [(52_123_456_800, -13_987_654_300), (-100, 180_000_000_000)],
+            1000 => [(52_123_457_000, -13_987_654_000), (0, 180_000_000_000)],
+            _ => unreachable!("only these granularities are tested"),
+        }
+    }
+
+    // (lat, lon) of every node of the first block, decoded per node, by to_owned and in bulk
+    fn decoded_coordinates(plain: bool, granularity: i64) -> [Vec<(f64, f64)>; 3] {
+        let nodes = COORDINATES.iter().enumerate().map(|(id, &(lat, lon))| {
+            let id = id as i64 + 1;
+            let tags = Vec::new();
+            OwnedNode { id, lat, lon, tags }.into()
+        });
+        let mut fixture = PbfFixture::new(nodes).with_granularity(granularity);
+        if plain {
+            fixture = fixture.with_plain_nodes();
+        }
+        let file = fixture.to_temp_file(&format!("granularity-{plain}-{granularity}"));
+        let block = OsmReader::from_path(&*file)
+            .unwrap()
+            .blocks()
+            .next()
+            .unwrap();
+        let zip = |lats: Vec<f64>, lons: Vec<f64>| lats.into_iter().zip(lons).collect();
+        match &block {
+            ElementBlock::DenseNodeBlock(block) => {
+                let (mut nodes, mut owned) = (Vec::new(), Vec::new());
+                for mut node in block.iter() {
+                    // to_owned does not advance the delta decoding, lat() and lon() do
+                    let node_owned = node.to_owned();
+                    owned.push((node_owned.lat, node_owned.lon));
+                    nodes.push((node.lat(), node.lon()));
+                }
+                [nodes, owned, zip(block.latitudes(), block.longitudes())]
+            }
+            ElementBlock::NodeBlock(block) => [
+                block.iter().map(|node| (node.lat(), node.lon())).collect(),
+                block
+                    .iter()
+                    .map(|node| node.to_owned())
+                    .map(|node| (node.lat, node.lon))
+                    .collect(),
+                zip(block.latitudes(), block.longitudes()),
+            ],
+            _ => unreachable!("the fixture only holds nodes"),
+        }
+    }
+
+    #[test]
+    fn coordinates_decode_with_the_granularity_of_the_block() {
+        for granularity in [1, 100, 1000] {
+            let expected: Vec<(f64, f64)> = expected_nanodegrees(granularity)
+                .iter()
+                .map(|&(lat, lon)| (lat as f64 * 1e-9, lon as f64 * 1e-9))
+                .collect();
+            for plain in [false, true] {
+                for decoded in decoded_coordinates(plain, granularity) {
+                    assert_eq!(
+                        decoded, expected,
+                        "granularity {granularity}, plain {plain}"
+                    );
+                }
+            }
+        }
+    }
+
+    // Timestamps of the nodes of the metadata fixture, multiples of its date granularity
+    const DATE_GRANULARITY: i64 = 500;
+    const TIMESTAMPS_MILLIS: [i64; 3] = [1_700_000_000_500, 1_700_000_002_000, 1_600_000_000_000];
+
+    // The metadata of every node of the first block, with plain or dense nodes
+    fn decoded_info(plain: bool) -> Vec<ElementInfo> {
+        let nodes = (1..=3).map(|id| {
+            let tags = vec![("name".to_owned(), format!("node {id}"))];
+            OwnedNode {
+                id,
+                lat: 0.0,
+                lon: 0.0,
+                tags,
+            }
+            .into()
+        });
+        let info = TIMESTAMPS_MILLIS
+            .iter()
+            .zip(1..)
+            .map(|(&timestamp_millis, version)| FixtureInfo {
+                version,
+                timestamp_millis,
+                changeset: 100 - version as i64,
+                uid: 7,
+                user: "mapper".to_owned(),
+                visible: None,
+            });
+        let mut fixture = PbfFixture::new(nodes)
+            .with_info(info)
+            .with_date_granularity(DATE_GRANULARITY);
+        if plain {
+            fixture = fixture.with_plain_nodes();
+        }
+        let file = fixture.to_temp_file(&format!("date-granularity-{plain}"));
+        let block = OsmReader::from_path(&*file)
+            .unwrap()
+            .blocks()
+            .next()
+            .unwrap();
+        match &block {
+            ElementBlock::DenseNodeBlock(block) => {
+                block.iter().map(|node| node.info().unwrap()).collect()
+            }
+            ElementBlock::NodeBlock(block) => {
+                block.iter().map(|node| node.info().unwrap()).collect()
+            }
+            _ => unreachable!("the fixture only holds nodes"),
+        }
+    }
+
+    #[test]
+    fn timestamps_decode_with_the_date_granularity_of_the_block() {
+        for plain in [false, true] {
+            let info = decoded_info(plain);
+            assert_eq!(info.len(), TIMESTAMPS_MILLIS.len(), "plain {plain}");
+            for (info, &millis) in info.iter().zip(&TIMESTAMPS_MILLIS) {
+                assert_eq!(
+                    info.raw_timestamp(),
+                    millis / DATE_GRANULARITY,
+                    "plain {plain}"
+                );
+                assert_eq!(info.timestamp_millis(), millis, "plain {plain}");
+                #[cfg(feature = "chrono")]
+                assert_eq!(
+                    info.datetime().map(|datetime| datetime.timestamp_millis()),
+                    Some(millis),
+                    "plain {plain}"
+                );
+            }
+            let changesets: Vec<_> = info
+                .iter()
+                .map(|info| (info.version(), info.changeset()))
+                .collect();
+            assert_eq!(changesets, [(1, 99), (2, 98), (3, 97)], "plain {plain}");
+        }
     }
 }
@@ -0,0 +1,100 @@
+use crate::{Blob, BlobHeader, ElementInfo};
+use quick_protobuf::{MessageWrite, Writer};
+use std::{borrow::Cow, collections::HashMap, io::Write, sync::Arc};
+
+// Encodes a message into its protobuf bytes
+pub(crate) fn encode_message(message: &impl MessageWrite) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(message.get_size());
+    message
+        .write_message(&mut Writer::new(&mut bytes))
+        .expect("writing into a Vec cannot fail");
+    bytes
+}
+
+// Frames a blob: length of the header, the header, then the blob
+pub(crate) fn write_blob(
+    output: &mut impl Write,
+    type_pb: &str,
+    data: &[u8],
+    indexdata: Option<&[u8]>,
+    zlib: bool,
+) -> std::io::Result<()> {
+    let mut blob = Blob {
+        raw_size: Some(data.len() as i32),
+        ..Blob::default()
+    };
+    if zlib {
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data)?;
+        blob.zlib_data = Some(Cow::Owned(encoder.finish()?));
+    } else {
+        blob.raw = Some(Cow::Borrowed(data));
+    }
+    let blob = encode_message(&blob);
+    let header = encode_message(&BlobHeader {
+        type_pb: Cow::Borrowed(type_pb),
+        indexdata: indexdata.map(Cow::Borrowed),
+        datasize: blob.len() as i32,
+    });
+    output.write_all(&(header.len() as u32).to_be_bytes())?;
+    output.write_all(&header)?;
+    output.write_all(&blob)
+}
+
+// The string table of a block being written, index 0 is reserved as the empty string
+pub(crate) struct Strings {
+    pub(crate) table: Vec<Cow<'static, [u8]>>,
+    ids: HashMap<Box<[u8]>, u32>,
+}
+
+impl Default for Strings {
+    fn default() -> Self {
+        Self {
+            table: vec![Cow::Borrowed(&[])],
+            ids: HashMap::new(),
+        }
+    }
+}
+
+impl Strings {
+    pub(crate) fn id(&mut self, s: &[u8]) -> u32 {
+        if let Some(&id) = self.ids.get(s) {
+            return id;
+        }
+        let id = self.table.len() as u32;
+        self.table.push(Cow::Owned(s.to_vec()));
+        self.ids.insert(Box::from(s), id);
+        id
+    }
+
+    pub(crate) fn tags<'a>(
+        &mut self,
+        tags: impl Iterator<Item = (&'a str, &'a str)>,
+    ) -> (Vec<u32>, Vec<u32>) {
+        tags.map(|(k, v)| (self.id(k.as_bytes()), self.id(v.as_bytes())))
+            .unzip()
+    }
+
+    // Moves the user name of the metadata from the table of its block into this table
+    pub(crate) fn info(
+        &mut self,
+        info: Option<ElementInfo>,
+        table: &[Arc<[u8]>],
+    ) -> Option<ElementInfo> {
+        let mut info = info?;
+        let user = table
+            .get(info.user_sid as usize)
+            .map_or(&[][..], |user| user);
+        info.user_sid = self.id(user);
+        Some(info)
+    }
+}
+
+// Encodes absolute values as deltas
+pub(crate) fn delta_encode(mut values: Vec<i64>) -> Vec<i64> {
+    for index in (1..values.len()).rev() {
+        values[index] -= values[index - 1];
+    }
+    values
+}
@@ -0,0 +1,80 @@
+use crate::MemberType;
+use core::fmt;
+
+/// Errors about the content of a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OsmError {
+    /// An element references an id rejected by the [`IdPolicy`](crate::IdPolicy) of the reader
+    Corrupt {
+        /// Type of the referencing element
+        element_type: MemberType,
+        /// ID of the referencing element
+        element_id: i64,
+        /// The rejected id after delta decoding
+        ref_id: i64,
+    },
+    /// The file ends inside of the blob starting at offset, e.g. because a download was interrupted
+    Truncated {
+        /// Byte offset of the incomplete blob, everything before it was read completely
+        offset: u64,
+    },
+    /// A blob is compressed with a codec the [`DefaultDecompressor`](crate::DefaultDecompressor)
+    /// does not support, register a [`BlobDecompressor`](crate::BlobDecompressor) that does
+    UnsupportedCompression {
+        /// Name of the codec, after the `*_data` field of the blob holding the data (e.g. `"zstd"`)
+        codec: &'static str,
+    },
+    /// The BlobHeader of the blob starting at offset exceeds the 64KB the format allows
+    HeaderTooLarge {
+        /// Byte offset of the blob
+        offset: u64,
+        /// Size of the BlobHeader according to its length prefix
+        size: usize,
+    },
+}
+
+impl fmt::Display for OsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Corrupt {
+                element_type,
+                element_id,
+                ref_id,
+            } => write!(
+                f,
+                "{element_type:?} {element_id} references invalid id {ref_id}. File corrupt?"
+            ),
+            Self::Truncated { offset } => {
+                write!(
+                    f,
+                    "File ends inside of the blob at offset {offset}. File truncated?"
+                )
+            }
+            Self::UnsupportedCompression { codec } => write!(
+                f,
+                "Blob uses {codec} compression, register a BlobDecompressor that supports it"
+            ),
+            Self::HeaderTooLarge { offset, size } => write!(
+                f,
+                "BlobHeader of the blob at offset {offset} has {size} bytes, \
+                 exceeding the limit of 64KB. File corrupt?"
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for OsmError {}
+
+#[cfg(feature = "std")]
+impl From<OsmError> for std::io::Error {
+    fn from(err: OsmError) -> Self {
+        let kind = match err {
+            OsmError::Truncated { .. } => std::io::ErrorKind::UnexpectedEof,
+            OsmError::UnsupportedCompression { .. } => std::io::ErrorKind::Unsupported,
+            _ => std::io::ErrorKind::InvalidData,
+        };
+        std::io::Error::new(kind, err)
+    }
+}
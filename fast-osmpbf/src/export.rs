@@ -0,0 +1,54 @@
+use crate::{ElementBlock, OsmReader};
+use std::io::Write;
+
+/// Writes all nodes (dense and plain) as CSV with the columns `id,lat,lon` followed by one column per tag key in `tag_columns`.
+/// Nodes missing a tag get an empty field, values containing commas, quotes or newlines are quoted.
+///
+/// Filters applied to the reader beforehand are respected. Note that a tag filter hides
+/// every tag not in the filter, so `tag_columns` should be a subset of the filter keys.
+/// Rows come in the order blocks finish decoding, which is not necessarily file order.
+pub fn export_nodes_csv<W: Write>(
+    reader: OsmReader,
+    writer: W,
+    tag_columns: &[&str],
+) -> std::io::Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(["id", "lat", "lon"].iter().chain(tag_columns))?;
+
+    for block in reader.blocks() {
+        match block {
+            ElementBlock::DenseNodeBlock(block) => {
+                for mut node in block.iter() {
+                    let (id, lat, lon) = (node.id(), node.lat(), node.lon());
+                    write_row(&mut writer, id, lat, lon, node.tags().get_all(tag_columns))?;
+                }
+            }
+            ElementBlock::NodeBlock(block) => {
+                for node in block.iter() {
+                    let (id, lat, lon) = (node.id(), node.lat(), node.lon());
+                    write_row(&mut writer, id, lat, lon, node.tags().get_all(tag_columns))?;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    writer.flush()
+}
+
+// Writes one row, values holds the tag value of each column
+fn write_row<W: Write>(
+    writer: &mut csv::Writer<W>,
+    id: i64,
+    lat: f64,
+    lon: f64,
+    values: Vec<Option<&str>>,
+) -> csv::Result<()> {
+    writer.write_field(id.to_string())?;
+    writer.write_field(lat.to_string())?;
+    writer.write_field(lon.to_string())?;
+    for value in values {
+        writer.write_field(value.unwrap_or_default())?;
+    }
+    writer.write_record(None::<&[u8]>)
+}
@@ -0,0 +1,317 @@
+use crate::encode::{delta_encode, encode_message, write_blob, Strings};
+use crate::{
+    DenseInfo, DenseNodes, ElementBlock, ElementInfo, HeaderBBox, HeaderBlock, Info, OsmReader,
+    PrimitiveBlock, PrimitiveGroup, Relation, StringTable, Way,
+};
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+// Elements per written block, the size of full blocks in most files
+const BLOCK_SIZE: usize = 8000;
+
+// Units of a coordinate in nanodegrees
+const NANO: i64 = 1_000_000_000;
+
+impl OsmReader {
+    /// Reads the file once and writes the elements passing the filters of the reader into a new
+    /// `.osm.pbf` file at `output_path`, like `osmium extract` or `osmium tags-filter` do, e.g.
+    /// `OsmReader::from_path(path)?.with_bbox_filter(bbox)?.filter_to_file("region.osm.pbf")`.
+    /// Returns the number of elements written.
+    ///
+    /// Blobs are decoded in parallel in batches of one blob per thread but written in file order,
+    /// so a sorted file stays sorted. Besides the batch only one output block is held in memory,
+    /// regardless of the size of the file. The output is zlib compressed, writes nodes as dense nodes
+    /// and keeps the metadata of the elements, the header keeps the features and source of the input.
+    ///
+    /// Elements are written the way the iterators yield them: with a tag filter only the tags of the
+    /// filter keys are kept, and the bbox filter only applies to nodes, combine it with
+    /// [`OsmReader::with_element_filter`] or [`OsmReader::with_predicate`] for ways and relations.
+    /// An unreadable blob stops the pass with its error, the output is incomplete then.
+    pub fn filter_to_file<P: AsRef<Path>>(self, output_path: P) -> std::io::Result<u64> {
+        let mut output = BufWriter::new(File::create(output_path)?);
+        let header = self.extract_header();
+        let header = encode_message(&header);
+        write_blob(&mut output, "OSMHeader", &header, None, true)?;
+
+        let mut encoder = BlockEncoder::default();
+        let mut count = 0;
+        for blocks in self.ordered_blocks() {
+            for block in blocks? {
+                count += encoder.push_block(&block, &mut output)?;
+            }
+        }
+        encoder.flush(&mut output)?;
+        output.flush()?;
+        Ok(count)
+    }
+
+    // Header of an extract, the bbox filter becomes the bbox of the file
+    fn extract_header(&self) -> HeaderBlock<'static> {
+        let header = self.header();
+        let has_feature =
+            |feature| header.is_some_and(|h| h.required_features().iter().any(|f| f == feature));
+        let mut required_features =
+            vec![Cow::Borrowed("OsmSchema-V0.6"), Cow::Borrowed("DenseNodes")];
+        if has_feature("HistoricalInformation") {
+            required_features.push(Cow::Borrowed("HistoricalInformation"));
+        }
+        // node locations of ways are not kept
+        let optional_features = header
+            .iter()
+            .flat_map(|h| h.optional_features())
+            .filter(|feature| *feature != "LocationsOnWays")
+            .map(|feature| Cow::Owned(feature.clone()))
+            .collect();
+        let to_nano = |degrees: f64| (degrees * NANO as f64).round() as i64;
+        HeaderBlock {
+            bbox: self.parse_options().bbox.map(|bbox| HeaderBBox {
+                left: to_nano(bbox.left),
+                right: to_nano(bbox.right),
+                top: to_nano(bbox.top),
+                bottom: to_nano(bbox.bottom),
+            }),
+            required_features,
+            optional_features,
+            writingprogram: Some(Cow::Borrowed("fast-osmpbf")),
+            source: header
+                .and_then(|h| h.source())
+                .map(|source| Cow::Owned(source.to_owned())),
+            // an extract is as current as its source
+            osmosis_replication_timestamp: header.and_then(|h| h.replication_timestamp()),
+            osmosis_replication_sequence_number: header.and_then(|h| h.replication_sequence()),
+            osmosis_replication_base_url: header
+                .and_then(|h| h.replication_base_url())
+                .map(|url| Cow::Owned(url.to_owned())),
+        }
+    }
+}
+
+// Element type and coordinate and date scales (granularity, lat_offset, lon_offset, date_granularity)
+// the elements of a written block share
+type BlockKey = (u8, [i64; 4]);
+
+// Collects the elements of one written block, it is written once full or when the key changes
+#[derive(Default)]
+struct BlockEncoder {
+    key: Option<BlockKey>,
+    len: usize,
+    strings: Strings,
+    nodes: DenseColumns,
+    ways: Vec<Way>,
+    relations: Vec<Relation>,
+}
+
+// Dense nodes with absolute values, delta encoded when the block is written
+#[derive(Default)]
+struct DenseColumns {
+    ids: Vec<i64>,
+    lats: Vec<i64>,
+    lons: Vec<i64>,
+    keys_vals: Vec<i32>,
+    infos: Vec<Option<ElementInfo>>,
+}
+
+impl BlockEncoder {
+    // Adds the elements of a block passing the filters, returns how many
+    fn push_block(
+        &mut self,
+        block: &ElementBlock,
+        output: &mut impl Write,
+    ) -> std::io::Result<u64> {
+        let mut count = 0;
+        match block {
+            ElementBlock::DenseNodeBlock(block) => {
+                let (granularity, lat_offset, lon_offset) =
+                    (block.granularity, block.lat_offset, block.lon_offset);
+                let key = (
+                    0,
+                    [granularity, lat_offset, lon_offset, block.date_granularity],
+                );
+                for mut node in block.iter() {
+                    self.start(key, output)?;
+                    let info = self.strings.info(node.info(), &block.table);
+                    let (keys, vals) = self.strings.tags(node.tags());
+                    let lat = (node.lat_scaled(NANO) - lat_offset) / granularity;
+                    let lon = (node.lon_scaled(NANO) - lon_offset) / granularity;
+                    self.nodes.push(node.id(), lat, lon, &keys, &vals, info);
+                    count += 1;
+                }
+            }
+            ElementBlock::NodeBlock(block) => {
+                let (granularity, lat_offset, lon_offset) =
+                    (block.granularity, block.lat_offset, block.lon_offset);
+                let key = (
+                    0,
+                    [granularity, lat_offset, lon_offset, block.date_granularity],
+                );
+                for node in block.iter() {
+                    self.start(key, output)?;
+                    let info = self.strings.info(node.info(), &block.table);
+                    let (keys, vals) = self.strings.tags(node.tags());
+                    let lat = (node.lat_scaled(NANO) - lat_offset) / granularity;
+                    let lon = (node.lon_scaled(NANO) - lon_offset) / granularity;
+                    self.nodes.push(node.id(), lat, lon, &keys, &vals, info);
+                    count += 1;
+                }
+            }
+            ElementBlock::WayBlock(block) => {
+                let key = (1, [0, 0, 0, block.date_granularity]);
+                for way in block.iter() {
+                    self.start(key, output)?;
+                    let info = self.strings.info(way.info(), &block.table);
+                    let (keys, vals) = self.strings.tags(way.tags());
+                    self.ways.push(Way {
+                        id: way.id(),
+                        keys,
+                        vals,
+                        info: info.map(encode_info),
+                        refs: way.node_ids_raw().to_vec(),
+                    });
+                    count += 1;
+                }
+            }
+            ElementBlock::RelationBlock(block) => {
+                let key = (2, [0, 0, 0, block.date_granularity]);
+                for relation in block.iter() {
+                    self.start(key, output)?;
+                    let info = self.strings.info(relation.info(), &block.table);
+                    let (keys, vals) = self.strings.tags(relation.tags());
+                    let members: Vec<_> = relation.members().collect();
+                    let roles_sid = members.iter().map(|m| self.strings.id(m.role().as_bytes()));
+                    self.relations.push(Relation {
+                        id: relation.id(),
+                        keys,
+                        vals,
+                        info: info.map(encode_info),
+                        roles_sid: roles_sid.map(|id| id as i32).collect(),
+                        memids: delta_encode(members.iter().map(|member| member.id()).collect()),
+                        types: members.iter().map(|member| member.member_type()).collect(),
+                    });
+                    count += 1;
+                }
+            }
+        }
+        Ok(count)
+    }
+
+    // Makes room for one more element with the key, writing the current block if needed
+    fn start(&mut self, key: BlockKey, output: &mut impl Write) -> std::io::Result<()> {
+        if self.key != Some(key) || self.len == BLOCK_SIZE {
+            self.flush(output)?;
+            self.key = Some(key);
+        }
+        self.len += 1;
+        Ok(())
+    }
+
+    // Writes the collected elements as one blob, if there are any
+    fn flush(&mut self, output: &mut impl Write) -> std::io::Result<()> {
+        let Some((kind, [granularity, lat_offset, lon_offset, date_granularity])) = self.key.take()
+        else {
+            return Ok(());
+        };
+        let mut group = PrimitiveGroup::default();
+        match kind {
+            0 => group.dense = Some(std::mem::take(&mut self.nodes).into_dense_nodes()),
+            1 => group.ways = std::mem::take(&mut self.ways),
+            _ => group.relations = std::mem::take(&mut self.relations),
+        }
+        let block = PrimitiveBlock {
+            stringtable: StringTable {
+                s: std::mem::take(&mut self.strings).table,
+            },
+            primitivegroup: vec![group],
+            granularity,
+            lat_offset,
+            lon_offset,
+            date_granularity,
+        };
+        self.len = 0;
+        write_blob(output, "OSMData", &encode_message(&block), None, true)
+    }
+}
+
+impl DenseColumns {
+    fn push(
+        &mut self,
+        id: i64,
+        lat: i64,
+        lon: i64,
+        keys: &[u32],
+        vals: &[u32],
+        info: Option<ElementInfo>,
+    ) {
+        self.ids.push(id);
+        self.lats.push(lat);
+        self.lons.push(lon);
+        for (key, val) in keys.iter().zip(vals) {
+            self.keys_vals.push(*key as i32);
+            self.keys_vals.push(*val as i32);
+        }
+        self.keys_vals.push(0);
+        self.infos.push(info);
+    }
+
+    fn into_dense_nodes(self) -> DenseNodes {
+        // the metadata columns are left out if no node has metadata
+        let denseinfo = self.infos.iter().any(Option::is_some).then(|| {
+            let infos = &self.infos;
+            let column = |value: fn(&ElementInfo) -> i64| {
+                delta_encode(
+                    infos
+                        .iter()
+                        .map(|info| info.as_ref().map_or(0, value))
+                        .collect(),
+                )
+            };
+            let has_visible = infos.iter().flatten().any(|info| info.visible.is_some());
+            DenseInfo {
+                version: infos
+                    .iter()
+                    .map(|info| info.map_or(-1, |info| info.version))
+                    .collect(),
+                timestamp: column(|info| info.timestamp),
+                changeset: column(|info| info.changeset),
+                uid: column(|info| info.uid.into())
+                    .into_iter()
+                    .map(|uid| uid as i32)
+                    .collect(),
+                user_sid: column(|info| info.user_sid.into())
+                    .into_iter()
+                    .map(|sid| sid as i32)
+                    .collect(),
+                visible: match has_visible {
+                    true => infos
+                        .iter()
+                        .map(|info| info.and_then(|info| info.visible).unwrap_or(true))
+                        .collect(),
+                    false => Vec::new(),
+                },
+            }
+        });
+        let has_tags = self.keys_vals.iter().any(|&id| id != 0);
+        DenseNodes {
+            id: delta_encode(self.ids),
+            denseinfo,
+            lat: delta_encode(self.lats),
+            lon: delta_encode(self.lons),
+            // without any tag the zeros ending the tags of every node are left out
+            keys_vals: if has_tags { self.keys_vals } else { Vec::new() },
+        }
+    }
+}
+
+fn encode_info(info: ElementInfo) -> Info {
+    Info {
+        version: info.version,
+        timestamp: Some(info.timestamp),
+        changeset: Some(info.changeset),
+        uid: Some(info.uid),
+        user_sid: Some(info.user_sid),
+        visible: info.visible,
+    }
+}
@@ -0,0 +1,464 @@
+use crate::encode::{delta_encode, encode_message, write_blob, Strings};
+use crate::{
+    DenseInfo, DenseNodes, Element, HeaderBBox, HeaderBlock, Info, Node, OwnedNode, OwnedRelation,
+    OwnedWay, PrimitiveBlock, PrimitiveGroup, Relation, StringTable, Way,
+};
+use std::{borrow::Cow, path::Path};
+
+// Granularity of coordinates, the default of the format (100 nanodegrees)
+const DEFAULT_GRANULARITY: i64 = 100;
+// Granularity of timestamps, the default of the format (1000 milliseconds)
+const DEFAULT_DATE_GRANULARITY: i64 = 1000;
+
+/// Builds small but valid `.osm.pbf` files with known contents, e.g. for integration tests of edge
+/// cases (an empty file, untagged nodes, a huge relation) without checking in binary files.
+///
+/// The elements are written in the given order. Consecutive elements of the same type share a block
+/// until it holds [`PbfFixture::with_block_size`] elements, every type change starts a new block.
+/// Nodes are written as dense nodes unless [`PbfFixture::with_plain_nodes`] is used.
+/// Coordinates are rounded to the default granularity of the format (100 nanodegrees) unless
+/// [`PbfFixture::with_granularity`] is used, relative to [`PbfFixture::with_offsets`] if set.
+/// Elements carry no metadata unless [`PbfFixture::with_info`] is used.
+#[derive(Debug, Clone)]
+pub struct PbfFixture {
+    elements: Vec<Element>,
+    info: Option<Vec<FixtureInfo>>,
+    dense_nodes: bool,
+    block_size: usize,
+    zlib: bool,
+    bbox_hints: bool,
+    grid: Grid,
+    date_granularity: i64,
+}
+
+/// Metadata of an element written by [`PbfFixture::with_info`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FixtureInfo {
+    /// Version of the element
+    pub version: i32,
+    /// Milliseconds since the unix epoch, rounded down to the date granularity of the fixture
+    pub timestamp_millis: i64,
+    /// Id of the changeset that last changed the element
+    pub changeset: i64,
+    /// Id of the user that last changed the element
+    pub uid: i32,
+    /// Name of the user that last changed the element
+    pub user: String,
+    /// Whether the element is visible, only set in history files
+    pub visible: Option<bool>,
+}
+
+impl PbfFixture {
+    /// Creates a fixture of `elements`, written as dense nodes in uncompressed blobs of at most 8000 elements
+    pub fn new(elements: impl IntoIterator<Item = Element>) -> Self {
+        Self {
+            elements: elements.into_iter().collect(),
+            info: None,
+            dense_nodes: true,
+            block_size: 8000,
+            zlib: false,
+            bbox_hints: false,
+            grid: Grid {
+                granularity: DEFAULT_GRANULARITY,
+                lat_offset: 0,
+                lon_offset: 0,
+            },
+            date_granularity: DEFAULT_DATE_GRANULARITY,
+        }
+    }
+
+    /// Writes nodes as plain [`Node`] messages instead of dense nodes
+    pub fn with_plain_nodes(mut self) -> Self {
+        self.dense_nodes = false;
+        self
+    }
+
+    /// Limits the number of elements per blob (at least 1), e.g. to test code that spans several blobs
+    pub fn with_block_size(mut self, elements: usize) -> Self {
+        self.block_size = elements.max(1);
+        self
+    }
+
+    /// Compresses the blobs with zlib, like most files in the wild
+    pub fn with_zlib(mut self) -> Self {
+        self.zlib = true;
+        self
+    }
+
+    /// Stores the bounding box of the nodes of every blob in the `indexdata` of its header as an
+    /// encoded `HeaderBBox`, which the reader takes as [`ElementBlock::bbox`](crate::ElementBlock::bbox)
+    pub fn with_bbox_hints(mut self) -> Self {
+        self.bbox_hints = true;
+        self
+    }
+
+    /// Stores coordinates in units of `granularity` nanodegrees (at least 1) instead of 100, e.g. 1000
+    /// like files of lower precision. Coordinates are rounded to the nearest unit.
+    pub fn with_granularity(mut self, granularity: i64) -> Self {
+        self.grid.granularity = granularity.max(1);
+        self
+    }
+
+    /// Stores coordinates relative to `lat_offset` and `lon_offset` nanodegrees instead of 0,
+    /// which the format allows to shift the grid of a coarse granularity
+    pub fn with_offsets(mut self, lat_offset: i64, lon_offset: i64) -> Self {
+        self.grid.lat_offset = lat_offset;
+        self.grid.lon_offset = lon_offset;
+        self
+    }
+
+    /// Writes `info` as the metadata of the elements, one entry per element in the same order.
+    /// Dense nodes store it as delta encoded [`DenseInfo`], all other elements as [`Info`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `info` does not hold exactly one entry per element.
+    pub fn with_info(mut self, info: impl IntoIterator<Item = FixtureInfo>) -> Self {
+        let info: Vec<_> = info.into_iter().collect();
+        assert_eq!(
+            info.len(),
+            self.elements.len(),
+            "every element needs exactly one info"
+        );
+        self.info = Some(info);
+        self
+    }
+
+    /// Stores timestamps in units of `millis` milliseconds (at least 1) instead of 1000
+    pub fn with_date_granularity(mut self, millis: i64) -> Self {
+        self.date_granularity = millis.max(1);
+        self
+    }
+
+    /// Encodes the header blob and all data blobs into the bytes of a `.osm.pbf` file
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut required_features = vec![Cow::Borrowed("OsmSchema-V0.6")];
+        if self.dense_nodes {
+            required_features.push(Cow::Borrowed("DenseNodes"));
+        }
+        let header = HeaderBlock {
+            required_features,
+            writingprogram: Some(Cow::Borrowed("fast-osmpbf")),
+            ..HeaderBlock::default()
+        };
+
+        let mut file = Vec::new();
+        self.write_blob(&mut file, "OSMHeader", &encode_message(&header), None);
+        let mut start = 0;
+        while start < self.elements.len() {
+            let kind = element_type(&self.elements[start]);
+            let len = self.elements[start..]
+                .iter()
+                .take(self.block_size)
+                .take_while(|element| element_type(element) == kind)
+                .count();
+            let block = self.primitive_block(start..start + len);
+            let indexdata = self.bbox_hint(&self.elements[start..start + len]);
+            let data = encode_message(&block);
+            self.write_blob(&mut file, "OSMData", &data, indexdata.as_deref());
+            start += len;
+        }
+        file
+    }
+
+    /// Writes the file to `path`, see [`PbfFixture::to_bytes`]
+    pub fn write_to_path<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        std::fs::write(path, self.to_bytes())
+    }
+
+    // Writes the file into the temporary directory under a name unique to the test, removed on drop
+    #[cfg(test)]
+    pub(crate) fn to_temp_file(&self, name: &str) -> TempFile {
+        let file_name = format!("fast-osmpbf-{}-{name}.osm.pbf", std::process::id());
+        let path = std::env::temp_dir().join(file_name);
+        self.write_to_path(&path)
+            .expect("the temporary directory is writable");
+        TempFile(path)
+    }
+
+    // A file of only ways, spread over several blocks, whose nodes are not in it
+    #[cfg(test)]
+    pub(crate) fn ways_only() -> Self {
+        let ways = (1..=5).map(|id| {
+            let tags = vec![("highway".to_owned(), "path".to_owned())];
+            let node_ids = vec![id, id + 1];
+            OwnedWay { id, node_ids, tags }.into()
+        });
+        Self::new(ways).with_block_size(2)
+    }
+
+    // A file of only relations, spread over several blocks, whose members are not in it
+    #[cfg(test)]
+    pub(crate) fn relations_only() -> Self {
+        let relations = (1..=5).map(|id| {
+            let tags = vec![("type".to_owned(), "multipolygon".to_owned())];
+            let members = vec![crate::OwnedRelationMember {
+                id,
+                member_type: crate::MemberType::WAY,
+                role: "outer".to_owned(),
+            }];
+            OwnedRelation { id, members, tags }.into()
+        });
+        Self::new(relations).with_block_size(2)
+    }
+
+    // Frames a blob, compressed if the fixture uses zlib
+    fn write_blob(&self, file: &mut Vec<u8>, type_pb: &str, data: &[u8], indexdata: Option<&[u8]>) {
+        write_blob(file, type_pb, data, indexdata, self.zlib)
+            .expect("writing into a Vec cannot fail");
+    }
+
+    // The encoded bounding box of the nodes of a blob, at the coordinates they are stored with
+    fn bbox_hint(&self, elements: &[Element]) -> Option<Vec<u8>> {
+        if !self.bbox_hints {
+            return None;
+        }
+        let grid = self.grid;
+        let bbox = elements
+            .iter()
+            .filter_map(|element| match element {
+                Element::Node(node) => Some(grid.coordinates(node)),
+                _ => None,
+            })
+            .map(|(lat, lon)| {
+                let lat = lat * grid.granularity + grid.lat_offset;
+                let lon = lon * grid.granularity + grid.lon_offset;
+                HeaderBBox {
+                    left: lon,
+                    right: lon,
+                    top: lat,
+                    bottom: lat,
+                }
+            })
+            .reduce(|a, b| HeaderBBox {
+                left: a.left.min(b.left),
+                right: a.right.max(b.right),
+                top: a.top.max(b.top),
+                bottom: a.bottom.min(b.bottom),
+            })?;
+        Some(encode_message(&bbox))
+    }
+
+    // Encodes the elements in range, all of a single type, into one block
+    fn primitive_block(&self, range: std::ops::Range<usize>) -> PrimitiveBlock<'static> {
+        let elements = &self.elements[range.clone()];
+        let info = self.info.as_ref().map(|info| &info[range]);
+        let mut strings = Strings::default();
+        let mut group = PrimitiveGroup::default();
+        let nodes = || {
+            elements.iter().filter_map(|element| match element {
+                Element::Node(node) => Some(node),
+                _ => None,
+            })
+        };
+        if self.dense_nodes {
+            if nodes().next().is_some() {
+                let mut dense = dense_nodes(nodes(), self.grid, &mut strings);
+                dense.denseinfo = info.map(|info| self.dense_info(info, &mut strings));
+                group.dense = Some(dense);
+            }
+        } else {
+            group.nodes = nodes()
+                .map(|node| plain_node(node, self.grid, &mut strings))
+                .collect();
+        }
+        for element in elements {
+            match element {
+                Element::Node(_) => (),
+                Element::Way(way) => group.ways.push(encode_way(way, &mut strings)),
+                Element::Relation(relation) => group
+                    .relations
+                    .push(encode_relation(relation, &mut strings)),
+            }
+        }
+        // A block holds a single type, so the info of its elements is in the same order
+        if let Some(info) = info {
+            let encoded = group
+                .nodes
+                .iter_mut()
+                .map(|node| &mut node.info)
+                .chain(group.ways.iter_mut().map(|way| &mut way.info))
+                .chain(
+                    group
+                        .relations
+                        .iter_mut()
+                        .map(|relation| &mut relation.info),
+                );
+            for (encoded, info) in encoded.zip(info) {
+                *encoded = Some(self.info(info, &mut strings));
+            }
+        }
+
+        PrimitiveBlock {
+            stringtable: StringTable { s: strings.table },
+            primitivegroup: vec![group],
+            granularity: self.grid.granularity,
+            lat_offset: self.grid.lat_offset,
+            lon_offset: self.grid.lon_offset,
+            date_granularity: self.date_granularity,
+        }
+    }
+
+    fn info(&self, info: &FixtureInfo, strings: &mut Strings) -> Info {
+        Info {
+            version: info.version,
+            timestamp: Some(info.timestamp_millis / self.date_granularity),
+            changeset: Some(info.changeset),
+            uid: Some(info.uid),
+            user_sid: Some(strings.id(info.user.as_bytes())),
+            visible: info.visible,
+        }
+    }
+
+    // Stores the metadata of dense nodes column wise, all but the version delta encoded
+    fn dense_info(&self, info: &[FixtureInfo], strings: &mut Strings) -> DenseInfo {
+        let column =
+            |value: fn(&FixtureInfo) -> i64| delta_encode(info.iter().map(value).collect());
+        let user_sids = info
+            .iter()
+            .map(|info| strings.id(info.user.as_bytes()).into());
+        let has_visible = info.iter().any(|info| info.visible.is_some());
+        DenseInfo {
+            version: info.iter().map(|info| info.version).collect(),
+            timestamp: delta_encode(
+                info.iter()
+                    .map(|info| info.timestamp_millis / self.date_granularity)
+                    .collect(),
+            ),
+            changeset: column(|info| info.changeset),
+            uid: column(|info| info.uid.into())
+                .into_iter()
+                .map(|uid| uid as i32)
+                .collect(),
+            user_sid: delta_encode(user_sids.collect())
+                .into_iter()
+                .map(|user_sid| user_sid as i32)
+                .collect(),
+            visible: if has_visible {
+                info.iter()
+                    .map(|info| info.visible.unwrap_or(true))
+                    .collect()
+            } else {
+                Vec::new()
+            },
+        }
+    }
+}
+
+// A fixture written by to_temp_file
+#[cfg(test)]
+pub(crate) struct TempFile(std::path::PathBuf);
+
+#[cfg(test)]
+impl std::ops::Deref for TempFile {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn element_type(element: &Element) -> u8 {
+    match element {
+        Element::Node(_) => 0,
+        Element::Way(_) => 1,
+        Element::Relation(_) => 2,
+    }
+}
+
+// The granularity and offsets coordinates are stored with, in nanodegrees
+#[derive(Debug, Clone, Copy)]
+struct Grid {
+    granularity: i64,
+    lat_offset: i64,
+    lon_offset: i64,
+}
+
+impl Grid {
+    // (lat, lon) of a node in units of the granularity, rounded to the nearest unit
+    fn coordinates(&self, node: &OwnedNode) -> (i64, i64) {
+        let unit = |degrees: f64, offset: i64| {
+            ((degrees * 1e9 - offset as f64) / self.granularity as f64).round() as i64
+        };
+        (
+            unit(node.lat, self.lat_offset),
+            unit(node.lon, self.lon_offset),
+        )
+    }
+}
+
+// Stores ids and coordinates delta encoded, tags of all nodes in one list with a 0 after every node
+fn dense_nodes<'a>(
+    nodes: impl Iterator<Item = &'a OwnedNode>,
+    grid: Grid,
+    strings: &mut Strings,
+) -> DenseNodes {
+    let mut dense = DenseNodes::default();
+    for node in nodes {
+        let (lat, lon) = grid.coordinates(node);
+        dense.id.push(node.id);
+        dense.lat.push(lat);
+        dense.lon.push(lon);
+        for (key, value) in &node.tags {
+            dense.keys_vals.push(strings.id(key.as_bytes()) as i32);
+            dense.keys_vals.push(strings.id(value.as_bytes()) as i32);
+        }
+        dense.keys_vals.push(0);
+    }
+    dense.id = delta_encode(dense.id);
+    dense.lat = delta_encode(dense.lat);
+    dense.lon = delta_encode(dense.lon);
+    dense
+}
+
+fn plain_node(node: &OwnedNode, grid: Grid, strings: &mut Strings) -> Node {
+    let (keys, vals) = tags(&node.tags, strings);
+    let (lat, lon) = grid.coordinates(node);
+    Node {
+        id: node.id,
+        keys,
+        vals,
+        info: None,
+        lat,
+        lon,
+    }
+}
+
+fn encode_way(way: &OwnedWay, strings: &mut Strings) -> Way {
+    let (keys, vals) = tags(&way.tags, strings);
+    Way {
+        id: way.id,
+        keys,
+        vals,
+        info: None,
+        refs: delta_encode(way.node_ids.clone()),
+    }
+}
+
+fn encode_relation(relation: &OwnedRelation, strings: &mut Strings) -> Relation {
+    let (keys, vals) = tags(&relation.tags, strings);
+    let members = &relation.members;
+    Relation {
+        id: relation.id,
+        keys,
+        vals,
+        info: None,
+        roles_sid: members
+            .iter()
+            .map(|member| strings.id(member.role.as_bytes()) as i32)
+            .collect(),
+        memids: delta_encode(members.iter().map(|member| member.id).collect()),
+        types: members.iter().map(|member| member.member_type).collect(),
+    }
+}
+
+fn tags(tags: &[(String, String)], strings: &mut Strings) -> (Vec<u32>, Vec<u32>) {
+    strings.tags(tags.iter().map(|(k, v)| (k.as_str(), v.as_str())))
+}
@@ -0,0 +1,371 @@
+use crate::{Element, ElementBlock, MemberType, OsmReader, OwnedNode, OwnedRelation, OwnedWay};
+use geo::orient::Direction;
+use geo::{
+    Area, Contains, Coord, Geometry, GeometryCollection, LineString, MultiPolygon, Orient, Point,
+    Polygon,
+};
+
+/// A problem found while assembling a multipolygon.
+/// Broken parts are left out of the geometry, the rest is still assembled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GeometryIssue {
+    /// The way resolver did not know a way member
+    MissingWay(i64),
+    /// The node resolver did not know a node of a way, the way is left out
+    MissingNode {
+        /// ID of the way referencing the node
+        way_id: i64,
+        /// ID of the missing node
+        node_id: i64,
+    },
+    /// The ways could not be connected to a closed ring
+    UnclosedRing {
+        /// IDs of the ways in the order they were connected
+        way_ids: Vec<i64>,
+    },
+    /// A closed ring has less than 3 distinct nodes
+    DegenerateRing {
+        /// IDs of the ways forming the ring
+        way_ids: Vec<i64>,
+    },
+    /// An inner ring does not lie inside any outer ring
+    InnerOutsideOuters {
+        /// IDs of the ways forming the ring
+        way_ids: Vec<i64>,
+    },
+}
+
+/// The result of [`assemble_multipolygon`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultipolygonAssembly {
+    /// The assembled geometry, [`None`] if not a single valid outer ring could be built
+    pub multipolygon: Option<MultiPolygon<f64>>,
+    /// Everything that was wrong with the relation
+    pub issues: Vec<GeometryIssue>,
+}
+
+// A way member resolved to node ids and coordinates
+struct Segment {
+    way_id: i64,
+    nodes: Vec<(i64, Coord<f64>)>,
+}
+
+impl Segment {
+    fn first(&self) -> i64 {
+        self.nodes[0].0
+    }
+    fn last(&self) -> i64 {
+        self.nodes[self.nodes.len() - 1].0
+    }
+}
+
+// A closed ring built from one or more ways
+struct Ring {
+    way_ids: Vec<i64>,
+    polygon: Polygon<f64>,
+}
+
+/// Assembles the rings of a multipolygon (or boundary) relation into a [`MultiPolygon`].
+///
+/// Way members are connected end to end (reversing ways where needed) into closed rings,
+/// then every inner ring is assigned to the smallest outer ring containing it.
+/// Members with an empty role are treated as outer, members that are not ways are ignored.
+/// Rings are oriented counter-clockwise for outers and clockwise for inners.
+///
+/// `way_resolver` returns the node ids of a way, `node_resolver` returns the (lon, lat) of a node.
+pub fn assemble_multipolygon<W, N>(
+    relation: &OwnedRelation,
+    way_resolver: W,
+    node_resolver: N,
+) -> MultipolygonAssembly
+where
+    W: Fn(i64) -> Option<Vec<i64>>,
+    N: Fn(i64) -> Option<(f64, f64)>,
+{
+    let mut issues = Vec::new();
+    let mut outer_segments = Vec::new();
+    let mut inner_segments = Vec::new();
+
+    for member in &relation.members {
+        if member.member_type != MemberType::WAY {
+            continue;
+        }
+        let segments = match member.role.as_str() {
+            "inner" => &mut inner_segments,
+            _ => &mut outer_segments,
+        };
+        if let Some(segment) =
+            resolve_segment(member.id, &way_resolver, &node_resolver, &mut issues)
+        {
+            segments.push(segment);
+        }
+    }
+
+    let outers = build_rings(outer_segments, &mut issues);
+    let inners = build_rings(inner_segments, &mut issues);
+
+    let mut polygons: Vec<(f64, Polygon<f64>)> = outers
+        .into_iter()
+        .map(|ring| (ring.polygon.unsigned_area(), ring.polygon))
+        .collect();
+    for inner in inners {
+        let point = Point::from(inner.polygon.exterior().0[0]);
+        let outer = polygons
+            .iter_mut()
+            .filter(|(_, outer)| outer.contains(&point))
+            .min_by(|(a, _), (b, _)| a.total_cmp(b));
+        match outer {
+            Some((_, outer)) => outer.interiors_push(inner.polygon.into_inner().0),
+            None => issues.push(GeometryIssue::InnerOutsideOuters {
+                way_ids: inner.way_ids,
+            }),
+        }
+    }
+
+    let multipolygon = (!polygons.is_empty()).then(|| {
+        MultiPolygon::new(polygons.into_iter().map(|(_, polygon)| polygon).collect())
+            .orient(Direction::Default)
+    });
+    MultipolygonAssembly {
+        multipolygon,
+        issues,
+    }
+}
+
+// Resolves a way member to its node ids and coordinates
+fn resolve_segment<W, N>(
+    way_id: i64,
+    way_resolver: &W,
+    node_resolver: &N,
+    issues: &mut Vec<GeometryIssue>,
+) -> Option<Segment>
+where
+    W: Fn(i64) -> Option<Vec<i64>>,
+    N: Fn(i64) -> Option<(f64, f64)>,
+{
+    let Some(node_ids) = way_resolver(way_id).filter(|ids| !ids.is_empty()) else {
+        issues.push(GeometryIssue::MissingWay(way_id));
+        return None;
+    };
+    let mut nodes = Vec::with_capacity(node_ids.len());
+    for node_id in node_ids {
+        let Some((x, y)) = node_resolver(node_id) else {
+            issues.push(GeometryIssue::MissingNode { way_id, node_id });
+            return None;
+        };
+        nodes.push((node_id, Coord { x, y }));
+    }
+    Some(Segment { way_id, nodes })
+}
+
+// Connects segments end to end into closed rings
+fn build_rings(mut segments: Vec<Segment>, issues: &mut Vec<GeometryIssue>) -> Vec<Ring> {
+    let mut rings = Vec::new();
+
+    while let Some(segment) = segments.pop() {
+        let mut way_ids = vec![segment.way_id];
+        let start = segment.first();
+        let mut nodes = segment.nodes;
+
+        while nodes[nodes.len() - 1].0 != start || nodes.len() == 1 {
+            let end = nodes[nodes.len() - 1].0;
+            let Some(pos) = segments
+                .iter()
+                .position(|s| s.first() == end || s.last() == end)
+            else {
+                break;
+            };
+            let next = segments.swap_remove(pos);
+            way_ids.push(next.way_id);
+            if next.first() == end {
+                nodes.extend(next.nodes.into_iter().skip(1));
+            } else {
+                nodes.extend(next.nodes.into_iter().rev().skip(1));
+            }
+        }
+
+        if nodes[nodes.len() - 1].0 != start || nodes.len() == 1 {
+            issues.push(GeometryIssue::UnclosedRing { way_ids });
+        } else if nodes.len() < 4 {
+            issues.push(GeometryIssue::DegenerateRing { way_ids });
+        } else {
+            let exterior = LineString::from_iter(nodes.into_iter().map(|(_, coord)| coord));
+            rings.push(Ring {
+                way_ids,
+                polygon: Polygon::new(exterior, Vec::new()),
+            });
+        }
+    }
+
+    rings
+}
+
+/// Converts a node into a [`Point`] with x = lon and y = lat
+pub fn node_geometry(node: &OwnedNode) -> Point<f64> {
+    Point::new(node.lon, node.lat)
+}
+
+/// Converts a way into a [`Polygon`] if it is closed and tagged as an area
+/// (`building`, `landuse` or `area` other than `no`), otherwise into a [`LineString`].
+/// Returns [`None`] if `node_resolver` does not know a node or the way has less than 2 nodes.
+///
+/// `node_resolver` returns the (lon, lat) of a node.
+pub fn way_geometry<N>(way: &OwnedWay, node_resolver: N) -> Option<Geometry<f64>>
+where
+    N: Fn(i64) -> Option<(f64, f64)>,
+{
+    let line = resolve_line(&way.node_ids, &node_resolver)?;
+    let closed = way.node_ids.len() >= 4 && way.node_ids.first() == way.node_ids.last();
+    let area = way.tags.iter().any(|(key, value)| match key.as_str() {
+        "building" | "landuse" => true,
+        "area" => value != "no",
+        _ => false,
+    });
+    if closed && area {
+        Some(
+            Polygon::new(line, Vec::new())
+                .orient(Direction::Default)
+                .into(),
+        )
+    } else {
+        Some(line.into())
+    }
+}
+
+/// Converts a relation into a [`Geometry`]. Multipolygons and boundaries are assembled with
+/// [`assemble_multipolygon`] into a [`MultiPolygon`], any other relation becomes a
+/// [`GeometryCollection`] of its node members as points and its way members as line strings.
+/// Unresolvable and relation members are left out, [`None`] is returned if nothing is left.
+///
+/// `way_resolver` returns the node ids of a way, `node_resolver` returns the (lon, lat) of a node.
+pub fn relation_geometry<W, N>(
+    relation: &OwnedRelation,
+    way_resolver: W,
+    node_resolver: N,
+) -> Option<Geometry<f64>>
+where
+    W: Fn(i64) -> Option<Vec<i64>>,
+    N: Fn(i64) -> Option<(f64, f64)>,
+{
+    let is_area = relation
+        .tags
+        .iter()
+        .any(|(key, value)| key == "type" && (value == "multipolygon" || value == "boundary"));
+    if is_area {
+        return assemble_multipolygon(relation, way_resolver, node_resolver)
+            .multipolygon
+            .map(Geometry::from);
+    }
+
+    let geometries: Vec<Geometry<f64>> = relation
+        .members
+        .iter()
+        .filter_map(|member| match member.member_type {
+            MemberType::NODE => {
+                node_resolver(member.id).map(|(lon, lat)| Point::new(lon, lat).into())
+            }
+            MemberType::WAY => way_resolver(member.id)
+                .and_then(|node_ids| resolve_line(&node_ids, &node_resolver))
+                .map(Geometry::from),
+            MemberType::RELATION => None,
+        })
+        .collect();
+    if geometries.is_empty() {
+        return None;
+    }
+    Some(Geometry::GeometryCollection(GeometryCollection(geometries)))
+}
+
+// Resolves node ids to a line string, None if a node is missing or there are less than 2 nodes
+fn resolve_line<N>(node_ids: &[i64], node_resolver: &N) -> Option<LineString<f64>>
+where
+    N: Fn(i64) -> Option<(f64, f64)>,
+{
+    if node_ids.len() < 2 {
+        return None;
+    }
+    node_ids
+        .iter()
+        .map(|&node_id| node_resolver(node_id).map(|(x, y)| Coord { x, y }))
+        .collect()
+}
+
+impl OsmReader {
+    /// Creates an iterator that yields every element together with its [`Geometry`],
+    /// see [`node_geometry`], [`way_geometry`] and [`relation_geometry`].
+    /// Elements whose geometry cannot be built (e.g. missing nodes) are skipped.
+    /// Filters applied to the reader are respected, but the resolvers need to know
+    /// the nodes and ways outside of them, e.g. from a first pass over the file.
+    ///
+    /// `way_resolver` returns the node ids of a way,
+    /// `node_resolver` returns the (lon, lat) of a node.
+    pub fn geometries<W, N>(
+        self,
+        way_resolver: W,
+        node_resolver: N,
+    ) -> impl Iterator<Item = (Element, Geometry<f64>)>
+    where
+        W: Fn(i64) -> Option<Vec<i64>>,
+        N: Fn(i64) -> Option<(f64, f64)>,
+    {
+        self.blocks().flat_map(move |block| {
+            let mut geometries = Vec::new();
+            match &block {
+                ElementBlock::DenseNodeBlock(block) => {
+                    for node in block.iter() {
+                        let node = node.to_owned();
+                        let point = node_geometry(&node);
+                        geometries.push((node.into(), point.into()));
+                    }
+                }
+                ElementBlock::NodeBlock(block) => {
+                    for node in block.iter() {
+                        let node = node.to_owned();
+                        let point = node_geometry(&node);
+                        geometries.push((node.into(), point.into()));
+                    }
+                }
+                ElementBlock::WayBlock(block) => {
+                    for way in block.iter() {
+                        let way = way.to_owned();
+                        if let Some(geometry) = way_geometry(&way, &node_resolver) {
+                            geometries.push((way.into(), geometry));
+                        }
+                    }
+                }
+                ElementBlock::RelationBlock(block) => {
+                    for relation in block.iter() {
+                        let relation = relation.to_owned();
+                        if let Some(geometry) =
+                            relation_geometry(&relation, &way_resolver, &node_resolver)
+                        {
+                            geometries.push((relation.into(), geometry));
+                        }
+                    }
+                }
+            }
+            geometries
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PbfFixture;
+
+    #[test]
+    fn files_without_nodes_yield_no_geometries() {
+        for (fixture, name) in [
+            (PbfFixture::ways_only(), "ways-only-geometries"),
+            (PbfFixture::relations_only(), "relations-only-geometries"),
+        ] {
+            let file = fixture.to_temp_file(name);
+            let reader = OsmReader::from_path(&*file).unwrap();
+            // the file is the first pass, it knows no node locations
+            let node_ids = |id: i64| Some(vec![id, id + 1]);
+            let geometries = reader.geometries(node_ids, |_| None);
+            assert_eq!(geometries.count(), 0, "{name}");
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use crate::HeaderBlock;
+use alloc::{string::String, vec::Vec};
+use quick_protobuf::{BytesReader, MessageRead};
+
+// Producers with known quirks, matched case-insensitively against the start of the writing program.
+// Only add producers whose quirk is confirmed by a sample file or by their documentation.
+const KNOWN_PRODUCER_QUIRKS: &[(&str, ProducerQuirk)] = &[("JOSM", ProducerQuirk::NegativeIds)];
+
+/// A known deviation of the files of a producer from what readers usually expect,
+/// see [`OsmHeader::producer_quirks`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ProducerQuirk {
+    /// Elements created in an editor but not uploaded yet carry negative ids,
+    /// which [`IdPolicy::Strict`](crate::IdPolicy::Strict) rejects
+    NegativeIds,
+}
+
+/// The OSMHeader block at the start of a .osm.pbf file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OsmHeader {
+    required_features: Vec<String>,
+    optional_features: Vec<String>,
+    writing_program: Option<String>,
+    source: Option<String>,
+    replication_timestamp: Option<i64>,
+    replication_sequence: Option<i64>,
+    replication_base_url: Option<String>,
+}
+
+impl OsmHeader {
+    /// Parses the header from the bytes of an already decompressed OSMHeader blob
+    pub fn from_bytes(bytes: &[u8]) -> quick_protobuf::Result<Self> {
+        let mut reader = BytesReader::from_bytes(bytes);
+        let header = HeaderBlock::from_reader(&mut reader, bytes)?;
+
+        Ok(Self {
+            required_features: header
+                .required_features
+                .into_iter()
+                .map(|f| f.into_owned())
+                .collect(),
+            optional_features: header
+                .optional_features
+                .into_iter()
+                .map(|f| f.into_owned())
+                .collect(),
+            writing_program: header.writingprogram.map(|p| p.into_owned()),
+            source: header.source.map(|s| s.into_owned()),
+            replication_timestamp: header.osmosis_replication_timestamp,
+            replication_sequence: header.osmosis_replication_sequence_number,
+            replication_base_url: header.osmosis_replication_base_url.map(|u| u.into_owned()),
+        })
+    }
+    /// Get the features a parser has to support to read the file (e.g. `DenseNodes`)
+    pub fn required_features(&self) -> &[String] {
+        &self.required_features
+    }
+    /// Get the optional features of the file (e.g. `Sort.Type_then_ID`)
+    pub fn optional_features(&self) -> &[String] {
+        &self.optional_features
+    }
+    /// Get the program that wrote the file
+    pub fn writing_program(&self) -> Option<&str> {
+        self.writing_program.as_deref()
+    }
+    /// Get the known quirks of the program that wrote the file, empty for unknown programs.
+    /// The list is maintained in the crate and only names deviations seen in real files,
+    /// the parser itself already tolerates the common ones (e.g. dense nodes missing the trailing
+    /// 0 of their tags). Use [`OsmReader::with_producer_workarounds`](crate::OsmReader::with_producer_workarounds)
+    /// to adapt the reader to them.
+    pub fn producer_quirks(&self) -> Vec<ProducerQuirk> {
+        let Some(program) = self.writing_program() else {
+            return Vec::new();
+        };
+        KNOWN_PRODUCER_QUIRKS
+            .iter()
+            .filter(|(producer, _)| {
+                program
+                    .get(..producer.len())
+                    .is_some_and(|start| start.eq_ignore_ascii_case(producer))
+            })
+            .map(|&(_, quirk)| quirk)
+            .collect()
+    }
+    /// Get the source of the data
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+    /// Get the sequence number of the replication state the data is current to, if the producer recorded
+    /// it (e.g. osmium or osmupdate). Files produced from the diffs of a replication server carry the
+    /// sequence of the last applied diff, so an updater chains them like this:
+    ///
+    /// 1. read `replication_sequence()` of the current file, e.g. 4230996
+    /// 2. download and apply the diffs from 4230997 on, see [`OsmHeader::replication_diff_url`]
+    /// 3. write the updated file with the sequence of the last applied diff, which is where the next
+    ///    run continues
+    pub fn replication_sequence(&self) -> Option<i64> {
+        self.replication_sequence
+    }
+    /// Get the time of the replication state in seconds since the Unix epoch
+    pub fn replication_timestamp(&self) -> Option<i64> {
+        self.replication_timestamp
+    }
+    /// Get the time of the replication state as [`chrono::DateTime`].
+    /// Returns [`None`] if it is missing or out of chrono's range.
+    #[cfg(feature = "chrono")]
+    pub fn replication_datetime(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        chrono::DateTime::from_timestamp(self.replication_timestamp?, 0)
+    }
+    /// Get the url of the replication server the sequence refers to,
+    /// e.g. `https://planet.openstreetmap.org/replication/minute`
+    pub fn replication_base_url(&self) -> Option<&str> {
+        self.replication_base_url.as_deref()
+    }
+    /// Get the url of the diff with `sequence` on the replication server of the file, in the directory
+    /// layout of osmosis replication (e.g. `<base url>/004/230/997.osc.gz` for 4230997).
+    /// Returns [`None`] without a base url or for a negative sequence.
+    pub fn replication_diff_url(&self, sequence: i64) -> Option<String> {
+        let base_url = self.replication_base_url()?.trim_end_matches('/');
+        if sequence < 0 {
+            return None;
+        }
+        Some(alloc::format!(
+            "{base_url}/{:03}/{:03}/{:03}.osc.gz",
+            sequence / 1_000_000,
+            sequence / 1_000 % 1_000,
+            sequence % 1_000
+        ))
+    }
+    /// Check if elements are sorted by type (nodes, then ways, then relations) and then by id
+    pub fn is_sorted_by_type_then_id(&self) -> bool {
+        self.optional_features
+            .iter()
+            .any(|f| f == "Sort.Type_then_ID")
+    }
+    /// Check if blobs are sorted geographically, so elements of one region are stored close together
+    pub fn is_geographically_sorted(&self) -> bool {
+        self.optional_features
+            .iter()
+            .any(|f| f == "Sort.Geographic")
+    }
+}
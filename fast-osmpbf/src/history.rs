@@ -0,0 +1,325 @@
+use crate::{
+    parser::{OsmParser, ParseOptions},
+    Element, ElementBlock, ElementInfo, ElementRef, OsmReader, OwnedNode, OwnedRelation, OwnedWay,
+};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::{collections::VecDeque, io::ErrorKind, sync::Arc};
+
+/// The kind of change an entry of a change file applies, see [`OsmReader::changes`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ElementChange {
+    /// The element is new, its version is 1
+    Create,
+    /// An existing element changed, or the metadata does not tell
+    Modify,
+    /// The element was deleted, `visible` is false
+    Delete,
+}
+
+impl ElementChange {
+    /// Infers the change from the metadata of an entry: `visible == false` is a [`ElementChange::Delete`],
+    /// otherwise version 1 is a [`ElementChange::Create`] and everything else a [`ElementChange::Modify`],
+    /// including entries without metadata.
+    pub fn infer(info: Option<ElementInfo>) -> Self {
+        match info {
+            Some(info) if info.visible() == Some(false) => ElementChange::Delete,
+            Some(info) if info.version() == 1 => ElementChange::Create,
+            _ => ElementChange::Modify,
+        }
+    }
+}
+
+impl OsmReader {
+    /// Creates an iterator that yields every entry of a change file (e.g. a PBF converted from an
+    /// osmChange diff) with the [`ElementChange`] inferred by [`ElementChange::infer`] and its
+    /// metadata, in file order, so an update can be applied to a local database entry by entry.
+    /// A delete carries only the id and the metadata: its tags, node ids and members are empty and
+    /// the coordinates of a node are 0. Filters applied to the reader are respected.
+    ///
+    /// The inference needs the `visible` flag, which only files with history metadata carry,
+    /// without it deletes are indistinguishable from modifications.
+    /// Blobs are decoded in parallel but consumed in file order, an unreadable blob ends the
+    /// iterator with its error.
+    pub fn changes(
+        self,
+    ) -> impl Iterator<Item = std::io::Result<(ElementChange, Element, Option<ElementInfo>)>> {
+        self.ordered_blocks().flat_map(|blocks| match blocks {
+            Ok(blocks) => blocks.iter().flat_map(block_changes).map(Ok).collect(),
+            Err(err) => vec![Err(err)],
+        })
+    }
+
+    /// Creates an iterator that yields only the highest version of every element (e.g. to build a
+    /// snapshot from an osmChange derived file, which can contain multiple entries per id).
+    /// Entries of one id need to follow each other, so the file has to be sorted by `Sort.Type_then_ID`,
+    /// otherwise an error is returned. Of entries with the same version, the last one wins,
+    /// elements without metadata count as version -1. Ids whose highest version is a deletion
+    /// (`visible` is false) are left out. Filters applied to the reader are respected.
+    ///
+    /// Blobs are decoded in parallel but consumed in file order. An element out of order ends
+    /// the iterator with an [`ErrorKind::InvalidData`] error, as does an unreadable blob.
+    /// For unsorted files, collect all elements into a map keyed by
+    /// [`ElementId`](crate::ElementId) while keeping the highest version instead.
+    pub fn latest_versions(
+        self,
+    ) -> std::io::Result<impl Iterator<Item = std::io::Result<Element>>> {
+        if !self
+            .header()
+            .is_some_and(|header| header.is_sorted_by_type_then_id())
+        {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "Deduplicating versions requires a file sorted by Sort.Type_then_ID",
+            ));
+        }
+
+        let parse_options = self.parse_options();
+        Ok(LatestVersions {
+            reader: self,
+            parse_options,
+            ready: VecDeque::new(),
+            latest: None,
+            error: None,
+            done: false,
+        })
+    }
+}
+
+// Order of the element types in a file sorted by Sort.Type_then_ID
+const NODE: u8 = 0;
+const WAY: u8 = 1;
+const RELATION: u8 = 2;
+
+// The entry with the highest version seen so far of the current id, None if it is a deletion
+struct Latest {
+    element_type: u8,
+    id: i64,
+    version: i32,
+    element: Option<Element>,
+}
+
+struct LatestVersions {
+    reader: OsmReader,
+    parse_options: ParseOptions,
+    ready: VecDeque<Element>,
+    latest: Option<Latest>,
+    // returned once the elements completed before it are consumed
+    error: Option<std::io::Error>,
+    done: bool,
+}
+
+impl LatestVersions {
+    // Reads as many blobs as there are threads and decodes them in parallel, keeping file order
+    fn next_batch(&mut self) -> std::io::Result<Vec<Vec<ElementBlock>>> {
+        let batch_size = rayon::current_num_threads();
+        let mut blobs: Vec<Arc<[u8]>> = Vec::with_capacity(batch_size);
+        while blobs.len() < batch_size {
+            match self.reader.next_blob_with_offset()? {
+                Some((_, blob)) => blobs.push(blob),
+                None => {
+                    self.done = true;
+                    break;
+                }
+            }
+        }
+
+        let decompressor = &*self.reader.config.decompressor;
+        let parse_options = &self.parse_options;
+        blobs
+            .into_par_iter()
+            .map_init(Vec::new, |decompressed_blob, blob| {
+                OsmParser::deserialize_blob(&blob, decompressor, parse_options, decompressed_blob)
+            })
+            .collect()
+    }
+
+    // Keeps the entry if it is the highest version of its id, the element is only built if needed
+    fn push(
+        &mut self,
+        element_type: u8,
+        id: i64,
+        info: Option<ElementInfo>,
+        element: impl FnOnce() -> Element,
+    ) -> std::io::Result<()> {
+        let version = info.map_or(-1, |info| info.version());
+        let deleted = info.and_then(|info| info.visible()) == Some(false);
+        let element = || (!deleted).then(element);
+        match &mut self.latest {
+            Some(latest) if (latest.element_type, latest.id) == (element_type, id) => {
+                if version >= latest.version {
+                    latest.version = version;
+                    latest.element = element();
+                }
+            }
+            Some(latest) if (latest.element_type, latest.id) > (element_type, id) => {
+                return Err(std::io::Error::new(
+                    ErrorKind::InvalidData,
+                    "Elements are not sorted by type then id",
+                ));
+            }
+            latest => {
+                let next = Latest {
+                    element_type,
+                    id,
+                    version,
+                    element: element(),
+                };
+                if let Some(element) = latest.replace(next).and_then(|previous| previous.element) {
+                    self.ready.push_back(element);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn push_block(&mut self, block: &ElementBlock) -> std::io::Result<()> {
+        match block {
+            ElementBlock::DenseNodeBlock(block) => {
+                for node in block.iter() {
+                    // DenseNodeRef::id advances the ref, to_owned would then decode the wrong id
+                    let id = node.prev_id + block.nodes.id[node.index];
+                    self.push(NODE, id, node.info(), || node.to_owned().into())?;
+                }
+            }
+            ElementBlock::NodeBlock(block) => {
+                for node in block.iter() {
+                    self.push(NODE, node.id(), node.info(), || node.to_owned().into())?;
+                }
+            }
+            ElementBlock::WayBlock(block) => {
+                for way in block.iter() {
+                    self.push(WAY, way.id(), way.info(), || way.to_owned().into())?;
+                }
+            }
+            ElementBlock::RelationBlock(block) => {
+                for relation in block.iter() {
+                    self.push(RELATION, relation.id(), relation.info(), || {
+                        relation.to_owned().into()
+                    })?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn fill(&mut self) -> std::io::Result<()> {
+        for blocks in self.next_batch()? {
+            for block in &blocks {
+                self.push_block(block)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for LatestVersions {
+    type Item = std::io::Result<Element>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(element) = self.ready.pop_front() {
+                return Some(Ok(element));
+            }
+            if let Some(err) = self.error.take() {
+                return Some(Err(err));
+            }
+            if self.done {
+                return self.latest.take()?.element.map(Ok);
+            }
+            if let Err(err) = self.fill() {
+                // stop after the first error, the position in the file is unreliable then
+                // and higher versions of the current id may be lost
+                self.done = true;
+                self.latest = None;
+                self.error = Some(err);
+            }
+        }
+    }
+}
+
+// Converts every element of a block into a change entry, deletes are stripped down to their id
+fn block_changes(block: &ElementBlock) -> Vec<(ElementChange, Element, Option<ElementInfo>)> {
+    let mut changes = Vec::new();
+    let mut push = |element: ElementRef<'_>| {
+        let info = element.info();
+        let change = ElementChange::infer(info);
+        let element = match change {
+            ElementChange::Delete => deleted(&element),
+            _ => element.to_owned(),
+        };
+        changes.push((change, element, info));
+    };
+    match block {
+        ElementBlock::DenseNodeBlock(block) => block
+            .iter()
+            .for_each(|node| push(ElementRef::DenseNode(node))),
+        ElementBlock::NodeBlock(block) => {
+            block.iter().for_each(|node| push(ElementRef::Node(node)))
+        }
+        ElementBlock::WayBlock(block) => block.iter().for_each(|way| push(ElementRef::Way(way))),
+        ElementBlock::RelationBlock(block) => block
+            .iter()
+            .for_each(|relation| push(ElementRef::Relation(relation))),
+    }
+    changes
+}
+
+// An element with nothing but its id
+fn deleted(element: &ElementRef<'_>) -> Element {
+    let id = element.id();
+    match element {
+        ElementRef::DenseNode(_) | ElementRef::Node(_) => OwnedNode {
+            id,
+            lat: 0.0,
+            lon: 0.0,
+            tags: Vec::new(),
+        }
+        .into(),
+        ElementRef::Way(_) => OwnedWay {
+            id,
+            node_ids: Vec::new(),
+            tags: Vec::new(),
+        }
+        .into(),
+        ElementRef::Relation(_) => OwnedRelation {
+            id,
+            members: Vec::new(),
+            tags: Vec::new(),
+        }
+        .into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(version: i32, visible: Option<bool>) -> ElementInfo {
+        ElementInfo {
+            version,
+            timestamp: 0,
+            changeset: 0,
+            uid: 0,
+            user_sid: 0,
+            visible,
+            date_granularity: 1000,
+        }
+    }
+
+    #[test]
+    fn infer_changes_from_the_metadata() {
+        let cases = [
+            (Some(info(1, Some(false))), ElementChange::Delete),
+            (Some(info(3, Some(false))), ElementChange::Delete),
+            (Some(info(1, Some(true))), ElementChange::Create),
+            (Some(info(1, None)), ElementChange::Create),
+            (Some(info(2, Some(true))), ElementChange::Modify),
+            (Some(info(7, None)), ElementChange::Modify),
+            (Some(info(-1, None)), ElementChange::Modify),
+            (None, ElementChange::Modify),
+        ];
+        for (info, change) in cases {
+            assert_eq!(ElementChange::infer(info), change, "{info:?}");
+        }
+    }
+}
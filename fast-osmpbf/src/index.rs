@@ -0,0 +1,213 @@
+use crate::{parser::OsmParser, simd, ElementBlock, OsmReader, OwnedNode, OwnedRelation, OwnedWay};
+use rayon::iter::{ParallelBridge, ParallelIterator};
+use std::sync::Arc;
+
+/// The id range of one element type inside a blob
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlobIndexEntry {
+    /// Byte offset of the blob in the file
+    pub offset: u64,
+    /// Smallest id of the element type in the blob
+    pub min_id: i64,
+    /// Largest id of the element type in the blob
+    pub max_id: i64,
+}
+
+/// Maps id ranges to the blobs containing them, one list per element type.
+/// Each list is ordered by offset, which for files sorted by `Sort.Type_then_ID` also means ordered by id.
+/// Build it once with [`OsmReader::build_blob_index`] and reuse it for lookups.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlobIndex {
+    /// Blobs containing nodes (dense or plain)
+    pub nodes: Vec<BlobIndexEntry>,
+    /// Blobs containing ways
+    pub ways: Vec<BlobIndexEntry>,
+    /// Blobs containing relations
+    pub relations: Vec<BlobIndexEntry>,
+}
+
+impl BlobIndex {
+    // Binary search for the blob whose id range contains id
+    fn find(entries: &[BlobIndexEntry], id: i64) -> Option<u64> {
+        let pos = entries.partition_point(|entry| entry.max_id < id);
+        entries
+            .get(pos)
+            .filter(|entry| entry.min_id <= id)
+            .map(|entry| entry.offset)
+    }
+}
+
+// Extends the (min, max) id range of an element type inside a blob
+fn extend_range(range: &mut Option<(i64, i64)>, ids: impl Iterator<Item = i64>) {
+    for id in ids {
+        let (min, max) = range.get_or_insert((id, id));
+        *min = (*min).min(id);
+        *max = (*max).max(id);
+    }
+}
+
+impl OsmReader {
+    /// Scans the whole file once and builds a [`BlobIndex`] that maps id ranges to blob offsets.
+    /// Decoding happens in parallel, the element filter is respected.
+    pub fn build_blob_index(&mut self) -> std::io::Result<BlobIndex> {
+        // stop reading after the first error, the position in the file is unreliable then
+        let decompressor = Arc::clone(&self.config.decompressor);
+        let parse_options = self.parse_options();
+        let mut failed = false;
+        let blobs = std::iter::from_fn(|| {
+            if failed {
+                return None;
+            }
+            let blob = self.next_blob_with_offset().transpose();
+            failed = matches!(blob, Some(Err(_)));
+            blob
+        });
+
+        let mut ranges = blobs
+            .par_bridge()
+            .map_init(Vec::new, |decompressed_blob, blob| {
+                let (offset, blob) = blob?;
+                let mut nodes = None;
+                let mut ways = None;
+                let mut relations = None;
+                let blocks = OsmParser::deserialize_blob(
+                    &blob,
+                    &*decompressor,
+                    &parse_options,
+                    decompressed_blob,
+                )?;
+                for block in blocks {
+                    match block {
+                        ElementBlock::DenseNodeBlock(block) => extend_range(
+                            &mut nodes,
+                            simd::delta_decode_i64(&block.nodes.id).into_iter(),
+                        ),
+                        ElementBlock::NodeBlock(block) => {
+                            extend_range(&mut nodes, block.nodes.iter().map(|n| n.id))
+                        }
+                        ElementBlock::WayBlock(block) => {
+                            extend_range(&mut ways, block.ways.iter().map(|w| w.id))
+                        }
+                        ElementBlock::RelationBlock(block) => {
+                            extend_range(&mut relations, block.relations.iter().map(|r| r.id))
+                        }
+                    }
+                }
+                Ok((offset, [nodes, ways, relations]))
+            })
+            .collect::<std::io::Result<Vec<_>>>()?;
+        ranges.sort_unstable_by_key(|(offset, _)| *offset);
+
+        let mut index = BlobIndex::default();
+        for (offset, [nodes, ways, relations]) in ranges {
+            for (entries, range) in [
+                (&mut index.nodes, nodes),
+                (&mut index.ways, ways),
+                (&mut index.relations, relations),
+            ] {
+                if let Some((min_id, max_id)) = range {
+                    entries.push(BlobIndexEntry {
+                        offset,
+                        min_id,
+                        max_id,
+                    });
+                }
+            }
+        }
+        Ok(index)
+    }
+
+    /// Looks up a node by id, decoding only the one blob the index points to.
+    /// Returns an error if the file is not sorted by `Sort.Type_then_ID`.
+    /// Note that this moves the read position of the reader.
+    pub fn get_node(&mut self, index: &BlobIndex, id: i64) -> std::io::Result<Option<OwnedNode>> {
+        let Some(blocks) = self.blocks_at(&index.nodes, id)? else {
+            return Ok(None);
+        };
+        for block in blocks {
+            match block {
+                ElementBlock::DenseNodeBlock(block) => {
+                    let ids = simd::delta_decode_i64(&block.nodes.id);
+                    if let Some(pos) = ids.iter().position(|&node_id| node_id == id) {
+                        return Ok(block.iter().nth(pos).map(|node| node.to_owned()));
+                    }
+                }
+                ElementBlock::NodeBlock(block) => {
+                    if let Some(node) = block.iter().find(|node| node.id() == id) {
+                        return Ok(Some(node.to_owned()));
+                    }
+                }
+                _ => (),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Looks up a way by id, decoding only the one blob the index points to.
+    /// Returns an error if the file is not sorted by `Sort.Type_then_ID`.
+    /// Note that this moves the read position of the reader.
+    pub fn get_way(&mut self, index: &BlobIndex, id: i64) -> std::io::Result<Option<OwnedWay>> {
+        let Some(blocks) = self.blocks_at(&index.ways, id)? else {
+            return Ok(None);
+        };
+        Ok(blocks.iter().find_map(|block| match block {
+            ElementBlock::WayBlock(block) => block
+                .iter()
+                .find(|way| way.id() == id)
+                .map(|way| way.to_owned()),
+            _ => None,
+        }))
+    }
+
+    /// Looks up a relation by id, decoding only the one blob the index points to.
+    /// Returns an error if the file is not sorted by `Sort.Type_then_ID`.
+    /// Note that this moves the read position of the reader.
+    pub fn get_relation(
+        &mut self,
+        index: &BlobIndex,
+        id: i64,
+    ) -> std::io::Result<Option<OwnedRelation>> {
+        let Some(blocks) = self.blocks_at(&index.relations, id)? else {
+            return Ok(None);
+        };
+        Ok(blocks.iter().find_map(|block| match block {
+            ElementBlock::RelationBlock(block) => block
+                .iter()
+                .find(|relation| relation.id() == id)
+                .map(|relation| relation.to_owned()),
+            _ => None,
+        }))
+    }
+
+    // Decodes the blob that may contain id according to the index entries
+    fn blocks_at(
+        &mut self,
+        entries: &[BlobIndexEntry],
+        id: i64,
+    ) -> std::io::Result<Option<Vec<ElementBlock>>> {
+        if !self
+            .header()
+            .is_some_and(|header| header.is_sorted_by_type_then_id())
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Lookups by id require a file sorted by Sort.Type_then_ID",
+            ));
+        }
+
+        let Some(offset) = BlobIndex::find(entries, id) else {
+            return Ok(None);
+        };
+        self.seek_to(offset)?;
+        match self.next_blob_with_offset()? {
+            Some((_, blob)) => OsmParser::deserialize_blob(
+                &blob,
+                &*self.config.decompressor,
+                &self.parse_options(),
+                &mut self.decompressed,
+            )
+            .map(Some),
+            None => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use crate::{DenseInfo, Info, MemberType};
+
+/// Metadata of an element (version, timestamp, changeset and author).
+/// Files are not required to include it, so accessors on the element refs return an [`Option`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ElementInfo {
+    pub(crate) version: i32,
+    pub(crate) timestamp: i64,
+    pub(crate) changeset: i64,
+    pub(crate) uid: i32,
+    pub(crate) user_sid: u32,
+    pub(crate) visible: Option<bool>,
+    pub(crate) date_granularity: i64,
+}
+
+impl ElementInfo {
+    pub(crate) fn from_info(info: &Info, date_granularity: i64) -> Self {
+        Self {
+            version: info.version,
+            timestamp: info.timestamp.unwrap_or(0),
+            changeset: info.changeset.unwrap_or(0),
+            uid: info.uid.unwrap_or(0),
+            user_sid: info.user_sid.unwrap_or(0),
+            visible: info.visible,
+            date_granularity,
+        }
+    }
+    /// Get version (-1 if unknown)
+    #[inline]
+    pub fn version(&self) -> i32 {
+        self.version
+    }
+    /// Get raw timestamp, in units of the block's `date_granularity`
+    #[inline]
+    pub fn raw_timestamp(&self) -> i64 {
+        self.timestamp
+    }
+    /// Get timestamp in milliseconds since the unix epoch
+    #[inline]
+    pub fn timestamp_millis(&self) -> i64 {
+        self.timestamp * self.date_granularity
+    }
+    /// Get timestamp as [`SystemTime`](std::time::SystemTime)
+    #[cfg(feature = "std")]
+    pub fn system_time(&self) -> std::time::SystemTime {
+        let millis = self.timestamp_millis();
+        let duration = std::time::Duration::from_millis(millis.unsigned_abs());
+        if millis >= 0 {
+            std::time::UNIX_EPOCH + duration
+        } else {
+            std::time::UNIX_EPOCH - duration
+        }
+    }
+    /// Get timestamp as [`chrono::DateTime`]. Returns [`None`] if out of chrono's range.
+    #[cfg(feature = "chrono")]
+    pub fn datetime(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        chrono::DateTime::from_timestamp_millis(self.timestamp_millis())
+    }
+    /// Get changeset id
+    #[inline]
+    pub fn changeset(&self) -> i64 {
+        self.changeset
+    }
+    /// Get user id
+    #[inline]
+    pub fn uid(&self) -> i32 {
+        self.uid
+    }
+    /// Get index of the user name in the block's string table
+    #[inline]
+    pub fn user_sid(&self) -> u32 {
+        self.user_sid
+    }
+    /// Get visibility. Only present in history files, where `false` marks a deleted element.
+    #[inline]
+    pub fn visible(&self) -> Option<bool> {
+        self.visible
+    }
+}
+
+/// Id and metadata of one element, without its coordinates, tags and references.
+/// Created by [`ElementBlock::metadata`](crate::ElementBlock::metadata).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ElementMeta<'a> {
+    pub(crate) element_type: MemberType,
+    pub(crate) id: i64,
+    pub(crate) info: ElementInfo,
+    pub(crate) user: &'a str,
+}
+
+impl<'a> ElementMeta<'a> {
+    /// Get the type of the element
+    #[inline]
+    pub fn element_type(&self) -> MemberType {
+        self.element_type
+    }
+    /// Get ID
+    #[inline]
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+    /// Get metadata (version, timestamp, changeset, user)
+    #[inline]
+    pub fn info(&self) -> &ElementInfo {
+        &self.info
+    }
+    /// Get user name (empty if unknown or not valid UTF-8)
+    #[inline]
+    pub fn user(&self) -> &'a str {
+        self.user
+    }
+}
+
+// Accumulators for the delta encoded columns of DenseInfo
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct DenseInfoState {
+    timestamp: i64,
+    changeset: i64,
+    uid: i32,
+    user_sid: i32,
+}
+
+impl DenseInfoState {
+    // Applies the deltas of the node at index, yielding the state of that node
+    #[inline]
+    pub(crate) fn advance(&mut self, info: &DenseInfo, index: usize) {
+        self.timestamp += info.timestamp.get(index).copied().unwrap_or(0);
+        self.changeset += info.changeset.get(index).copied().unwrap_or(0);
+        self.uid += info.uid.get(index).copied().unwrap_or(0);
+        self.user_sid += info.user_sid.get(index).copied().unwrap_or(0);
+    }
+
+    // Decodes the info of the node at index, self being the state of the previous node
+    #[inline]
+    pub(crate) fn info(
+        mut self,
+        info: &DenseInfo,
+        index: usize,
+        date_granularity: i64,
+    ) -> ElementInfo {
+        self.advance(info, index);
+        ElementInfo {
+            version: info.version.get(index).copied().unwrap_or(-1),
+            timestamp: self.timestamp,
+            changeset: self.changeset,
+            uid: self.uid,
+            user_sid: self.user_sid as u32,
+            visible: info.visible.get(index).copied(),
+            date_granularity,
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+};
+
+/// Shares the strings of the string tables of all blocks, so identical strings (e.g. `highway`
+/// or `name`, which occur in the table of almost every block) are allocated only once.
+/// Useful when many blocks stay in memory, e.g. for an in-memory database of a whole region.
+///
+/// Every interned string is kept until the interner is dropped. Each block locks the interner once
+/// while its string table is built, so parsing gets slower with many threads, which is why it is opt-in.
+/// Register it with [`OsmReader::with_interner`](crate::OsmReader::with_interner), one interner can
+/// be shared by several readers.
+#[derive(Debug, Default)]
+pub struct Interner {
+    strings: Mutex<HashSet<Arc<[u8]>>>,
+}
+
+impl Interner {
+    /// Creates an empty interner
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the number of distinct strings
+    pub fn len(&self) -> usize {
+        self.strings.lock().unwrap().len()
+    }
+
+    /// Check if no string was interned yet
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Builds a string table out of the shared copies of strings
+    pub(crate) fn intern_all<'a>(
+        &self,
+        strings: impl IntoIterator<Item = &'a [u8]>,
+    ) -> Vec<Arc<[u8]>> {
+        let mut shared = self.strings.lock().unwrap();
+        strings
+            .into_iter()
+            .map(|s| match shared.get(s) {
+                Some(s) => Arc::clone(s),
+                None => {
+                    let s: Arc<[u8]> = Arc::from(s);
+                    shared.insert(Arc::clone(&s));
+                    s
+                }
+            })
+            .collect()
+    }
+}
@@ -1,17 +1,131 @@
 #![doc = include_str!("../README.md")]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
 
 include!(concat!(env!("OUT_DIR"), "/proto/mod.rs"));
 
+/// Contains the conversion of blocks into Arrow record batches
+#[cfg(feature = "arrow")]
+pub mod arrow;
+/// Contains the async reader for tokio
+#[cfg(feature = "tokio")]
+pub mod async_reader;
+/// Contains the decompression of blobs
+#[cfg(feature = "std")]
+pub mod decompress;
 /// Contains Element and corresponding Iterator
 pub mod element;
+// Contains the encoding of blocks and blobs shared by extracts and fixtures
+#[cfg(feature = "std")]
+pub(crate) mod encode;
+/// Contains the errors about the content of a file
+pub mod error;
+/// Exports elements to other formats
+#[cfg(feature = "csv")]
+pub mod export;
+/// Contains the writing of filtered extracts to .osm.pbf files
+#[cfg(feature = "std")]
+pub mod extract;
+/// Contains the generator of small .osm.pbf files for tests
+#[cfg(any(feature = "testing", all(test, feature = "std")))]
+pub mod fixture;
+/// Assembles geometries of elements
+#[cfg(feature = "geometry")]
+pub mod geometry;
+/// Contains the OSMHeader of a file
+pub mod header;
+/// Contains passes over history and change files
+#[cfg(feature = "std")]
+pub mod history;
+/// Contains an index over blobs for lookups by id
+#[cfg(feature = "std")]
+pub mod index;
+/// Contains metadata of elements
+pub mod info;
+/// Contains the sharing of strings across blocks
+#[cfg(feature = "std")]
+pub mod intern;
+/// Contains the on-disk store of node locations
+#[cfg(feature = "mmap")]
+pub mod locations;
+/// Contains the merging of small blocks into larger ones
+#[cfg(feature = "std")]
+pub mod merge;
+/// Contains the resolution of nested relations
+pub mod nested;
+/// Contains owned elements that do not borrow from their block
+pub mod owned;
 /// Handles parsing .osm.pbf files
 pub mod parser;
 /// Prelude
 pub mod prelude;
 /// Contains Reader and methods to apply filters
+#[cfg(feature = "std")]
 pub mod reader;
+/// Contains the reverse references of ways and relations
+#[cfg(feature = "std")]
+pub mod reverse;
+/// Contains the extraction of routing graphs
+#[cfg(feature = "std")]
+pub mod routing;
+/// SIMD accelerated bulk decoding of delta encoded columns and tag filtering
+pub mod simd;
+/// Contains the sorting of owned elements by id
+#[cfg(feature = "std")]
+pub mod sort;
+/// Contains the counters and timings of a run of the reader
+#[cfg(feature = "stats")]
+pub mod stats;
+/// Contains the extraction of web mercator tiles
+#[cfg(feature = "std")]
+pub mod tile;
+/// Contains the integrity check of files
+#[cfg(feature = "std")]
+pub mod validate;
 
+#[cfg(feature = "arrow")]
+pub use arrow::*;
+#[cfg(feature = "tokio")]
+pub use async_reader::*;
+#[cfg(feature = "std")]
+pub use decompress::*;
 pub use element::*;
+pub use error::*;
+#[cfg(feature = "csv")]
+pub use export::*;
+#[cfg(any(feature = "testing", all(test, feature = "std")))]
+pub use fixture::*;
+#[cfg(feature = "geometry")]
+pub use geometry::*;
+pub use header::*;
+#[cfg(feature = "std")]
+pub use history::*;
+#[cfg(feature = "std")]
+pub use index::*;
+pub use info::{ElementInfo, ElementMeta};
+#[cfg(feature = "std")]
+pub use intern::*;
+#[cfg(feature = "mmap")]
+pub use locations::*;
+#[cfg(feature = "std")]
+pub use merge::*;
+pub use nested::*;
 pub use osmdata::*;
 pub use osmformat::*;
+pub use owned::*;
+pub use parser::{decode_blob, decode_primitive_block};
+#[cfg(feature = "std")]
 pub use reader::*;
+#[cfg(feature = "std")]
+pub use reverse::*;
+#[cfg(feature = "std")]
+pub use routing::*;
+#[cfg(feature = "std")]
+pub use sort::*;
+#[cfg(feature = "stats")]
+pub use stats::*;
+#[cfg(feature = "std")]
+pub use tile::*;
+#[cfg(feature = "std")]
+pub use validate::*;
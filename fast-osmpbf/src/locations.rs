@@ -0,0 +1,204 @@
+use crate::{ElementBlock, OsmReader};
+use memmap2::MmapMut;
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+// Bytes per node id: latitude and longitude as i32 in units of 1e-7 degrees
+const SLOT_SIZE: u64 = 8;
+// Coordinates are stored XORed with this marker, so the zeroed holes of a grown (sparse) file decode
+// to it and read as ids without location. No coordinate in range reaches it.
+const EMPTY: i32 = i32::MAX;
+// Units of 1e-7 degrees
+const SCALE: i64 = 10_000_000;
+// The file grows in steps of this many ids (128 MiB), so inserting ascending ids rarely remaps it
+const GROWTH_SLOTS: u64 = 1 << 24;
+
+/// An on-disk store of node locations indexed by node id, backed by a memory mapped flat file with
+/// 8 bytes per id like the dense file index of osmium. Lets way geometries of a planet be resolved on
+/// machines without the memory to hold all node locations, the operating system keeps the pages of
+/// recently used ids cached.
+///
+/// Coordinates are rounded to 1e-7 degrees (about 1 cm). The file holds 8 bytes for every id up to the
+/// largest one, grown in steps of 128 MiB (about 100 GB for a planet), but on filesystems with sparse
+/// files only the ranges of ids that occur use disk space. Lookups are O(1), negative ids cannot be stored.
+/// Changes reach the file when the pages are written back by the operating system, at the latest when
+/// the store is dropped, or explicitly with [`LocationFile::flush`].
+#[derive(Debug)]
+pub struct LocationFile {
+    file: File,
+    // None while the file is empty, empty files cannot be mapped on every platform
+    map: Option<MmapMut>,
+    slots: u64,
+}
+
+impl LocationFile {
+    /// Creates an empty store at `path`, an existing file is truncated
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        Ok(Self {
+            file,
+            map: None,
+            slots: 0,
+        })
+    }
+
+    /// Opens a store written earlier, e.g. by a previous run of [`OsmReader::build_location_file`].
+    /// Fails with [`ErrorKind::InvalidData`] if the length of the file is not a multiple of 8 bytes.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        let len = file.metadata()?.len();
+        if len % SLOT_SIZE != 0 {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "length of the location file is not a multiple of 8 bytes",
+            ));
+        }
+        let mut store = Self {
+            file,
+            map: None,
+            slots: len / SLOT_SIZE,
+        };
+        store.remap()?;
+        Ok(store)
+    }
+
+    /// Stores the location of a node, replacing an earlier one of the same id.
+    /// The file grows as needed, fails with [`ErrorKind::InvalidInput`] for a negative id
+    /// or a coordinate outside of [-180, 180] x [-90, 90].
+    pub fn insert(&mut self, id: i64, lon: f64, lat: f64) -> io::Result<()> {
+        if !(-180.0..=180.0).contains(&lon) || !(-90.0..=90.0).contains(&lat) {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "node location out of range",
+            ));
+        }
+        let scale = SCALE as f64;
+        let (lon, lat) = ((lon * scale).round() as i32, (lat * scale).round() as i32);
+        self.insert_scaled(id, lon, lat)
+    }
+
+    /// Get the (lon, lat) of a node, [`None`] if no location was stored for `id`.
+    /// The order matches the node resolvers of the `geometry` feature,
+    /// e.g. `way_geometry(&way, |id| locations.get(id))`.
+    pub fn get(&self, id: i64) -> Option<(f64, f64)> {
+        let map = self.map.as_ref()?;
+        if id < 0 || id as u64 >= self.slots {
+            return None;
+        }
+        let start = id as usize * SLOT_SIZE as usize;
+        let slot = &map[start..start + SLOT_SIZE as usize];
+        let lat = i32::from_le_bytes(slot[..4].try_into().unwrap()) ^ EMPTY;
+        let lon = i32::from_le_bytes(slot[4..].try_into().unwrap()) ^ EMPTY;
+        if lat == EMPTY {
+            return None;
+        }
+        let scale = SCALE as f64;
+        Some((lon as f64 / scale, lat as f64 / scale))
+    }
+
+    /// Writes all changes to the file and waits until they are on disk
+    pub fn flush(&self) -> io::Result<()> {
+        match &self.map {
+            Some(map) => map.flush(),
+            None => Ok(()),
+        }
+    }
+
+    // Stores coordinates already in units of 1e-7 degrees
+    fn insert_scaled(&mut self, id: i64, lon: i32, lat: i32) -> io::Result<()> {
+        if id < 0 {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "negative node ids cannot be stored in a location file",
+            ));
+        }
+        let id = id as u64;
+        if id >= self.slots {
+            self.slots = (id / GROWTH_SLOTS + 1) * GROWTH_SLOTS;
+            self.file.set_len(self.slots * SLOT_SIZE)?;
+            self.remap()?;
+        }
+        let map = self.map.as_mut().expect("a grown file is mapped");
+        let start = id as usize * SLOT_SIZE as usize;
+        map[start..start + 4].copy_from_slice(&(lat ^ EMPTY).to_le_bytes());
+        map[start + 4..start + 8].copy_from_slice(&(lon ^ EMPTY).to_le_bytes());
+        Ok(())
+    }
+
+    // Maps the whole file again after its length changed
+    fn remap(&mut self) -> io::Result<()> {
+        self.map = None;
+        if self.slots > 0 {
+            // the file is opened for writing by this store only, modifying it from elsewhere while
+            // it is mapped is not supported
+            self.map = Some(unsafe { MmapMut::map_mut(&self.file)? });
+        }
+        Ok(())
+    }
+}
+
+impl OsmReader {
+    /// Scans the nodes of the file into a new [`LocationFile`] at `path`, e.g. as the first pass
+    /// of resolving way geometries. Filters applied to the reader are respected, so a bbox filter
+    /// stores only the nodes inside of it, and an [`ElementFilter`](crate::ElementFilter) with only
+    /// nodes skips parsing ways and relations. Fails for nodes with negative ids, a file without nodes
+    /// (e.g. a derived file of only ways) yields an empty store.
+    pub fn build_location_file<P: AsRef<Path>>(self, path: P) -> io::Result<LocationFile> {
+        let mut locations = LocationFile::create(path)?;
+        let mut blocks = self.blocks();
+        for block in blocks.by_ref() {
+            match &block {
+                ElementBlock::DenseNodeBlock(block) => {
+                    for mut node in block.iter() {
+                        let lon = node.lon_scaled_rounded(SCALE) as i32;
+                        let lat = node.lat_scaled_rounded(SCALE) as i32;
+                        locations.insert_scaled(node.id(), lon, lat)?;
+                    }
+                }
+                ElementBlock::NodeBlock(block) => {
+                    for node in block.iter() {
+                        let lon = node.lon_scaled_rounded(SCALE) as i32;
+                        let lat = node.lat_scaled_rounded(SCALE) as i32;
+                        locations.insert_scaled(node.id(), lon, lat)?;
+                    }
+                }
+                _ => (),
+            }
+        }
+        blocks.finish()?;
+        Ok(locations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PbfFixture;
+
+    #[test]
+    fn files_without_nodes_build_empty_location_files() {
+        for (fixture, name) in [
+            (PbfFixture::ways_only(), "ways-only-locations"),
+            (PbfFixture::relations_only(), "relations-only-locations"),
+        ] {
+            let file = fixture.to_temp_file(name);
+            let store = file.with_extension("locations");
+            let locations = OsmReader::from_path(&*file)
+                .unwrap()
+                .build_location_file(&store)
+                .unwrap();
+            assert!((0..=10).all(|id| locations.get(id).is_none()), "{name}");
+            assert_eq!(std::fs::metadata(&store).unwrap().len(), 0, "{name}");
+            drop(locations);
+            std::fs::remove_file(&store).unwrap();
+        }
+    }
+}
@@ -0,0 +1,491 @@
+use crate::element::{CachedTagIds, RawStringTable};
+use crate::parser::OsmParser;
+#[cfg(feature = "stats")]
+use crate::ParseStats;
+use crate::{
+    BBox, DenseInfo, DenseNodeBlock, DenseNodes, ElementBlock, ElementBlockIter, Info, NodeBlock,
+    RelationBlock, WayBlock,
+};
+use std::{collections::HashMap, iter::FusedIterator, sync::Arc};
+
+/// Iterator over the blocks of [`OsmReader::coalesced_blocks`](crate::OsmReader::coalesced_blocks),
+/// consecutive small blocks of the same type come merged into one block.
+///
+/// Merging is not free: the elements are copied into a new block, every string of the merged
+/// string tables costs a hash lookup and the tag, role and user ids of the elements are rewritten
+/// to the merged table. Dense nodes also decode and re-encode their delta encoded columns.
+/// This pays off for fragmented files with many blocks of a few hundred elements, where the
+/// channel and the per-block work of the consumer dominate. Blocks of full size (8000 elements
+/// in most files) reach the target on their own and pass through unchanged.
+///
+/// Tags, roles and user names resolve to the same strings as before, the ids of the merged block
+/// refer to its merged string table (e.g. [`ElementInfo::user_sid`](crate::ElementInfo::user_sid)
+/// and [`ElementBlock::string_table_raw`]). Only blocks with the same granularities, offsets and
+/// source compression are merged. The merged blocks are held outside of the in flight limits.
+pub struct CoalescedBlocks {
+    blocks: ElementBlockIter,
+    target_elements: usize,
+    // the block that ended the last run, it starts the next one
+    next: Option<ElementBlock>,
+}
+
+impl CoalescedBlocks {
+    pub(crate) fn new(blocks: ElementBlockIter, target_elements: usize) -> Self {
+        Self {
+            blocks,
+            target_elements,
+            next: None,
+        }
+    }
+
+    /// Waits for the background threads and returns the first error they ran into,
+    /// see [`ElementBlockIter::finish`]
+    pub fn finish(self) -> std::io::Result<()> {
+        self.blocks.finish()
+    }
+
+    /// Get the blob counts, sizes and timings of the run so far, see [`ElementBlockIter::stats`]
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> ParseStats {
+        self.blocks.stats()
+    }
+}
+
+impl Iterator for CoalescedBlocks {
+    type Item = ElementBlock;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let first = self.next.take().or_else(|| self.blocks.next())?;
+        let mut len = block_len(&first);
+        let mut run = vec![first];
+        while len < self.target_elements {
+            let Some(block) = self.blocks.next() else {
+                break;
+            };
+            if len + block_len(&block) > self.target_elements || !mergeable(&run[0], &block) {
+                self.next = Some(block);
+                break;
+            }
+            len += block_len(&block);
+            run.push(block);
+        }
+        Some(merge_blocks(run))
+    }
+}
+
+impl FusedIterator for CoalescedBlocks {}
+
+impl std::fmt::Debug for CoalescedBlocks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CoalescedBlocks")
+            .field("target_elements", &self.target_elements)
+            .finish_non_exhaustive()
+    }
+}
+
+// Number of elements of a block, including the ones hidden by filters
+fn block_len(block: &ElementBlock) -> usize {
+    match block {
+        ElementBlock::DenseNodeBlock(block) => block.len(),
+        ElementBlock::NodeBlock(block) => block.len(),
+        ElementBlock::WayBlock(block) => block.len(),
+        ElementBlock::RelationBlock(block) => block.len(),
+    }
+}
+
+// Check if the elements of both blocks decode the same way once they share a string table
+fn mergeable(a: &ElementBlock, b: &ElementBlock) -> bool {
+    match (a, b) {
+        (ElementBlock::DenseNodeBlock(a), ElementBlock::DenseNodeBlock(b)) => {
+            // dense info columns are all optional, only the visible flags cannot be filled in
+            let info_shape = |block: &DenseNodeBlock| {
+                block.nodes.denseinfo.as_ref().map(|i| i.visible.is_empty())
+            };
+            (a.granularity, a.lat_offset, a.lon_offset)
+                == (b.granularity, b.lat_offset, b.lon_offset)
+                && a.date_granularity == b.date_granularity
+                && a.compression == b.compression
+                && info_shape(a) == info_shape(b)
+        }
+        (ElementBlock::NodeBlock(a), ElementBlock::NodeBlock(b)) => {
+            (a.granularity, a.lat_offset, a.lon_offset)
+                == (b.granularity, b.lat_offset, b.lon_offset)
+                && a.date_granularity == b.date_granularity
+                && a.compression == b.compression
+        }
+        (ElementBlock::WayBlock(a), ElementBlock::WayBlock(b)) => {
+            a.date_granularity == b.date_granularity && a.compression == b.compression
+        }
+        (ElementBlock::RelationBlock(a), ElementBlock::RelationBlock(b)) => {
+            a.date_granularity == b.date_granularity && a.compression == b.compression
+        }
+        _ => false,
+    }
+}
+
+// Merges mergeable blocks into the first one, in order
+fn merge_blocks(mut run: Vec<ElementBlock>) -> ElementBlock {
+    if run.len() == 1 {
+        return run.pop().unwrap();
+    }
+    match &run[0] {
+        ElementBlock::DenseNodeBlock(_) => ElementBlock::DenseNodeBlock(merge_dense(
+            run.into_iter()
+                .filter_map(ElementBlock::into_dense_node_block),
+        )),
+        ElementBlock::NodeBlock(_) => ElementBlock::NodeBlock(merge_nodes(
+            run.into_iter().filter_map(ElementBlock::into_node_block),
+        )),
+        ElementBlock::WayBlock(_) => ElementBlock::WayBlock(merge_ways(
+            run.into_iter().filter_map(ElementBlock::into_way_block),
+        )),
+        ElementBlock::RelationBlock(_) => ElementBlock::RelationBlock(merge_relations(
+            run.into_iter()
+                .filter_map(ElementBlock::into_relation_block),
+        )),
+    }
+}
+
+// String table, raw string table and tag filter ids of a merged block
+type BlockTables = (
+    Arc<Vec<Arc<[u8]>>>,
+    Option<Arc<RawStringTable>>,
+    Arc<CachedTagIds>,
+);
+
+// The string table of a merged block, built from the tables of its parts
+struct MergedTable {
+    strings: Vec<Arc<[u8]>>,
+    ids: HashMap<Arc<[u8]>, u32>,
+    // ids of the tag filter keys, None without a tag filter
+    tag_ids: Option<Vec<u32>>,
+    key_count: usize,
+}
+
+impl MergedTable {
+    fn new(cached_tag_ids: &CachedTagIds) -> Self {
+        let mut table = Self {
+            strings: Vec::new(),
+            ids: HashMap::new(),
+            tag_ids: cached_tag_ids.ids.as_ref().map(|_| Vec::new()),
+            key_count: cached_tag_ids.key_count,
+        };
+        // id 0 stays the empty string, it ends the tags of a node in DenseNodes
+        table.id_of(&Arc::from(&b""[..]));
+        table
+    }
+
+    fn id_of(&mut self, string: &Arc<[u8]>) -> u32 {
+        *self.ids.entry(Arc::clone(string)).or_insert_with(|| {
+            self.strings.push(Arc::clone(string));
+            (self.strings.len() - 1) as u32
+        })
+    }
+
+    // Adds the strings of the table of a part, returns the merged id of every id of the part
+    fn add(&mut self, table: &[Arc<[u8]>], cached_tag_ids: &CachedTagIds) -> Vec<u32> {
+        let map: Vec<u32> = table.iter().map(|string| self.id_of(string)).collect();
+        if let (Some(tag_ids), Some(ids)) = (&mut self.tag_ids, &cached_tag_ids.ids) {
+            tag_ids.extend(ids.iter().map(|&id| remap(&map, id)));
+            tag_ids.sort_unstable();
+            tag_ids.dedup();
+        }
+        map
+    }
+
+    fn finish(self, raw_table: bool) -> BlockTables {
+        let raw_table = raw_table.then(|| Arc::new(RawStringTable::new(&self.strings)));
+        let cached_tag_ids = CachedTagIds {
+            ids: self.tag_ids,
+            key_count: self.key_count,
+        };
+        (Arc::new(self.strings), raw_table, Arc::new(cached_tag_ids))
+    }
+}
+
+// Ids outside of the table of a part (the table is left out if nothing needs it) are kept
+#[inline]
+fn remap(map: &[u32], id: u32) -> u32 {
+    map.get(id as usize).copied().unwrap_or(id)
+}
+
+fn remap_all(map: &[u32], ids: &mut [u32]) {
+    for id in ids {
+        *id = remap(map, *id);
+    }
+}
+
+fn remap_info(map: &[u32], info: &mut Option<Info>) {
+    if let Some(user_sid) = info.as_mut().and_then(|info| info.user_sid.as_mut()) {
+        *user_sid = remap(map, *user_sid);
+    }
+}
+
+// Appends the predicate matches of the next part, a part without them matches completely
+fn extend_matches(
+    merged: &mut Option<Vec<bool>>,
+    len: usize,
+    matches: Option<Vec<bool>>,
+    part_len: usize,
+) {
+    match (merged.as_mut(), matches) {
+        (Some(merged), Some(matches)) => merged.extend(matches),
+        (Some(merged), None) => merged.resize(len + part_len, true),
+        (None, Some(matches)) => {
+            let mut all = vec![true; len];
+            all.extend(matches);
+            *merged = Some(all);
+        }
+        (None, None) => (),
+    }
+}
+
+// Decodes a delta encoded column of a part, missing deltas at the end repeat the last value
+fn absolute<T: Copy + Into<i64>>(deltas: &[T], len: usize) -> impl Iterator<Item = i64> + '_ {
+    (0..len).scan(0i64, move |value, index| {
+        *value += deltas.get(index).map_or(0, |&delta| delta.into());
+        Some(*value)
+    })
+}
+
+// Encodes absolute values as deltas in place
+fn delta_encode(values: &mut [i64]) {
+    for index in (1..values.len()).rev() {
+        values[index] -= values[index - 1];
+    }
+}
+
+fn merge_dense(parts: impl Iterator<Item = DenseNodeBlock>) -> DenseNodeBlock {
+    let mut parts = parts.peekable();
+    let first = parts.peek().expect("a run is never empty");
+    let (granularity, lat_offset, lon_offset) =
+        (first.granularity, first.lat_offset, first.lon_offset);
+    let (date_granularity, bbox, compression) =
+        (first.date_granularity, first.bbox, first.compression);
+    let raw_table = first.raw_table.is_some();
+    let has_info = first.nodes.denseinfo.is_some();
+    let mut table = MergedTable::new(&first.cached_tag_ids);
+
+    let mut merged = DenseNodes::default();
+    let mut info = DenseInfo::default();
+    // absolute timestamps, changesets, uids and user ids, and if any part has the column
+    let (mut timestamps, mut changesets) = (Vec::new(), Vec::new());
+    let (mut uids, mut user_sids) = (Vec::new(), Vec::new());
+    let mut has_column = [false; 4];
+    let mut matches = None;
+    let mut bbox_hint = first.bbox_hint;
+    let mut has_tags = false;
+    for mut part in parts {
+        let map = table.add(&part.table, &part.cached_tag_ids);
+        bbox_hint = union_hint(bbox_hint, part.bbox_hint);
+        let len = part.len();
+        let nodes = &*part.nodes;
+        merged.id.extend(absolute(&nodes.id, len));
+        merged.lat.extend(absolute(&nodes.lat, len));
+        merged.lon.extend(absolute(&nodes.lon, len));
+
+        // tags are copied node by node, so a missing zero at the end cannot join the next part
+        has_tags |= !nodes.keys_vals.is_empty();
+        for index in 0..len {
+            let tags = &nodes.keys_vals[part.kv_range(index)];
+            for pair in tags.chunks_exact(2).take_while(|pair| pair[0] != 0) {
+                merged.keys_vals.push(remap(&map, pair[0] as u32) as i32);
+                merged.keys_vals.push(remap(&map, pair[1] as u32) as i32);
+            }
+            merged.keys_vals.push(0);
+        }
+
+        if let Some(part_info) = &nodes.denseinfo {
+            let versions = (0..len).map(|i| part_info.version.get(i).copied().unwrap_or(-1));
+            info.version.extend(versions);
+            if !part_info.visible.is_empty() {
+                let visible = (0..len).map(|i| part_info.visible.get(i).copied().unwrap_or(true));
+                info.visible.extend(visible);
+            }
+            timestamps.extend(absolute(&part_info.timestamp, len));
+            changesets.extend(absolute(&part_info.changeset, len));
+            uids.extend(absolute(&part_info.uid, len));
+            let sids = absolute(&part_info.user_sid, len);
+            user_sids.extend(sids.map(|sid| i64::from(remap(&map, sid as u32))));
+            has_column[0] |= !part_info.timestamp.is_empty();
+            has_column[1] |= !part_info.changeset.is_empty();
+            has_column[2] |= !part_info.uid.is_empty();
+            has_column[3] |= !part_info.user_sid.is_empty();
+        }
+        extend_matches(
+            &mut matches,
+            merged.id.len() - len,
+            part.matches.take(),
+            len,
+        );
+    }
+
+    for column in [&mut merged.id, &mut merged.lat, &mut merged.lon] {
+        delta_encode(column);
+    }
+    if !has_tags {
+        merged.keys_vals = Vec::new();
+    }
+    if has_info {
+        // columns no part had stay empty, like in the parts
+        let encode = |mut values: Vec<i64>, present: bool| {
+            delta_encode(&mut values);
+            if present {
+                values
+            } else {
+                Vec::new()
+            }
+        };
+        info.timestamp = encode(timestamps, has_column[0]);
+        info.changeset = encode(changesets, has_column[1]);
+        let to_i32 = |values: Vec<i64>| values.into_iter().map(|v| v as i32).collect();
+        info.uid = to_i32(encode(uids, has_column[2]));
+        info.user_sid = to_i32(encode(user_sids, has_column[3]));
+        merged.denseinfo = Some(info);
+    }
+
+    let (table, raw_table, cached_tag_ids) = table.finish(raw_table);
+    DenseNodeBlock {
+        kv_offsets: OsmParser::compute_offsets(&merged.keys_vals, merged.id.len()),
+        nodes: Arc::new(merged),
+        table,
+        raw_table,
+        cached_tag_ids,
+        granularity,
+        lat_offset,
+        lon_offset,
+        date_granularity,
+        bbox,
+        matches,
+        compression,
+        bbox_hint,
+    }
+}
+
+fn merge_nodes(parts: impl Iterator<Item = NodeBlock>) -> NodeBlock {
+    let mut parts = parts.peekable();
+    let first = parts.peek().expect("a run is never empty");
+    let (granularity, lat_offset, lon_offset) =
+        (first.granularity, first.lat_offset, first.lon_offset);
+    let (date_granularity, bbox, compression) =
+        (first.date_granularity, first.bbox, first.compression);
+    let raw_table = first.raw_table.is_some();
+    let mut table = MergedTable::new(&first.cached_tag_ids);
+
+    let mut nodes = Vec::new();
+    let mut matches = None;
+    let mut bbox_hint = first.bbox_hint;
+    for mut part in parts {
+        let map = table.add(&part.table, &part.cached_tag_ids);
+        bbox_hint = union_hint(bbox_hint, part.bbox_hint);
+        let len = part.len();
+        for mut node in Arc::unwrap_or_clone(part.nodes) {
+            remap_all(&map, &mut node.keys);
+            remap_all(&map, &mut node.vals);
+            remap_info(&map, &mut node.info);
+            nodes.push(node);
+        }
+        extend_matches(&mut matches, nodes.len() - len, part.matches.take(), len);
+    }
+
+    let (table, raw_table, cached_tag_ids) = table.finish(raw_table);
+    NodeBlock {
+        nodes: Arc::new(nodes),
+        cached_tag_ids,
+        table,
+        raw_table,
+        granularity,
+        lat_offset,
+        lon_offset,
+        date_granularity,
+        bbox,
+        matches,
+        compression,
+        bbox_hint,
+    }
+}
+
+fn merge_ways(parts: impl Iterator<Item = WayBlock>) -> WayBlock {
+    let mut parts = parts.peekable();
+    let first = parts.peek().expect("a run is never empty");
+    let (date_granularity, compression) = (first.date_granularity, first.compression);
+    let raw_table = first.raw_table.is_some();
+    let mut table = MergedTable::new(&first.cached_tag_ids);
+
+    let mut ways = Vec::new();
+    let mut matches = None;
+    let mut bbox_hint = first.bbox_hint;
+    for mut part in parts {
+        let map = table.add(&part.table, &part.cached_tag_ids);
+        bbox_hint = union_hint(bbox_hint, part.bbox_hint);
+        let len = part.len();
+        for mut way in Arc::unwrap_or_clone(part.ways) {
+            remap_all(&map, &mut way.keys);
+            remap_all(&map, &mut way.vals);
+            remap_info(&map, &mut way.info);
+            ways.push(way);
+        }
+        extend_matches(&mut matches, ways.len() - len, part.matches.take(), len);
+    }
+
+    let (table, raw_table, cached_tag_ids) = table.finish(raw_table);
+    WayBlock {
+        ways: Arc::new(ways),
+        cached_tag_ids,
+        table,
+        raw_table,
+        date_granularity,
+        matches,
+        compression,
+        bbox_hint,
+    }
+}
+
+fn merge_relations(parts: impl Iterator<Item = RelationBlock>) -> RelationBlock {
+    let mut parts = parts.peekable();
+    let first = parts.peek().expect("a run is never empty");
+    let (date_granularity, compression) = (first.date_granularity, first.compression);
+    let raw_table = first.raw_table.is_some();
+    let mut table = MergedTable::new(&first.cached_tag_ids);
+
+    let mut relations = Vec::new();
+    let mut matches = None;
+    let mut bbox_hint = first.bbox_hint;
+    for mut part in parts {
+        let map = table.add(&part.table, &part.cached_tag_ids);
+        bbox_hint = union_hint(bbox_hint, part.bbox_hint);
+        let len = part.len();
+        for mut relation in Arc::unwrap_or_clone(part.relations) {
+            remap_all(&map, &mut relation.keys);
+            remap_all(&map, &mut relation.vals);
+            remap_info(&map, &mut relation.info);
+            for role in &mut relation.roles_sid {
+                *role = remap(&map, *role as u32) as i32;
+            }
+            relations.push(relation);
+        }
+        extend_matches(
+            &mut matches,
+            relations.len() - len,
+            part.matches.take(),
+            len,
+        );
+    }
+
+    let (table, raw_table, cached_tag_ids) = table.finish(raw_table);
+    RelationBlock {
+        relations: Arc::new(relations),
+        cached_tag_ids,
+        table,
+        raw_table,
+        date_granularity,
+        matches,
+        compression,
+        bbox_hint,
+    }
+}
+
+// The bbox hint of merged blocks, only known if every part has one
+fn union_hint(merged: Option<BBox>, part: Option<BBox>) -> Option<BBox> {
+    Some(merged?.union(&part?))
+}
@@ -0,0 +1,117 @@
+use crate::{Element, MemberType, OwnedRelation, RelationMember};
+use alloc::{
+    collections::{BTreeSet, VecDeque},
+    vec::Vec,
+};
+
+/// The members collected by [`resolve_nested_relation`], every list is sorted and free of duplicates
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NestedMembers {
+    /// Ids of the node members of the relation and of all resolved sub relations
+    pub node_ids: Vec<i64>,
+    /// Ids of the way members of the relation and of all resolved sub relations
+    pub way_ids: Vec<i64>,
+    /// Ids of the resolved relations, including the relation itself
+    pub relation_ids: Vec<i64>,
+    /// Ids of the relations the resolver did not know or that are deeper than the depth limit,
+    /// their members are missing from the other lists
+    pub unresolved_relation_ids: Vec<i64>,
+}
+
+/// Collects the node and way ids a relation references directly or through its relation members
+/// (e.g. the ways of all states of a country boundary super-relation), in breadth first order.
+///
+/// `resolver` returns a relation by id, e.g. [`OsmReader::get_relation`](crate::OsmReader::get_relation)
+/// or a lookup into relations collected in a first pass. It is called at most once per relation id,
+/// so cycles (relations that are members of each other, directly or not) end the recursion.
+/// Relation members are followed to `max_depth` levels below the relation, 0 only collects its own members.
+pub fn resolve_nested_relation<R>(id: i64, max_depth: usize, mut resolver: R) -> NestedMembers
+where
+    R: FnMut(i64) -> Option<OwnedRelation>,
+{
+    let mut node_ids = BTreeSet::new();
+    let mut way_ids = BTreeSet::new();
+    let mut relation_ids = BTreeSet::new();
+    let mut unresolved = BTreeSet::new();
+
+    // relations seen as member (or as the root), so every relation is queued once
+    let mut seen = BTreeSet::from([id]);
+    let mut queue = VecDeque::from([(id, 0)]);
+    while let Some((id, depth)) = queue.pop_front() {
+        let Some(relation) = (depth <= max_depth).then(|| resolver(id)).flatten() else {
+            unresolved.insert(id);
+            continue;
+        };
+        relation_ids.insert(id);
+        for member in &relation.members {
+            match member.member_type {
+                MemberType::NODE => {
+                    node_ids.insert(member.id);
+                }
+                MemberType::WAY => {
+                    way_ids.insert(member.id);
+                }
+                MemberType::RELATION => {
+                    if seen.insert(member.id) {
+                        queue.push_back((member.id, depth + 1));
+                    }
+                }
+            }
+        }
+    }
+
+    NestedMembers {
+        node_ids: node_ids.into_iter().collect(),
+        way_ids: way_ids.into_iter().collect(),
+        relation_ids: relation_ids.into_iter().collect(),
+        unresolved_relation_ids: unresolved.into_iter().collect(),
+    }
+}
+
+/// A member of a relation, yielded by [`RelationRef::resolved_members`](crate::RelationRef::resolved_members)
+#[derive(Debug)]
+pub enum ResolvedMember<'a, R> {
+    /// A member whose element is fetched on demand with [`LazyMember::resolve`]
+    Lazy(LazyMember<'a, R>),
+    /// A relation member that is the relation itself, walking into it would never end.
+    /// Longer cycles (relations that are members of each other) are only detected by
+    /// [`resolve_nested_relation`].
+    Cycle(RelationMember<'a>),
+}
+
+/// A member of a relation together with the resolver of its element, see [`ResolvedMember`]
+#[derive(Debug)]
+pub struct LazyMember<'a, R> {
+    member: RelationMember<'a>,
+    resolver: &'a R,
+}
+
+impl<'a, R> LazyMember<'a, R>
+where
+    R: Fn(MemberType, i64) -> Option<Element>,
+{
+    pub(crate) fn new(member: RelationMember<'a>, resolver: &'a R) -> Self {
+        Self { member, resolver }
+    }
+    /// Get the id of the member
+    #[inline]
+    pub fn id(&self) -> i64 {
+        self.member.id()
+    }
+    /// Get the element type of the member
+    #[inline]
+    pub fn member_type(&self) -> MemberType {
+        self.member.member_type()
+    }
+    /// Get the role of the member (e.g. `outer`)
+    #[inline]
+    pub fn role(&self) -> &'a str {
+        self.member.role()
+    }
+    /// Fetches the element with the resolver, on every call, so keep the result to use it twice.
+    /// Returns [`None`] if the resolver does not know the element, which is common for extracts
+    /// (members outside of the region are left out) and for deleted elements.
+    pub fn resolve(&self) -> Option<Element> {
+        (self.resolver)(self.member_type(), self.id())
+    }
+}
@@ -0,0 +1,171 @@
+use crate::MemberType;
+use alloc::{borrow::ToOwned, string::String, vec::Vec};
+use core::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+};
+
+/// An owned OSM element that does not borrow from its [`ElementBlock`](crate::ElementBlock).
+/// Mostly useful for tests, where you want to compare parsed elements against expected values.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Element {
+    /// An owned [`Node`](crate::Node)
+    Node(OwnedNode),
+    /// An owned [`Way`](crate::Way)
+    Way(OwnedWay),
+    /// An owned [`Relation`](crate::Relation)
+    Relation(OwnedRelation),
+}
+
+/// An owned node with decoded coordinates.
+/// Tags are sorted by (key, value), so equality does not depend on tag order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OwnedNode {
+    /// ID
+    pub id: i64,
+    /// Latitude
+    pub lat: f64,
+    /// Longitude
+    pub lon: f64,
+    /// Sorted (key, value) pairs
+    pub tags: Vec<(String, String)>,
+}
+
+/// An owned way with decoded node ids.
+/// Tags are sorted by (key, value), so equality does not depend on tag order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OwnedWay {
+    /// ID
+    pub id: i64,
+    /// Node ids in way order
+    pub node_ids: Vec<i64>,
+    /// Sorted (key, value) pairs
+    pub tags: Vec<(String, String)>,
+}
+
+/// An owned relation with decoded members.
+/// Tags are sorted by (key, value), so equality does not depend on tag order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OwnedRelation {
+    /// ID
+    pub id: i64,
+    /// Members in relation order
+    pub members: Vec<OwnedRelationMember>,
+    /// Sorted (key, value) pairs
+    pub tags: Vec<(String, String)>,
+}
+
+/// An owned member of an [`OwnedRelation`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct OwnedRelationMember {
+    /// ID
+    pub id: i64,
+    /// Element type ([`Node`](crate::Node), [`Way`](crate::Way) or [`Relation`](crate::Relation))
+    pub member_type: MemberType,
+    /// Role
+    pub role: String,
+}
+
+impl From<OwnedNode> for Element {
+    fn from(node: OwnedNode) -> Self {
+        Element::Node(node)
+    }
+}
+
+impl From<OwnedWay> for Element {
+    fn from(way: OwnedWay) -> Self {
+        Element::Way(way)
+    }
+}
+
+impl From<OwnedRelation> for Element {
+    fn from(relation: OwnedRelation) -> Self {
+        Element::Relation(relation)
+    }
+}
+
+/// Identity of an owned element: its element type and id
+pub trait ElementId {
+    /// Get element type ([`Node`](crate::Node), [`Way`](crate::Way) or [`Relation`](crate::Relation)) and ID
+    fn element_id(&self) -> (MemberType, i64);
+}
+
+impl ElementId for OwnedNode {
+    fn element_id(&self) -> (MemberType, i64) {
+        (MemberType::NODE, self.id)
+    }
+}
+
+impl ElementId for OwnedWay {
+    fn element_id(&self) -> (MemberType, i64) {
+        (MemberType::WAY, self.id)
+    }
+}
+
+impl ElementId for OwnedRelation {
+    fn element_id(&self) -> (MemberType, i64) {
+        (MemberType::RELATION, self.id)
+    }
+}
+
+impl ElementId for Element {
+    fn element_id(&self) -> (MemberType, i64) {
+        match self {
+            Element::Node(node) => node.element_id(),
+            Element::Way(way) => way.element_id(),
+            Element::Relation(relation) => relation.element_id(),
+        }
+    }
+}
+
+/// Wrapper that compares, orders and hashes an owned element by (element type, id) only,
+/// so you can deduplicate and merge elements from different sources in a `HashSet` or `BTreeSet`.
+///
+/// This identity is deliberately distinct from the `PartialEq` of the owned types,
+/// which compares all values (coordinates, tags, ...) and is meant for tests.
+/// Elements are ordered nodes first, then ways, then relations, each by id.
+#[derive(Debug, Clone)]
+pub struct ById<T>(pub T);
+
+impl<T: ElementId> ById<T> {
+    // element type as number so it can be ordered and hashed
+    fn key(&self) -> (i32, i64) {
+        let (member_type, id) = self.0.element_id();
+        (member_type as i32, id)
+    }
+}
+
+impl<T: ElementId> PartialEq for ById<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl<T: ElementId> Eq for ById<T> {}
+
+impl<T: ElementId> PartialOrd for ById<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: ElementId> Ord for ById<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl<T: ElementId> Hash for ById<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+// Collects (key, value) pairs into a sorted Vec so comparisons are order-independent
+pub(crate) fn owned_tags<'a>(
+    tags: impl Iterator<Item = (&'a str, &'a str)>,
+) -> Vec<(String, String)> {
+    let mut tags: Vec<(String, String)> = tags.map(|(k, v)| (k.to_owned(), v.to_owned())).collect();
+    tags.sort_unstable();
+    tags
+}
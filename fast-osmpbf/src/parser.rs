@@ -1,19 +1,100 @@
+use crate::element::{CachedTagIds, RawStringTable};
 use crate::{
-    Blob, DenseNodeBlock, ElementBlock, NodeBlock, PrimitiveBlock, RelationBlock, WayBlock,
-    ELEMENT_FILTER, TAG_KEYS_FILTER,
+    BBox, DenseNodeBlock, ElementBlock, ElementFilter, NodeBlock, PrimitiveBlock, RelationBlock,
+    WayBlock,
 };
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
 use quick_protobuf::{BytesReader, MessageRead};
-use std::{borrow::Cow, io::Read, sync::Arc};
+#[cfg(feature = "std")]
+use crate::{Blob, BlobDecompressor, BlobStats, Compression, IdPolicy, Interner, OsmError};
+
+/// Parses [`ElementBlock`]s from the bytes of an already decompressed `PrimitiveBlock`.
+/// This is the entry point when you read and decompress blobs yourself,
+/// e.g. without the `std` feature where [`OsmReader`](crate::OsmReader) is not available.
+/// The blocks are ordered dense nodes, nodes, ways, relations, regardless of the group order in the blob.
+pub fn decode_blob(blob: &[u8]) -> quick_protobuf::Result<Vec<ElementBlock>> {
+    OsmParser::parse_blob(blob, &ParseOptions::default())
+}
+
+/// Same as [`decode_blob`], but only parses the element types enabled in `filter`.
+/// Useful to plug the element model onto blobs coming from a custom data source.
+pub fn decode_primitive_block(
+    blob: &[u8],
+    filter: ElementFilter,
+) -> quick_protobuf::Result<Vec<ElementBlock>> {
+    let options = ParseOptions {
+        element_filter: Some(filter),
+        ..ParseOptions::default()
+    };
+    OsmParser::parse_blob(blob, &options)
+}
+
+// Options and filters of the reader that change how blobs are parsed
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ParseOptions {
+    // drop all tags right after decoding, see OsmReader::with_skip_tags
+    pub(crate) skip_tags: bool,
+    // also drop way refs and relation members, keep the string table, see with_metadata_only
+    pub(crate) metadata_only: bool,
+    // drop ways with less than 2 node refs, see OsmReader::with_skip_degenerate_ways
+    pub(crate) skip_degenerate_ways: bool,
+    // also keep the string table flattened, see OsmReader::with_raw_string_table
+    pub(crate) raw_string_table: bool,
+    pub(crate) element_filter: Option<ElementFilter>,
+    // sorted keys of the tag filter
+    pub(crate) tag_keys: Option<Arc<[Box<str>]>>,
+    pub(crate) bbox: Option<BBox>,
+    #[cfg(feature = "std")]
+    pub(crate) id_policy: IdPolicy,
+    // shares the strings of the string tables, see OsmReader::with_interner
+    #[cfg(feature = "std")]
+    pub(crate) interner: Option<Arc<Interner>>,
+}
 
 pub(crate) struct OsmParser;
 impl OsmParser {
     /// Deserialize blob_slices into a Blob.
-    /// Then decompresses the blob if its stored in a compressed state.
+    /// Then decompresses the blob if its stored in a compressed state,
+    /// into a buffer that is reused across blobs (e.g. one per worker).
     /// Then parses ElementBlocks inside the decompressed blob.
-    pub(crate) fn deserialize_blob(blob_slice: Arc<[u8]>) -> std::io::Result<Vec<ElementBlock>> {
+    #[cfg(feature = "std")]
+    pub(crate) fn deserialize_blob(
+        blob_slice: &[u8],
+        decompressor: &dyn BlobDecompressor,
+        options: &ParseOptions,
+        decompressed_blob: &mut Vec<u8>,
+    ) -> std::io::Result<Vec<ElementBlock>> {
+        let stats = Self::decompress_blob_into(blob_slice, decompressor, 0, decompressed_blob)?;
+        let mut element_blocks = Self::parse_blob(decompressed_blob, options)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        for block in &mut element_blocks {
+            block.set_source_compression(stats.compression);
+        }
+        Self::validate_ids(&element_blocks, options.id_policy)?;
+        Ok(element_blocks)
+    }
+    /// Deserialize blob_slice into a Blob and decompresses it with the given decompressor.
+    #[cfg(feature = "std")]
+    pub(crate) fn decompress_blob(
+        blob_slice: &[u8],
+        decompressor: &dyn BlobDecompressor,
+    ) -> std::io::Result<Vec<u8>> {
+        let mut decompressed_blob = Vec::new();
+        Self::decompress_blob_into(blob_slice, decompressor, 0, &mut decompressed_blob)?;
+        Ok(decompressed_blob)
+    }
+    /// Same as decompress_blob, but decompresses into a buffer that is reused across blobs
+    /// and reports size and codec of the blob at offset. The buffer is cleared first.
+    #[cfg(feature = "std")]
+    pub(crate) fn decompress_blob_into(
+        blob_slice: &[u8],
+        decompressor: &dyn BlobDecompressor,
+        offset: u64,
+        decompressed_blob: &mut Vec<u8>,
+    ) -> std::io::Result<BlobStats> {
         // Deserialize blob
-        let mut reader = BytesReader::from_bytes(&blob_slice);
-        let blob = Blob::from_reader(&mut reader, &blob_slice)
+        let mut reader = BytesReader::from_bytes(blob_slice);
+        let blob = Blob::from_reader(&mut reader, blob_slice)
             .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
 
         // either take the raw_size if available or use 2 * compressed_size as heuristic
@@ -21,42 +102,66 @@ impl OsmParser {
             Some(raw_size) => raw_size as usize,
             None => blob_slice.len() * 2,
         };
-        let mut decompressed_blob: Vec<u8> = Vec::with_capacity(size);
-        if let Some(raw) = &blob.raw {
-            decompressed_blob.extend_from_slice(raw);
-        } else if let Some(zlib) = &blob.zlib_data {
-            let mut decoder = flate2::read::ZlibDecoder::new(&zlib[..]);
-            decoder.read_to_end(&mut decompressed_blob)?;
-        } else if let Some(lzma) = &blob.lzma_data {
-            let mut decoder = xz2::read::XzDecoder::new(&lzma[..]);
-            decoder.read_to_end(&mut decompressed_blob)?;
-        } else {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "Empty OSMData blob",
-            ));
-        };
+        decompressed_blob.clear();
+        decompressed_blob.reserve(size);
+        decompressor.decompress(&blob, decompressed_blob)?;
 
-        return Self::parse_blob(&decompressed_blob);
+        let (compression, compressed_len) =
+            Compression::of(&blob).unwrap_or((Compression::Raw, 0));
+        let stats = BlobStats {
+            offset,
+            compression,
+            compressed_len,
+            raw_size: blob.raw_size.map(|raw_size| raw_size as usize),
+            decompressed_len: decompressed_blob.len(),
+        };
+        Ok(stats)
     }
     // Processes a blob in parallel using rayon (one task per PrimitiveGroup)
-    fn parse_blob(blob: &[u8]) -> std::io::Result<Vec<ElementBlock>> {
+    pub(crate) fn parse_blob(
+        blob: &[u8],
+        options: &ParseOptions,
+    ) -> quick_protobuf::Result<Vec<ElementBlock>> {
         let mut reader = BytesReader::from_bytes(blob);
-        let block = PrimitiveBlock::from_reader(&mut reader, blob)
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
-        let table: Vec<Cow<'static, [u8]>> = block
-            .stringtable
-            .s
-            .into_iter()
-            .map(|s| Cow::Owned(s.to_vec()))
-            .collect();
-        let stringtable = Arc::new(table);
-        let cached_tag_ids = match TAG_KEYS_FILTER.get() {
-            Some(_) => Self::get_tag_ids(&stringtable),
-            None => Arc::new(Vec::with_capacity(0)),
+        let mut block = PrimitiveBlock::from_reader(&mut reader, blob)?;
+        if options.skip_degenerate_ways {
+            Self::strip_degenerate_ways(&mut block);
+        }
+        let skip_tags = options.skip_tags || options.metadata_only;
+        if skip_tags {
+            Self::strip_tags(&mut block);
+        }
+        if options.metadata_only {
+            Self::strip_references(&mut block);
+        }
+
+        // without tags the string table is only needed for user names and roles of relation members
+        let needs_table = !skip_tags
+            || options.metadata_only
+            || block.primitivegroup.iter().any(|g| !g.relations.is_empty());
+        let table = if needs_table {
+            Self::string_table(block.stringtable.s.iter().map(|s| &**s), options)
+        } else {
+            Vec::new()
         };
+        let raw_table = options
+            .raw_string_table
+            .then(|| Arc::new(RawStringTable::new(&table)));
+        let stringtable = Arc::new(table);
+        let cached_tag_ids = Arc::new(match &options.tag_keys {
+            Some(keys) => CachedTagIds {
+                // tags are stripped with skip_tags, so no key can pass the filter
+                ids: Some(if skip_tags {
+                    Vec::with_capacity(0)
+                } else {
+                    Self::get_tag_ids(&stringtable, keys)
+                }),
+                key_count: keys.len(),
+            },
+            None => CachedTagIds::default(),
+        });
 
-        let element_filter = ELEMENT_FILTER.get();
+        let element_filter = options.element_filter;
 
         let element_count: usize = block
             .primitivegroup
@@ -71,104 +176,397 @@ impl OsmParser {
         let mut elements: Vec<ElementBlock> = Vec::with_capacity(element_count);
 
         for group in block.primitivegroup {
-            if let Some(dense_nodes) = group.dense {
-                if element_filter.map_or(true, |f| f.nodes) {
-                    let table = Arc::clone(&stringtable);
-                    elements.push(ElementBlock::DenseNodeBlock(DenseNodeBlock {
-                        table,
-                        cached_tag_ids: Arc::clone(&cached_tag_ids),
-                        granularity: block.granularity,
-                        lat_offset: block.lat_offset,
-                        lon_offset: block.lon_offset,
-                        kv_offsets: Self::compute_offsets(
-                            &dense_nodes.keys_vals,
-                            dense_nodes.id.len(),
-                        ),
-                        nodes: Arc::from(dense_nodes),
-                    }));
-                }
+            if let Some(dense_nodes) = group.dense
+                && element_filter.is_none_or(|f| f.nodes)
+            {
+                let table = Arc::clone(&stringtable);
+                let raw_table = raw_table.clone();
+                elements.push(ElementBlock::DenseNodeBlock(DenseNodeBlock {
+                    table,
+                    raw_table,
+                    cached_tag_ids: Arc::clone(&cached_tag_ids),
+                    granularity: block.granularity,
+                    lat_offset: block.lat_offset,
+                    lon_offset: block.lon_offset,
+                    date_granularity: block.date_granularity,
+                    kv_offsets: Self::compute_offsets(&dense_nodes.keys_vals, dense_nodes.id.len()),
+                    nodes: Arc::from(dense_nodes),
+                    bbox: options.bbox,
+                    matches: None,
+                    #[cfg(feature = "std")]
+                    compression: Compression::Raw,
+                    #[cfg(feature = "std")]
+                    bbox_hint: None,
+                }));
             }
-            if !group.nodes.is_empty() {
-                if element_filter.map_or(true, |f| f.nodes) {
-                    let table = Arc::clone(&stringtable);
-                    elements.push(ElementBlock::NodeBlock(NodeBlock {
-                        nodes: Arc::from(group.nodes),
-                        cached_tag_ids: Arc::clone(&cached_tag_ids),
-                        table,
-                    }));
-                }
+            if !group.nodes.is_empty() && element_filter.is_none_or(|f| f.nodes) {
+                let table = Arc::clone(&stringtable);
+                let raw_table = raw_table.clone();
+                elements.push(ElementBlock::NodeBlock(NodeBlock {
+                    nodes: Arc::from(group.nodes),
+                    cached_tag_ids: Arc::clone(&cached_tag_ids),
+                    table,
+                    raw_table,
+                    granularity: block.granularity,
+                    lat_offset: block.lat_offset,
+                    lon_offset: block.lon_offset,
+                    date_granularity: block.date_granularity,
+                    bbox: options.bbox,
+                    matches: None,
+                    #[cfg(feature = "std")]
+                    compression: Compression::Raw,
+                    #[cfg(feature = "std")]
+                    bbox_hint: None,
+                }));
             }
 
-            if !group.ways.is_empty() {
-                if element_filter.map_or(true, |f| f.ways) {
-                    let table = Arc::clone(&stringtable);
-                    elements.push(ElementBlock::WayBlock(WayBlock {
-                        ways: Arc::from(group.ways),
-                        cached_tag_ids: Arc::clone(&cached_tag_ids),
-                        table,
-                    }));
-                }
+            if !group.ways.is_empty() && element_filter.is_none_or(|f| f.ways) {
+                let table = Arc::clone(&stringtable);
+                let raw_table = raw_table.clone();
+                elements.push(ElementBlock::WayBlock(WayBlock {
+                    ways: Arc::from(group.ways),
+                    cached_tag_ids: Arc::clone(&cached_tag_ids),
+                    table,
+                    raw_table,
+                    date_granularity: block.date_granularity,
+                    matches: None,
+                    #[cfg(feature = "std")]
+                    compression: Compression::Raw,
+                    #[cfg(feature = "std")]
+                    bbox_hint: None,
+                }));
             }
 
-            if !group.relations.is_empty() {
-                if element_filter.map_or(true, |f| f.relations) {
-                    let table = Arc::clone(&stringtable);
-                    elements.push(ElementBlock::RelationBlock(RelationBlock {
-                        relations: Arc::from(group.relations),
-                        cached_tag_ids: Arc::clone(&cached_tag_ids),
-                        table,
-                    }));
-                }
+            if !group.relations.is_empty() && element_filter.is_none_or(|f| f.relations) {
+                let table = Arc::clone(&stringtable);
+                let raw_table = raw_table.clone();
+                elements.push(ElementBlock::RelationBlock(RelationBlock {
+                    relations: Arc::from(group.relations),
+                    cached_tag_ids: Arc::clone(&cached_tag_ids),
+                    table,
+                    raw_table,
+                    date_granularity: block.date_granularity,
+                    matches: None,
+                    #[cfg(feature = "std")]
+                    compression: Compression::Raw,
+                    #[cfg(feature = "std")]
+                    bbox_hint: None,
+                }));
             }
         }
 
+        // stable order of element types, so blocks of one blob can be streamed nodes before ways
+        elements.sort_by_key(|block| match block {
+            ElementBlock::DenseNodeBlock(_) => 0,
+            ElementBlock::NodeBlock(_) => 1,
+            ElementBlock::WayBlock(_) => 2,
+            ElementBlock::RelationBlock(_) => 3,
+        });
+
         Ok(elements)
     }
 
-    // Gets tag ids from stringtable if corresponding value is in TAG_KEYS_CACHE
-    fn get_tag_ids(table: &[Cow<'_, [u8]>]) -> Arc<Vec<u32>> {
-        Arc::new(
-            table
-                .iter()
-                .enumerate()
-                .filter_map(|(i, s)| {
-                    let key = unsafe { std::str::from_utf8_unchecked(s) };
-                    let cache = TAG_KEYS_FILTER.get().unwrap();
-
-                    // Branchless linear scan for ≤8 elements
-                    if cache.iter().any(|&k| k == key) {
-                        Some(i as u32)
-                    } else {
-                        None
+    // Checks the references of all ways and relations against the policy
+    #[cfg(feature = "std")]
+    pub(crate) fn validate_ids(blocks: &[ElementBlock], policy: IdPolicy) -> Result<(), OsmError> {
+        if policy == IdPolicy::Lenient {
+            return Ok(());
+        }
+        for block in blocks {
+            match block {
+                ElementBlock::WayBlock(block) => {
+                    for way in block.iter() {
+                        way.validate_node_ids(policy)?;
+                    }
+                }
+                ElementBlock::RelationBlock(block) => {
+                    for relation in block.iter() {
+                        relation.validate_members(policy)?;
                     }
-                })
-                .collect::<Vec<u32>>(),
-        )
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    // Drops the tags of all elements, so tag iterators are empty and no offsets need to be computed
+    fn strip_tags(block: &mut PrimitiveBlock) {
+        for group in block.primitivegroup.iter_mut() {
+            if let Some(dense_nodes) = group.dense.as_mut() {
+                dense_nodes.keys_vals = Vec::new();
+            }
+            for node in group.nodes.iter_mut() {
+                node.keys = Vec::new();
+                node.vals = Vec::new();
+            }
+            for way in group.ways.iter_mut() {
+                way.keys = Vec::new();
+                way.vals = Vec::new();
+            }
+            for relation in group.relations.iter_mut() {
+                relation.keys = Vec::new();
+                relation.vals = Vec::new();
+            }
+        }
+    }
+
+    // Drops ways with fewer than 2 node references
+    fn strip_degenerate_ways(block: &mut PrimitiveBlock) {
+        for group in block.primitivegroup.iter_mut() {
+            group.ways.retain(|way| way.refs.len() >= 2);
+        }
+    }
+
+    // Drops the node references of ways and the members of relations
+    fn strip_references(block: &mut PrimitiveBlock) {
+        for group in block.primitivegroup.iter_mut() {
+            for way in group.ways.iter_mut() {
+                way.refs = Vec::new();
+            }
+            for relation in group.relations.iter_mut() {
+                relation.roles_sid = Vec::new();
+                relation.memids = Vec::new();
+                relation.types = Vec::new();
+            }
+        }
+    }
+
+    // Copies the strings of a string table, shared with other blocks if there is an interner
+    #[cfg_attr(not(feature = "std"), allow(unused_variables))]
+    fn string_table<'a>(
+        strings: impl Iterator<Item = &'a [u8]>,
+        options: &ParseOptions,
+    ) -> Vec<Arc<[u8]>> {
+        #[cfg(feature = "std")]
+        if let Some(interner) = &options.interner {
+            return interner.intern_all(strings);
+        }
+        strings.map(Arc::from).collect()
+    }
+
+    // Gets tag ids from stringtable if corresponding value is in the tag filter
+    fn get_tag_ids(table: &[Arc<[u8]>], cache: &[Box<str>]) -> Vec<u32> {
+        table
+            .iter()
+            .enumerate()
+            .filter_map(|(i, s)| {
+                let key = unsafe { core::str::from_utf8_unchecked(s) };
+
+                // Branchless linear scan for ≤8 elements
+                if cache.iter().any(|k| **k == *key) {
+                    Some(i as u32)
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<u32>>()
     }
 
     // Computes offsets for keys_vals in DenseNodes
     // key_vals looks like [k, v, k, v, k, v, ..., 0, k, v, k, v ... 0 ...]
-    fn compute_offsets(keys_vals: &[i32], node_count: usize) -> Vec<usize> {
+    // It is empty if no node is tagged, and some encoders omit the zero of the last node.
+    // Offsets never point past the end, a truncated keys_vals leaves the remaining nodes untagged.
+    // Without any tags (common for bulk imports) there are no offsets at all, see DenseNodeBlock::kv_range.
+    pub(crate) fn compute_offsets(keys_vals: &[i32], node_count: usize) -> Vec<usize> {
+        if keys_vals.is_empty() {
+            return Vec::new();
+        }
         let mut offsets = Vec::with_capacity(node_count + 1);
         offsets.push(0);
 
+        let len = keys_vals.len();
         let mut idx = 0;
 
         for _ in 0..node_count {
-            while idx < keys_vals.len() && keys_vals[idx] != 0 {
+            while idx < len && keys_vals[idx] != 0 {
                 idx += 2; // skip k, v pair
             }
 
-            if idx >= keys_vals.len() {
-                // malformed, but avoid UB
-                offsets.push(idx);
-                continue;
-            }
-
-            idx += 1; // skip terminating zero
+            // skip terminating zero, a key without value at the end is dropped
+            idx = (idx + 1).min(len);
             offsets.push(idx);
         }
 
         offsets
     }
 }
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::encode::encode_message;
+    use crate::{DenseNodes, Node, PrimitiveGroup, Relation, StringTable, Way};
+    use alloc::borrow::Cow;
+
+    fn block_type(block: &ElementBlock) -> &'static str {
+        match block {
+            ElementBlock::DenseNodeBlock(_) => "dense nodes",
+            ElementBlock::NodeBlock(_) => "nodes",
+            ElementBlock::WayBlock(_) => "ways",
+            ElementBlock::RelationBlock(_) => "relations",
+        }
+    }
+
+    #[test]
+    fn blocks_of_a_blob_are_emitted_nodes_ways_relations() {
+        let relations = PrimitiveGroup {
+            relations: vec![Relation {
+                id: 1,
+                ..Relation::default()
+            }],
+            ..PrimitiveGroup::default()
+        };
+        let ways = PrimitiveGroup {
+            ways: vec![Way {
+                id: 2,
+                refs: vec![1, 1],
+                ..Way::default()
+            }],
+            ..PrimitiveGroup::default()
+        };
+        let nodes = PrimitiveGroup {
+            nodes: vec![Node {
+                id: 3,
+                ..Node::default()
+            }],
+            ..PrimitiveGroup::default()
+        };
+        let dense = PrimitiveGroup {
+            dense: Some(DenseNodes {
+                id: vec![4],
+                lat: vec![0],
+                lon: vec![0],
+                ..DenseNodes::default()
+            }),
+            ..PrimitiveGroup::default()
+        };
+        // a group with several types, which the format does not allow but some writers produce
+        let mixed = PrimitiveGroup {
+            dense: dense.dense.clone(),
+            ..ways.clone()
+        };
+        let block = PrimitiveBlock {
+            primitivegroup: vec![relations, ways, mixed, nodes, dense],
+            ..PrimitiveBlock::default()
+        };
+
+        let blocks = decode_blob(&encode_message(&block)).unwrap();
+        let types: Vec<_> = blocks.iter().map(block_type).collect();
+        assert_eq!(
+            types,
+            [
+                "dense nodes",
+                "dense nodes",
+                "nodes",
+                "ways",
+                "ways",
+                "relations"
+            ]
+        );
+    }
+
+    // Tags of every node of dense nodes with keys_vals as given, over the strings "", k, v, x, y
+    fn dense_tags(keys_vals: Vec<i32>, node_count: i64) -> Vec<Vec<(String, String)>> {
+        let strings = ["", "k", "v", "x", "y"];
+        let block = PrimitiveBlock {
+            stringtable: StringTable {
+                s: strings.map(|s| Cow::Borrowed(s.as_bytes())).to_vec(),
+            },
+            primitivegroup: vec![PrimitiveGroup {
+                dense: Some(DenseNodes {
+                    id: vec![1; node_count as usize],
+                    lat: vec![0; node_count as usize],
+                    lon: vec![0; node_count as usize],
+                    keys_vals,
+                    ..DenseNodes::default()
+                }),
+                ..PrimitiveGroup::default()
+            }],
+            ..PrimitiveBlock::default()
+        };
+        let blocks = decode_blob(&encode_message(&block)).unwrap();
+        let [ElementBlock::DenseNodeBlock(block)] = &blocks[..] else {
+            panic!("expected one block of dense nodes");
+        };
+        block
+            .iter()
+            .map(|node| {
+                node.tags()
+                    .map(|(k, v)| (k.to_owned(), v.to_owned()))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn tags(tags: &[(&str, &str)]) -> Vec<(String, String)> {
+        tags.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn dense_tags_of_all_tagged_nodes() {
+        let keys_vals = vec![1, 2, 0, 3, 4, 1, 2, 0, 1, 4, 0];
+        assert_eq!(OsmParser::compute_offsets(&keys_vals, 3), [0, 3, 8, 11]);
+        assert_eq!(
+            dense_tags(keys_vals, 3),
+            [
+                tags(&[("k", "v")]),
+                tags(&[("x", "y"), ("k", "v")]),
+                tags(&[("k", "y")])
+            ]
+        );
+    }
+
+    #[test]
+    fn dense_tags_of_untagged_nodes() {
+        // without any tag keys_vals is usually empty, but may also hold a zero per node
+        assert!(OsmParser::compute_offsets(&[], 3).is_empty());
+        assert_eq!(dense_tags(vec![], 3), [tags(&[]), tags(&[]), tags(&[])]);
+        assert_eq!(OsmParser::compute_offsets(&[0, 0, 0], 3), [0, 1, 2, 3]);
+        assert_eq!(
+            dense_tags(vec![0, 0, 0], 3),
+            [tags(&[]), tags(&[]), tags(&[])]
+        );
+    }
+
+    #[test]
+    fn dense_tags_of_alternating_nodes() {
+        let keys_vals = vec![1, 2, 0, 0, 3, 4, 0, 0];
+        assert_eq!(OsmParser::compute_offsets(&keys_vals, 4), [0, 3, 4, 7, 8]);
+        assert_eq!(
+            dense_tags(keys_vals, 4),
+            [
+                tags(&[("k", "v")]),
+                tags(&[]),
+                tags(&[("x", "y")]),
+                tags(&[])
+            ]
+        );
+    }
+
+    #[test]
+    fn dense_tags_without_final_zero() {
+        // the last node is tagged, but its zero is missing
+        let keys_vals = vec![1, 2, 0, 3, 4];
+        assert_eq!(OsmParser::compute_offsets(&keys_vals, 2), [0, 3, 5]);
+        assert_eq!(
+            dense_tags(keys_vals, 2),
+            [tags(&[("k", "v")]), tags(&[("x", "y")])]
+        );
+
+        // untagged nodes at the end without their zeros
+        let keys_vals = vec![1, 2, 0];
+        assert_eq!(OsmParser::compute_offsets(&keys_vals, 3), [0, 3, 3, 3]);
+        assert_eq!(
+            dense_tags(keys_vals, 3),
+            [tags(&[("k", "v")]), tags(&[]), tags(&[])]
+        );
+
+        // a key without value at the end is dropped
+        let keys_vals = vec![1, 2, 0, 3];
+        assert_eq!(OsmParser::compute_offsets(&keys_vals, 2), [0, 3, 4]);
+        assert_eq!(dense_tags(keys_vals, 2), [tags(&[("k", "v")]), tags(&[])]);
+    }
+}
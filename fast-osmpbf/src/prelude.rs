@@ -1,2 +1,14 @@
+#[cfg(feature = "std")]
 pub use crossbeam_channel::*;
+#[cfg(feature = "std")]
 pub use rayon::prelude::*;
+
+// The types a typical consumer needs: elements, owned elements and filters
+pub use crate::{
+    BBox, DenseNodeBlock, DenseNodeRef, Element, ElementBlock, ElementFilter, ElementInfo,
+    ElementKind, ElementMeta, ElementRef, FilterMode, IdPolicy, MemberType, NodeBlock, NodeRef,
+    NodeView, OsmError, OsmHeader, OwnedNode, OwnedRelation, OwnedRelationMember, OwnedWay,
+    RelationBlock, RelationMember, RelationRef, TagIter, WayBlock, WayRef,
+};
+#[cfg(feature = "std")]
+pub use crate::{BlobIndex, ElementBlockIter, OsmReader};
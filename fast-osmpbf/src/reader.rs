@@ -1,34 +1,513 @@
 use quick_protobuf::{BytesReader, MessageRead};
-use rayon::iter::{ParallelBridge, ParallelIterator};
+use rayon::iter::{IntoParallelIterator, ParallelBridge, ParallelIterator};
 
+#[cfg(feature = "stats")]
+use crate::stats::StatsCollector;
 use crate::{
-    parser::OsmParser, BlobHeader, ElementBlock, ElementBlockIter, ElementFilter, ELEMENT_FILTER,
-    TAG_KEYS_FILTER, TAG_KEYS_FILTER_COUNT,
+    element::ElementPredicate,
+    encode::encode_message,
+    parser::{OsmParser, ParseOptions},
+    BBox, Blob, BlobDecompressor, BlobHeader, BlobStats, CoalescedBlocks, DefaultDecompressor,
+    Element, ElementBlock, ElementBlockIter, ElementFilter, ElementRef, FilterMode, HeaderBBox,
+    IdPolicy, Interner, MemberType, OsmError, OsmHeader, OwnedWay, ProducerQuirk,
 };
+#[cfg(feature = "stats")]
+use std::time::Instant;
 use std::{
+    borrow::Cow,
+    collections::{BinaryHeap, HashMap, HashSet},
     fs::File,
-    io::{BufReader, Read},
-    path::Path,
-    sync::Arc,
+    io::{BufReader, ErrorKind, Read, Seek, SeekFrom},
+    ops::{Range, RangeInclusive},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::Sender,
+        Arc, Condvar, Mutex, OnceLock,
+    },
 };
 
 const BUF_SIZE: usize = 1024 * 1024; // 1MB
-const MAX_BLOB_SIZE: usize = 1 * 1024 * 1024; // 32MB
-const MAX_HEADER_SIZE: usize = 64 * 1024; // 64KB
+const MAX_BLOB_SIZE: usize = 1024 * 1024; // 1MB
+pub(crate) const MAX_HEADER_SIZE: usize = 64 * 1024; // 64KB, the limit of the format
 const MAX_Q_ELEMENTS: usize = 1_000;
 const MAX_TAGS: usize = 8;
 
 /// Reader that reads bytes from .osm.pbf file and passes them on to the parser
-#[derive(Debug)]
 pub struct OsmReader {
-    reader: BufReader<File>,
+    path: PathBuf,
+    reader: BufReader<Box<dyn Source>>,
     header: Vec<u8>,
     blob: Vec<u8>,
+    // decompressed blob of lookups, reused so repeated lookups do not allocate it again
+    pub(crate) decompressed: Vec<u8>,
+    osm_header: Option<OsmHeader>,
+    // byte offset of the next blob in the file
+    offset: u64,
+    // shares the offset with progress handles, as the reader moves into the reader thread
+    progress: ReadProgress,
+    pub(crate) config: ReaderConfig,
+    // shared with the reader thread of blocks, so the counts stay visible after the reader moved
+    pub(crate) skipped_blobs: SkippedBlobs,
+}
+
+// Options, callbacks and filters of a reader, shared by the readers of reopen
+#[derive(Clone)]
+pub(crate) struct ReaderConfig {
+    // only blobs starting inside of it are read, see from_path_range
+    range: Option<Range<u64>>,
+    max_in_flight_blocks: usize,
+    read_ahead: Option<usize>,
+    worker_pool: WorkerPool,
+    max_in_flight_bytes: Option<usize>,
+    pub(crate) decompressor: Arc<dyn BlobDecompressor>,
+    blob_stats: Option<Arc<BlobStatsCallback>>,
+    blob_errors: Option<Sender<BlobError>>,
+    predicate: Option<Arc<ElementPredicate>>,
+    interner: Option<Arc<Interner>>,
+    skip_tags: bool,
+    metadata_only: bool,
+    skip_degenerate_ways: bool,
+    raw_string_table: bool,
+    id_policy: IdPolicy,
+    producer_workarounds: bool,
+    truncation_as_eof: bool,
+    element_filter: OnceLock<ElementFilter>,
+    tag_filter: OnceLock<Arc<[Box<str>]>>,
+    tag_filter_mode: OnceLock<FilterMode>,
+    bbox_filter: OnceLock<BBox>,
+    region_early_exit: bool,
+    // id range per element type, indexed by MemberType
+    id_filter: [OnceLock<RangeInclusive<i64>>; 3],
+}
+
+type BlobStatsCallback = dyn Fn(&BlobStats) + Send + Sync;
+
+// The offset of a blob, its bytes and its bbox hint, see OsmReader::next_blob_with_hint
+type HintedBlob = (u64, Arc<[u8]>, Option<BBox>);
+
+/// Handle to poll how far an [`OsmReader`] got from another thread (e.g. to render a progress bar),
+/// see [`OsmReader::progress_handle`]. It stays valid after the reader moved into [`OsmReader::blocks`].
+#[derive(Debug, Clone)]
+pub struct ReadProgress {
+    bytes_read: Arc<AtomicU64>,
+    file_size: Option<u64>,
+}
+
+impl ReadProgress {
+    fn new(file_size: Option<u64>) -> Self {
+        Self {
+            bytes_read: Arc::default(),
+            file_size,
+        }
+    }
+
+    /// Get the number of bytes the reader consumed so far, blobs are counted once they are read,
+    /// before they are decoded
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read.load(Ordering::Relaxed)
+    }
+
+    /// Get the fraction of the file consumed so far, from 0.0 to 1.0,
+    /// [`None`] if the length of the source is unknown
+    pub fn progress(&self) -> Option<f32> {
+        let file_size = self.file_size?;
+        if file_size == 0 {
+            return Some(1.0);
+        }
+        Some((self.bytes_read() as f64 / file_size as f64).min(1.0) as f32)
+    }
+}
+
+/// A blob the workers of [`OsmReader::blocks`] skipped, see [`OsmReader::with_blob_errors`]
+#[derive(Debug)]
+pub struct BlobError {
+    /// Sequence number of the blob, counting the data blobs of the pass from 0 like
+    /// [`OsmReader::elements_with_blob_index`] (the OSMHeader and unknown blob types are not counted)
+    pub index: u64,
+    /// Byte offset of the blob in the file
+    pub offset: u64,
+    /// Why the blob was skipped
+    pub error: std::io::Error,
+}
+
+// Number of skipped blobs that are not OSMData, per blob type
+pub(crate) type SkippedBlobs = Arc<Mutex<HashMap<String, u64>>>;
+
+impl std::fmt::Debug for OsmReader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OsmReader")
+            .field("path", &self.path)
+            .field("osm_header", &self.osm_header)
+            .field("offset", &self.offset)
+            .field("range", &self.config.range)
+            .field("max_in_flight_blocks", &self.config.max_in_flight_blocks)
+            .field("read_ahead", &self.config.read_ahead)
+            .field("worker_pool", &self.config.worker_pool)
+            .field("max_in_flight_bytes", &self.config.max_in_flight_bytes)
+            .field("decompressor", &self.config.decompressor)
+            .field("blob_stats", &self.config.blob_stats.is_some())
+            .field("blob_errors", &self.config.blob_errors.is_some())
+            .field("predicate", &self.config.predicate.is_some())
+            .field("interner", &self.config.interner.is_some())
+            .field("skip_tags", &self.config.skip_tags)
+            .field("metadata_only", &self.config.metadata_only)
+            .field("skip_degenerate_ways", &self.config.skip_degenerate_ways)
+            .field("raw_string_table", &self.config.raw_string_table)
+            .field("id_policy", &self.config.id_policy)
+            .field("producer_workarounds", &self.config.producer_workarounds)
+            .field("truncation_as_eof", &self.config.truncation_as_eof)
+            .field("element_filter", &self.config.element_filter.get())
+            .field("tag_filter", &self.config.tag_filter.get())
+            .field("tag_filter_mode", &self.config.tag_filter_mode.get())
+            .field("bbox_filter", &self.config.bbox_filter.get())
+            .field("region_early_exit", &self.config.region_early_exit)
+            .field(
+                "id_filter",
+                &self.config.id_filter.each_ref().map(OnceLock::get),
+            )
+            .finish_non_exhaustive()
+    }
+}
+
+// What a reader reads the blobs from, a file outside of tests
+trait Source: Read + Seek + Send + Sync {
+    // Length of the whole source in bytes
+    fn size(&self) -> std::io::Result<u64>;
+}
+
+impl Source for File {
+    fn size(&self) -> std::io::Result<u64> {
+        Ok(self.metadata()?.len())
+    }
+}
+
+// Fills buf, returns how many bytes were read before reaching EOF
+fn read_until_eof(reader: &mut impl Read, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut read = 0;
+    while read < buf.len() {
+        match reader.read(&mut buf[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(err) if err.kind() == ErrorKind::Interrupted => (),
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(read)
+}
+
+// Copies borrowed bytes of a blob out of the read buffers
+fn owned_bytes(bytes: Option<Cow<'_, [u8]>>) -> Option<Cow<'static, [u8]>> {
+    bytes.map(|bytes| Cow::Owned(bytes.into_owned()))
+}
+
+// Collects the user names of a block, user sids are resolved once per block
+fn block_users(block: &ElementBlock) -> HashSet<String> {
+    let mut sids = HashSet::new();
+    let table = match block {
+        ElementBlock::DenseNodeBlock(block) => {
+            sids.extend(
+                block
+                    .iter()
+                    .filter_map(|node| node.info())
+                    .map(|i| i.user_sid()),
+            );
+            &block.table
+        }
+        ElementBlock::NodeBlock(block) => {
+            sids.extend(
+                block
+                    .iter()
+                    .filter_map(|node| node.info())
+                    .map(|i| i.user_sid()),
+            );
+            &block.table
+        }
+        ElementBlock::WayBlock(block) => {
+            sids.extend(
+                block
+                    .iter()
+                    .filter_map(|way| way.info())
+                    .map(|i| i.user_sid()),
+            );
+            &block.table
+        }
+        ElementBlock::RelationBlock(block) => {
+            sids.extend(
+                block
+                    .iter()
+                    .filter_map(|rel| rel.info())
+                    .map(|i| i.user_sid()),
+            );
+            &block.table
+        }
+    };
+
+    // the empty string at index 0 is used for elements without a user name
+    sids.into_iter()
+        .filter_map(|sid| table.get(sid as usize))
+        .filter(|name| !name.is_empty())
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .collect()
+}
+
+// Counts the stored tag pairs of a block from the key columns, without resolving any string
+fn block_tag_count(block: &ElementBlock) -> u64 {
+    let count: usize = match block {
+        // every node has 2 entries per tag and a terminating 0
+        ElementBlock::DenseNodeBlock(block) => block
+            .kv_offsets
+            .windows(2)
+            .map(|range| (range[1] - range[0]) / 2)
+            .sum(),
+        ElementBlock::NodeBlock(block) => block.nodes.iter().map(|node| node.keys.len()).sum(),
+        ElementBlock::WayBlock(block) => block.ways.iter().map(|way| way.keys.len()).sum(),
+        ElementBlock::RelationBlock(block) => block
+            .relations
+            .iter()
+            .map(|relation| relation.keys.len())
+            .sum(),
+    };
+    count as u64
+}
+
+// Counts the values of the tag key in a block, the key is resolved to its id in the string table once
+fn count_tag_values(block: &ElementBlock, key: &str) -> HashMap<String, u64> {
+    let (table, cached_tag_ids) = match block {
+        ElementBlock::DenseNodeBlock(block) => (&block.table, &block.cached_tag_ids),
+        ElementBlock::NodeBlock(block) => (&block.table, &block.cached_tag_ids),
+        ElementBlock::WayBlock(block) => (&block.table, &block.cached_tag_ids),
+        ElementBlock::RelationBlock(block) => (&block.table, &block.cached_tag_ids),
+    };
+    let Some(key_id) = table
+        .iter()
+        .position(|s| s.as_ref() == key.as_bytes())
+        .map(|id| id as u32)
+        .filter(|id| cached_tag_ids.keeps(*id))
+    else {
+        return HashMap::new();
+    };
+
+    let mut counts: HashMap<u32, u64> = HashMap::new();
+    let mut tally = |value: Option<u32>| {
+        if let Some(value) = value {
+            *counts.entry(value).or_default() += 1;
+        }
+    };
+    let value_of = |keys: &[u32], vals: &[u32]| {
+        let pos = keys.iter().position(|k| *k == key_id)?;
+        vals.get(pos).copied()
+    };
+    match block {
+        ElementBlock::DenseNodeBlock(block) => {
+            for node in block.iter() {
+                let value = block.nodes.keys_vals[block.kv_range(node.index)]
+                    .chunks_exact(2)
+                    .find(|kv| kv[0] as u32 == key_id)
+                    .map(|kv| kv[1] as u32);
+                tally(value);
+            }
+        }
+        ElementBlock::NodeBlock(block) => {
+            for node in block.iter() {
+                tally(value_of(&node.node.keys, &node.node.vals));
+            }
+        }
+        ElementBlock::WayBlock(block) => {
+            for way in block.ways.iter() {
+                tally(value_of(&way.keys, &way.vals));
+            }
+        }
+        ElementBlock::RelationBlock(block) => {
+            for relation in block.relations.iter() {
+                tally(value_of(&relation.keys, &relation.vals));
+            }
+        }
+    }
+
+    counts
+        .into_iter()
+        .filter_map(|(value, count)| {
+            let value = table.get(value as usize)?;
+            Some((String::from_utf8_lossy(value).into_owned(), count))
+        })
+        .collect()
+}
+
+// Converts all elements of a block into owned elements
+fn owned_elements(block: &ElementBlock) -> Vec<Element> {
+    match block {
+        ElementBlock::DenseNodeBlock(block) => {
+            block.iter().map(|node| node.to_owned().into()).collect()
+        }
+        ElementBlock::NodeBlock(block) => block.iter().map(|node| node.to_owned().into()).collect(),
+        ElementBlock::WayBlock(block) => block.iter().map(|way| way.to_owned().into()).collect(),
+        ElementBlock::RelationBlock(block) => block
+            .iter()
+            .map(|relation| relation.to_owned().into())
+            .collect(),
+    }
+}
+
+// The n elements with the lowest priority seen so far, the highest of them on top of the heap
+struct Reservoir {
+    n: usize,
+    heap: BinaryHeap<Sampled>,
+}
+
+// An element of the reservoir, ordered by its priority only
+struct Sampled {
+    // random part first, type and id make the order total
+    priority: (u64, i32, i64),
+    element: Element,
+}
+
+impl PartialEq for Sampled {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority
+    }
+}
+
+impl Eq for Sampled {}
+
+impl PartialOrd for Sampled {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Sampled {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.priority.cmp(&other.priority)
+    }
+}
+
+impl Reservoir {
+    fn new(n: usize) -> Self {
+        Self {
+            n,
+            heap: BinaryHeap::with_capacity(n + 1),
+        }
+    }
+
+    // The element is only converted to an owned one if it makes it into the reservoir
+    fn offer(&mut self, element: ElementRef<'_>, seed: u64) {
+        let element_type = element.element_type() as i32;
+        let id = element.id();
+        let random = mix(mix(seed ^ id as u64) ^ element_type as u64);
+        let priority = (random, element_type, id);
+        if self.heap.len() == self.n && self.heap.peek().is_some_and(|top| top.priority <= priority)
+        {
+            return;
+        }
+        self.push(Sampled {
+            priority,
+            element: element.to_owned(),
+        });
+    }
+
+    fn push(&mut self, sampled: Sampled) {
+        self.heap.push(sampled);
+        if self.heap.len() > self.n {
+            self.heap.pop();
+        }
+    }
+
+    fn offer_block(&mut self, block: &ElementBlock, seed: u64) {
+        match block {
+            ElementBlock::DenseNodeBlock(block) => {
+                for node in block.iter() {
+                    self.offer(ElementRef::DenseNode(node), seed);
+                }
+            }
+            ElementBlock::NodeBlock(block) => {
+                for node in block.iter() {
+                    self.offer(ElementRef::Node(node), seed);
+                }
+            }
+            ElementBlock::WayBlock(block) => {
+                for way in block.iter() {
+                    self.offer(ElementRef::Way(way), seed);
+                }
+            }
+            ElementBlock::RelationBlock(block) => {
+                for relation in block.iter() {
+                    self.offer(ElementRef::Relation(relation), seed);
+                }
+            }
+        }
+    }
+
+    fn merge(mut self, mut other: Self) -> Self {
+        if self.heap.len() < other.heap.len() {
+            std::mem::swap(&mut self, &mut other);
+        }
+        for sampled in other.heap {
+            self.push(sampled);
+        }
+        self
+    }
+
+    fn into_sorted_vec(self) -> Vec<Element> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|sampled| sampled.element)
+            .collect()
+    }
+}
+
+// Finalizer of splitmix64, spreads the bits of x evenly over the result
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+// Type of a blob according to its BlobHeader
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum BlobType {
+    Header,
+    Data,
+    Unknown(Box<str>),
+}
+
+impl BlobType {
+    pub(crate) fn of(header: &BlobHeader<'_>) -> Self {
+        match header.type_pb.as_ref() {
+            "OSMData" => BlobType::Data,
+            "OSMHeader" => BlobType::Header,
+            name => BlobType::Unknown(name.into()),
+        }
+    }
 }
 
 impl OsmReader {
     /// Creates a new OsmReader from a file path
     pub fn from_path<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        Self::from_path_with_decompressor(path, DefaultDecompressor)
+    }
+
+    /// Creates a new OsmReader for one shard of the file that only reads the blobs starting inside of
+    /// the byte range `start..end`, e.g. to process a file on many machines without coordination.
+    /// Shards with adjacent ranges (`0..n`, `n..2 * n`, ...) read every blob exactly once, a blob
+    /// is never split but belongs to the shard its first byte lies in.
+    ///
+    /// The first blob at or after `start` is found by scanning the framing from the beginning of the
+    /// file, which only reads the BlobHeaders and seeks over the blobs. The OSMHeader is read as usual.
+    pub fn from_path_range<P: AsRef<Path>>(path: P, start: u64, end: u64) -> std::io::Result<Self> {
+        let mut reader = Self::from_path(path)?;
+        reader.config.range = Some(start..end);
+        reader.skip_to(start)?;
+        Ok(reader)
+    }
+
+    /// Creates a new OsmReader from a file path that decompresses blobs with your own [`BlobDecompressor`],
+    /// e.g. to support zstd or to use a faster zlib implementation
+    pub fn from_path_with_decompressor<P: AsRef<Path>>(
+        path: P,
+        decompressor: impl BlobDecompressor + 'static,
+    ) -> std::io::Result<Self> {
         let path_ref = path.as_ref();
         let path = path.as_ref().to_string_lossy().to_string();
         if !path.ends_with(".osm.pbf") {
@@ -37,36 +516,321 @@ impl OsmReader {
                 format!("File {} is not a .osm.pbf file", path),
             ));
         }
-        let file = File::open(path_ref)?;
-        let reader = BufReader::with_capacity(BUF_SIZE, file);
+        let config = ReaderConfig {
+            range: None,
+            max_in_flight_blocks: MAX_Q_ELEMENTS,
+            read_ahead: None,
+            worker_pool: WorkerPool::Own,
+            max_in_flight_bytes: None,
+            decompressor: Arc::new(decompressor),
+            blob_stats: None,
+            blob_errors: None,
+            predicate: None,
+            interner: None,
+            skip_tags: false,
+            metadata_only: false,
+            skip_degenerate_ways: false,
+            raw_string_table: false,
+            id_policy: IdPolicy::Lenient,
+            producer_workarounds: false,
+            truncation_as_eof: false,
+            element_filter: OnceLock::new(),
+            tag_filter: OnceLock::new(),
+            tag_filter_mode: OnceLock::new(),
+            bbox_filter: OnceLock::new(),
+            region_early_exit: false,
+            id_filter: Default::default(),
+        };
+        Self::open(path_ref, config)
+    }
+
+    /// Opens the file again and returns a fresh reader positioned at the first blob (of its range),
+    /// with the same decompressor, limits, callbacks and filters as this one.
+    /// Since [`OsmReader::blocks`] consumes the reader, call this beforehand for every further pass
+    /// of a multi-pass algorithm (e.g. collecting node locations first and resolving ways after).
+    pub fn reopen(&self) -> std::io::Result<Self> {
+        let mut reader = Self::open(&self.path, self.config.clone())?;
+        if let Some(range) = &self.config.range {
+            reader.skip_to(range.start)?;
+        }
+        Ok(reader)
+    }
 
-        Ok(Self {
-            reader,
+    // Opens the file and reads its OSMHeader, the state of a pass starts out empty
+    fn open(path: &Path, config: ReaderConfig) -> std::io::Result<Self> {
+        let file = File::open(path)?;
+        let file_size = file.metadata()?.len();
+        let mut reader = Self {
+            path: path.to_path_buf(),
+            reader: BufReader::with_capacity(BUF_SIZE, Box::new(file)),
             header: Vec::with_capacity(MAX_HEADER_SIZE),
             blob: Vec::with_capacity(MAX_BLOB_SIZE),
-        })
+            decompressed: Vec::new(),
+            osm_header: None,
+            offset: 0,
+            progress: ReadProgress::new(Some(file_size)),
+            config,
+            skipped_blobs: SkippedBlobs::default(),
+        };
+        reader.osm_header = reader.read_osm_header()?;
+        Ok(reader)
+    }
+
+    /// Get the OSMHeader of the file, if the file starts with one
+    pub fn header(&self) -> Option<&OsmHeader> {
+        self.osm_header.as_ref()
+    }
+
+    /// Get the number of blobs skipped so far because they hold no OSMData, per blob type
+    /// (e.g. `"OSMHeader"`), not counting the OSMHeader the file starts with.
+    /// More than one header usually means concatenated files. See
+    /// [`ElementBlockIter::skipped_blob_types`] for the counts of [`OsmReader::blocks`].
+    pub fn skipped_blob_types(&self) -> HashMap<String, u64> {
+        self.skipped_blobs.lock().unwrap().clone()
+    }
+
+    /// Get the fraction of the file the reader consumed so far, from 0.0 to 1.0,
+    /// [`None`] if the length of the source is unknown. Use [`OsmReader::progress_handle`]
+    /// to poll it while iterating.
+    pub fn progress(&self) -> Option<f32> {
+        self.progress.progress()
+    }
+
+    /// Get a handle to poll the progress of this reader from another thread,
+    /// e.g. taken before calling [`OsmReader::blocks`]
+    pub fn progress_handle(&self) -> ReadProgress {
+        self.progress.clone()
+    }
+
+    /// Limits the number of decoded [`ElementBlock`] waiting to be consumed (default 1000).
+    /// This only counts blocks, see [`OsmReader::with_max_in_flight_bytes`] for a bound on memory.
+    pub fn with_max_in_flight_blocks(mut self, blocks: usize) -> Self {
+        self.config.max_in_flight_blocks = blocks.max(1);
+        self
+    }
+
+    /// Sets how many compressed blobs the reader thread reads ahead of the worker threads
+    /// (defaults to the number of worker threads). A deeper read-ahead keeps workers busy when reads stall,
+    /// e.g. on network filesystems, at the cost of one compressed blob (usually below 1MB) of memory per slot.
+    /// On local SSDs the default is usually enough, since reading is far faster than decompressing.
+    pub fn with_read_ahead(mut self, blobs: usize) -> Self {
+        self.config.read_ahead = Some(blobs.max(1));
+        self
+    }
+
+    /// Parses blobs on `pool` instead of a pool of its own, which [`OsmReader::blocks`] builds by default
+    /// with as many threads as the global rayon pool. Sharing the pool of the application keeps parsing
+    /// within its thread budget instead of oversubscribing the cores. The pool is an [`Arc`], since the
+    /// workers outlive the call to [`OsmReader::blocks`], and its number of threads sets the default read-ahead.
+    /// While blocks are parsed, other work on `pool` competes with the parsing for its threads.
+    pub fn with_thread_pool(mut self, pool: Arc<rayon::ThreadPool>) -> Self {
+        self.config.worker_pool = WorkerPool::Shared(pool);
+        self
+    }
+
+    /// Parses blobs on the global rayon pool instead of a pool of its own, see [`OsmReader::with_thread_pool`]
+    pub fn with_global_thread_pool(mut self) -> Self {
+        self.config.worker_pool = WorkerPool::Global;
+        self
+    }
+
+    /// Limits the decompressed bytes of [`ElementBlock`] waiting to be consumed (unbounded by default).
+    /// Once the limit is reached, decoding pauses until you consume blocks, which in turn pauses reading the file.
+    /// On top of the limit, every worker thread may hold one more decompressed blob (usually below 1MB each).
+    ///
+    /// Block sizes vary a lot, so this bounds memory far more reliably than [`OsmReader::with_max_in_flight_blocks`].
+    /// A low limit keeps RSS down but idles worker threads whenever the consumer falls behind,
+    /// as a rule of thumb keep it at a few MB per thread to not lose throughput.
+    pub fn with_max_in_flight_bytes(mut self, bytes: usize) -> Self {
+        self.config.max_in_flight_bytes = Some(bytes);
+        self
+    }
+
+    /// Drops all tags right after decoding a blob, so tag iterators of all elements are empty.
+    /// Skips copying the string table and the tag filter setup, which speeds up passes that only need
+    /// ids, coordinates and references (e.g. building a coordinate index). Roles of relation members are kept.
+    pub fn with_skip_tags(mut self) -> Self {
+        self.config.skip_tags = true;
+        self
+    }
+
+    /// Drops tags, node references of ways and members of relations right after decoding a blob,
+    /// only ids and metadata are left to read with [`ElementBlock::metadata`].
+    /// Unlike [`OsmReader::with_skip_tags`] the string table is kept for the user names.
+    /// Use it for audit workloads, e.g. listing all changesets that touched a file.
+    pub fn with_metadata_only(mut self) -> Self {
+        self.config.metadata_only = true;
+        self
+    }
+
+    /// Drops ways with less than 2 node references right after decoding a blob, as they have no
+    /// valid line geometry (see [`WayRef::is_valid_geometry`](crate::WayRef::is_valid_geometry)),
+    /// e.g. for routing or rendering. Blocks left without ways are never sent.
+    pub fn with_skip_degenerate_ways(mut self) -> Self {
+        self.config.skip_degenerate_ways = true;
+        self
+    }
+
+    /// Also keeps the string table of every blob flattened into concatenated bytes and offsets,
+    /// to read with [`ElementBlock::string_table_raw`] without copies or UTF-8 validation.
+    /// Costs one more copy of the table per blob, shared by the blocks of the blob.
+    /// Together with [`OsmReader::with_skip_tags`] the table is only kept for blobs with relations.
+    pub fn with_raw_string_table(mut self) -> Self {
+        self.config.raw_string_table = true;
+        self
+    }
+
+    /// Checks the node ids of ways and the member ids of relations against the policy (lenient by default).
+    /// [`OsmReader::blocks`] and [`OsmReader::par_blocks`] skip blobs containing a rejected id,
+    /// [`OsmReader::build_blob_index`] and lookups by id return it as an [`OsmError::Corrupt`](crate::OsmError::Corrupt).
+    /// Use [`IdPolicy::Strict`] for untrusted input, e.g. before feeding a router.
+    pub fn with_id_policy(mut self, policy: IdPolicy) -> Self {
+        self.config.id_policy = policy;
+        self
+    }
+
+    /// Builds the string tables of all blocks out of the strings shared by `interner`, so identical
+    /// strings of different blocks are allocated once, see [`Interner`]. Worth it if many blocks
+    /// are kept in memory, the interner is locked once per block.
+    pub fn with_interner(mut self, interner: Arc<Interner>) -> Self {
+        self.config.interner = Some(interner);
+        self
+    }
+
+    /// Adapts the options to the known quirks of the program that wrote the file
+    /// (see [`OsmHeader::producer_quirks`]), regardless of the order of the other options:
+    /// for [`ProducerQuirk::NegativeIds`] an [`IdPolicy::Strict`] is relaxed to [`IdPolicy::AllowNegative`].
+    /// Check [`OsmHeader::producer_quirks`] to warn about a file, this only changes how it is read.
+    pub fn with_producer_workarounds(mut self) -> Self {
+        self.config.producer_workarounds = true;
+        self
+    }
+
+    /// Treats a file that ends inside of a blob like a regular end of file, so all complete blobs are read
+    /// and the incomplete one is dropped (e.g. for files that are still being downloaded or written).
+    /// Otherwise reading the incomplete blob fails with an [`OsmError::Truncated`] (as [`ErrorKind::UnexpectedEof`]),
+    /// which is distinct from the [`ErrorKind::InvalidData`] of corrupt blobs.
+    pub fn with_truncation_as_eof(mut self) -> Self {
+        self.config.truncation_as_eof = true;
+        self
+    }
+
+    /// Stops reading at the first blob of nodes outside the bbox filter that follows a blob inside of it,
+    /// which turns a bbox extraction into an early-exit scan. Only takes effect if the file is sorted by
+    /// `Sort.Geographic` (see [`OsmHeader::is_geographically_sorted`]) and the element filter only lets
+    /// nodes through, otherwise the whole file is scanned.
+    ///
+    /// Opt-in because a geographic order (e.g. by tiles or along a Hilbert curve) does not keep an
+    /// arbitrary bbox contiguous: nodes inside the box that come after the first blob outside of it
+    /// are silently dropped. Only use it if the region is known to be stored contiguously, e.g. for
+    /// the bbox of a single tile. [`ElementBlockIter::stopped_after_region`] tells whether the scan
+    /// stopped before the end of the file.
+    pub fn with_region_early_exit(mut self) -> Self {
+        self.config.region_early_exit = true;
+        self
+    }
+
+    /// Calls `callback` with the [`BlobStats`] (sizes and codec) of every blob decompressed by
+    /// [`OsmReader::blocks`] or [`OsmReader::par_blocks`], e.g. to check whether recompressing a file would help.
+    /// The callback runs on the worker threads, in the order blobs finish decompressing.
+    pub fn with_blob_stats(
+        mut self,
+        callback: impl Fn(&BlobStats) + Send + Sync + 'static,
+    ) -> Self {
+        self.config.blob_stats = Some(Arc::new(callback));
+        self
+    }
+
+    /// Sends a [`BlobError`] for every blob [`OsmReader::blocks`] (or [`OsmReader::map_blocks`],
+    /// [`OsmReader::par_blocks`]) skips because it cannot be decompressed, parsed or fails the
+    /// [`IdPolicy`], so you learn which blobs are missing instead of only the first error of
+    /// [`ElementBlockIter::finish`]. Collect them after the pass, e.g. with `receiver.try_iter()`.
+    /// A read error ends the pass instead of skipping a blob, it is only returned by `finish`.
+    pub fn with_blob_errors(mut self, sender: Sender<BlobError>) -> Self {
+        self.config.blob_errors = Some(sender);
+        self
+    }
+
+    /// Keeps only the elements `predicate` returns true for, to filter by conditions the element,
+    /// tag and bbox filters cannot express. It runs on the worker threads of [`OsmReader::blocks`]
+    /// right after a blob is parsed and only sees elements passing the other filters.
+    /// Blocks without a match are never sent, the iterators and [`ElementBlock::metadata`] of the
+    /// other blocks skip the elements that do not match (`len()` and the bulk accessors do not).
+    pub fn with_predicate(
+        mut self,
+        predicate: impl Fn(&ElementRef<'_>) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.config.predicate = Some(Arc::new(predicate));
+        self
+    }
+
+    /// Same as [`OsmReader::apply_element_filter`], but consumes and returns the reader,
+    /// so filters can be chained onto [`OsmReader::from_path`] like the other options
+    #[must_use = "the reader is moved into the result"]
+    pub fn with_element_filter(self, filter: ElementFilter) -> Result<Self, &'static str> {
+        self.apply_element_filter(filter)?;
+        Ok(self)
+    }
+
+    /// Same as [`OsmReader::apply_tag_filter`], but consumes and returns the reader, see [`OsmReader::with_element_filter`]
+    #[must_use = "the reader is moved into the result"]
+    pub fn with_tag_filter<S: AsRef<str>>(self, tags: &[S]) -> Result<Self, &'static str> {
+        self.apply_tag_filter(tags)?;
+        Ok(self)
+    }
+
+    /// Same as [`OsmReader::apply_tag_filter_with_mode`], but consumes and returns the reader,
+    /// see [`OsmReader::with_element_filter`]
+    #[must_use = "the reader is moved into the result"]
+    pub fn with_tag_filter_mode<S: AsRef<str>>(
+        self,
+        tags: &[S],
+        mode: FilterMode,
+    ) -> Result<Self, &'static str> {
+        self.apply_tag_filter_with_mode(tags, mode)?;
+        Ok(self)
+    }
+
+    /// Same as [`OsmReader::apply_bbox_filter`], but consumes and returns the reader, see [`OsmReader::with_element_filter`]
+    #[must_use = "the reader is moved into the result"]
+    pub fn with_bbox_filter(self, bbox: BBox) -> Result<Self, &'static str> {
+        self.apply_bbox_filter(bbox)?;
+        Ok(self)
+    }
+
+    /// Same as [`OsmReader::apply_id_filter`], but consumes and returns the reader, see [`OsmReader::with_element_filter`]
+    #[must_use = "the reader is moved into the result"]
+    pub fn with_id_filter(
+        self,
+        element_type: MemberType,
+        ids: RangeInclusive<i64>,
+    ) -> Result<Self, &'static str> {
+        self.apply_id_filter(element_type, ids)?;
+        Ok(self)
     }
 
     /// Filters elements (dense_nodes, nodes, ways or relations) depending on the filter provided.
+    /// Like all filters it only applies to this reader (and the readers created by [`OsmReader::reopen`]).
     /// If you only are interested in specific elements, I highly encourage you to use this mechanism
     /// over filtering yourself in the iterator since it not only does the filtering for you,
     /// but actually speeds up computation.
     pub fn apply_element_filter(&self, filter: ElementFilter) -> Result<(), &'static str> {
-        if ELEMENT_FILTER.get().is_some() {
-            return Err("You cannot apply a filter more than once");
-        }
-
-        let _ = ELEMENT_FILTER.set(filter);
-        Ok(())
+        self.config
+            .element_filter
+            .set(filter)
+            .map_err(|_| "You cannot apply a filter more than once")
     }
 
     /// Filters out all tags (key, value) where key is not one of your provided Strings.
     /// If you only are interested in specific tags, I highly encourage you to use this mechanism
     /// over filtering yourself in the iterator since it not only does the filtering for you,
     /// but actually speeds up computation by abusing a caching mechanism.
-    /// You can provide between 0 and 8 filter keys.
-    pub fn apply_tag_filter(&self, tags: &[&str]) -> Result<(), &'static str> {
-        if TAG_KEYS_FILTER.get().is_some() {
+    /// You can provide between 0 and 8 filter keys, borrowed (`&str`) or owned (`String`).
+    /// The keys are copied into the reader and freed together with it.
+    /// Elements are kept even if none of their tags is left, to also drop them use
+    /// [`OsmReader::apply_tag_filter_with_mode`].
+    pub fn apply_tag_filter<S: AsRef<str>>(&self, tags: &[S]) -> Result<(), &'static str> {
+        if self.config.tag_filter.get().is_some() {
             return Err("You cannot apply a filter more than once");
         }
 
@@ -76,106 +840,906 @@ impl OsmReader {
             return Err("Not allowed to provide more than 8 tags");
         }
 
-        let mut leaked: Vec<&'static str> = tags
-            .iter()
-            .map(|t| {
-                let boxed: Box<str> = t.to_string().into_boxed_str();
-                let s: &'static mut str = Box::leak(boxed);
-                &*s // coerce &'static mut str → &'static str
+        let mut keys: Vec<Box<str>> = tags.iter().map(|t| Box::from(t.as_ref())).collect();
+        keys.sort_unstable();
+
+        self.config
+            .tag_filter
+            .set(Arc::from(keys))
+            .map_err(|_| "You cannot apply a filter more than once")
+    }
+
+    /// Same as [`OsmReader::apply_tag_filter`], but also drops the elements that do not pass `mode`:
+    /// with [`FilterMode::Any`] elements without any of the keys, with [`FilterMode::All`] elements
+    /// missing one of them. Elements are dropped on the worker threads before the predicate of
+    /// [`OsmReader::with_predicate`] runs, with the same effect on `len()` and the bulk accessors.
+    pub fn apply_tag_filter_with_mode<S: AsRef<str>>(
+        &self,
+        tags: &[S],
+        mode: FilterMode,
+    ) -> Result<(), &'static str> {
+        self.apply_tag_filter(tags)?;
+        self.config
+            .tag_filter_mode
+            .set(mode)
+            .map_err(|_| "You cannot apply a filter more than once")
+    }
+
+    /// Filters out all nodes (dense_nodes and nodes) outside of the bounding box.
+    /// Ways and relations are not affected, since their location is only known through their nodes.
+    /// Blocks without a single node inside the box are dropped before reaching you.
+    /// The nodes of every blob are decoded to decide that. Only if the file stores a bounding box hint
+    /// for a blob (see [`ElementBlock::bbox`]) and the hint lies outside of `bbox`, its nodes are dropped
+    /// undecoded, and the blob is not even decompressed if the element filter only lets nodes through.
+    /// The whole file is scanned unless [`OsmReader::with_region_early_exit`] is used.
+    pub fn apply_bbox_filter(&self, bbox: BBox) -> Result<(), &'static str> {
+        if self.config.bbox_filter.get().is_some() {
+            return Err("You cannot apply a filter more than once");
+        }
+        if !(bbox.bottom <= bbox.top && bbox.left <= bbox.right) {
+            return Err("The bbox needs bottom <= top and left <= right");
+        }
+
+        self.config
+            .bbox_filter
+            .set(bbox)
+            .map_err(|_| "You cannot apply a filter more than once")
+    }
+
+    /// Filters out the elements of `element_type` with an id outside of `ids`, elements of the other
+    /// types are kept. Ids are handed out in increasing order, so e.g. `10_000_000_000..=i64::MAX` for nodes
+    /// keeps only recently added ones. Combined with [`OsmReader::apply_element_filter`] this yields e.g. only
+    /// new ways. Every element type can have one range.
+    ///
+    /// Dense node ids are compared after the delta decoding every pass does anyway, the ids of the other
+    /// elements directly. Elements are dropped on the worker threads before the predicate of
+    /// [`OsmReader::with_predicate`] runs, with the same effect on `len()` and the bulk accessors.
+    pub fn apply_id_filter(
+        &self,
+        element_type: MemberType,
+        ids: RangeInclusive<i64>,
+    ) -> Result<(), &'static str> {
+        if ids.is_empty() {
+            return Err("The id range needs min <= max");
+        }
+        self.config.id_filter[element_type as usize]
+            .set(ids)
+            .map_err(|_| "You cannot apply a filter more than once")
+    }
+
+    /// Creates an iterator that yields only the elements that have every key of the tag filter
+    /// (e.g. all elements with a full address), regardless of their type.
+    /// Returns an error if no tag filter was applied with [`OsmReader::apply_tag_filter`].
+    /// To drop the other elements from every pass, apply the filter with [`FilterMode::All`] instead.
+    pub fn elements_matching_all_filter_keys(
+        self,
+    ) -> Result<impl Iterator<Item = Element>, &'static str> {
+        if self.config.tag_filter.get().is_none() {
+            return Err("You need to apply a tag filter first");
+        }
+
+        Ok(self.blocks().flat_map(|block| {
+            let elements: Vec<Element> = match block {
+                ElementBlock::DenseNodeBlock(block) => block
+                    .iter()
+                    .filter(|node| node.tags().has_all_filter_keys())
+                    .map(|node| node.to_owned().into())
+                    .collect(),
+                ElementBlock::NodeBlock(block) => block
+                    .iter()
+                    .filter(|node| node.tags().has_all_filter_keys())
+                    .map(|node| node.to_owned().into())
+                    .collect(),
+                ElementBlock::WayBlock(block) => block
+                    .iter()
+                    .filter(|way| way.tags().has_all_filter_keys())
+                    .map(|way| way.to_owned().into())
+                    .collect(),
+                ElementBlock::RelationBlock(block) => block
+                    .iter()
+                    .filter(|rel| rel.tags().has_all_filter_keys())
+                    .map(|rel| rel.to_owned().into())
+                    .collect(),
+            };
+            elements
+        }))
+    }
+
+    /// Counts the elements that have every key of the tag filter (e.g. all elements with a full address),
+    /// regardless of their type. Blocks are counted in parallel on the workers, nothing is allocated per element.
+    /// Returns an error if no tag filter was applied with [`OsmReader::apply_tag_filter`].
+    pub fn count_matching_all_keys(self) -> Result<usize, &'static str> {
+        if self.config.tag_filter.get().is_none() {
+            return Err("You need to apply a tag filter first");
+        }
+
+        Ok(self
+            .map_blocks(|block| match block {
+                ElementBlock::DenseNodeBlock(block) => block
+                    .iter()
+                    .filter(|node| node.tags().has_all_filter_keys())
+                    .count(),
+                ElementBlock::NodeBlock(block) => block
+                    .iter()
+                    .filter(|node| node.tags().has_all_filter_keys())
+                    .count(),
+                ElementBlock::WayBlock(block) => block
+                    .iter()
+                    .filter(|way| way.tags().has_all_filter_keys())
+                    .count(),
+                ElementBlock::RelationBlock(block) => block
+                    .iter()
+                    .filter(|rel| rel.tags().has_all_filter_keys())
+                    .count(),
             })
-            .collect();
+            .sum())
+    }
 
-        leaked.sort_unstable();
+    /// Collects the ways that reference at least one of `node_ids` (e.g. the nodes found inside of an
+    /// area by a previous pass), together with all node ids these ways reference, which is what a
+    /// following pass needs to backfill the nodes of the ways. Blocks are scanned in parallel,
+    /// filters applied to the reader are respected. Without an element filter, only ways are parsed.
+    pub fn ways_referencing(self, node_ids: &HashSet<i64>) -> (Vec<OwnedWay>, HashSet<i64>) {
+        let _ = self.apply_element_filter(ElementFilter {
+            nodes: false,
+            ways: true,
+            relations: false,
+        });
 
-        let _ = TAG_KEYS_FILTER.set(leaked.into_boxed_slice());
-        let _ = TAG_KEYS_FILTER_COUNT.set(tags.len());
-        Ok(())
+        self.par_blocks()
+            .map(|block| {
+                let (mut ways, mut referenced) = (Vec::new(), HashSet::new());
+                if let ElementBlock::WayBlock(block) = block {
+                    for way in block.iter() {
+                        let refs = way.node_ids_vec();
+                        if refs.iter().any(|id| node_ids.contains(id)) {
+                            referenced.extend(refs);
+                            ways.push(way.to_owned());
+                        }
+                    }
+                }
+                (ways, referenced)
+            })
+            .reduce(
+                || (Vec::new(), HashSet::new()),
+                |(mut ways, mut referenced), (other_ways, mut other_referenced)| {
+                    ways.extend(other_ways);
+                    if referenced.len() < other_referenced.len() {
+                        std::mem::swap(&mut referenced, &mut other_referenced);
+                    }
+                    referenced.extend(other_referenced);
+                    (ways, referenced)
+                },
+            )
+    }
+
+    /// Counts how many elements have each distinct value of the tag `key` (e.g. every `highway=*` value).
+    /// Blocks are counted in parallel and merged, filters applied to the reader are respected,
+    /// so apply an element filter to e.g. only count ways. With a tag filter, `key` needs to be one of its keys.
+    pub fn count_by_tag(self, key: &str) -> HashMap<String, u64> {
+        self.par_blocks()
+            .map(|block| count_tag_values(&block, key))
+            .reduce(HashMap::new, |mut counts, mut other| {
+                if counts.len() < other.len() {
+                    std::mem::swap(&mut counts, &mut other);
+                }
+                for (value, count) in other {
+                    *counts.entry(value).or_default() += count;
+                }
+                counts
+            })
+    }
+
+    /// Counts the tag pairs of all elements (e.g. to estimate the size of a tag table downstream),
+    /// summed per block in parallel from the stored key columns without resolving any string.
+    /// The element filter is respected, the tag filter is not: all pairs stored in the file are counted.
+    pub fn count_tags(self) -> u64 {
+        self.map_blocks(|block| block_tag_count(&block)).sum()
+    }
+
+    /// Collects the distinct user names of all elements (e.g. for attribution), elements without metadata are ignored.
+    /// Blocks are collected in parallel and merged, the element filter is respected.
+    /// The names are part of the string table, so this does not work together with [`OsmReader::with_skip_tags`].
+    pub fn distinct_users(self) -> HashSet<String> {
+        self.par_blocks().map(|block| block_users(&block)).reduce(
+            HashSet::new,
+            |mut users, mut other| {
+                if users.len() < other.len() {
+                    std::mem::swap(&mut users, &mut other);
+                }
+                users.extend(other);
+                users
+            },
+        )
+    }
+
+    /// Draws a uniformly random sample of up to `n` elements as owned elements (e.g. for statistical QA),
+    /// keeping no more than `n` elements per thread in memory. Filters applied to the reader are respected.
+    ///
+    /// Every element gets a pseudo random priority from `seed`, its type and its id, and the `n`
+    /// elements with the lowest priority are kept. Each thread fills a local reservoir and the reservoirs
+    /// are merged at the end, so the same seed always yields the same sample, regardless of scheduling.
+    /// Entries sharing type and id (e.g. versions in history files) share the priority, which of them
+    /// is kept at the edge of the sample then depends on the order. The sample is ordered by priority.
+    pub fn sample_random(self, n: usize, seed: u64) -> Vec<Element> {
+        if n == 0 {
+            return Vec::new();
+        }
+        self.par_blocks()
+            .fold(
+                || Reservoir::new(n),
+                |mut reservoir, block| {
+                    reservoir.offer_block(&block, seed);
+                    reservoir
+                },
+            )
+            .reduce(|| Reservoir::new(n), Reservoir::merge)
+            .into_sorted_vec()
+    }
+
+    /// Creates an iterator that yields owned elements in chunks of `n` across block boundaries
+    /// (e.g. to size database transactions), only the last chunk can be smaller.
+    /// Blocks are converted one at a time, so besides the pipeline at most one chunk and one block
+    /// are held in memory. Filters applied to the reader are respected.
+    ///
+    /// # Panics
+    /// Panics if `n` is 0.
+    pub fn chunks_of(self, n: usize) -> impl Iterator<Item = Vec<Element>> {
+        assert!(n != 0, "chunk size must be non-zero");
+        let mut blocks = self.blocks();
+        let mut pending = Vec::new().into_iter();
+        std::iter::from_fn(move || {
+            let mut chunk = Vec::with_capacity(n);
+            loop {
+                chunk.extend(pending.by_ref().take(n - chunk.len()));
+                if chunk.len() == n {
+                    return Some(chunk);
+                }
+                match blocks.next() {
+                    Some(block) => pending = owned_elements(&block).into_iter(),
+                    None => return (!chunk.is_empty()).then_some(chunk),
+                }
+            }
+        })
+    }
+
+    /// Creates an iterator that yields owned elements in file order together with the sequence
+    /// number of their source blob, counting data blobs from 0 (the OSMHeader and unknown blob
+    /// types are not counted), e.g. to find the blob of a broken way and re-read only that one.
+    /// Filters applied to the reader are respected.
+    ///
+    /// Blobs are decoded in parallel in batches of one blob per thread but consumed in file order.
+    /// An unreadable blob ends the iterator with its error after the elements of the blobs before it.
+    pub fn elements_with_blob_index(self) -> impl Iterator<Item = std::io::Result<(u64, Element)>> {
+        self.ordered_blocks()
+            .zip(0..)
+            .flat_map(|(blocks, blob_index)| match blocks {
+                Ok(blocks) => blocks
+                    .iter()
+                    .flat_map(owned_elements)
+                    .map(|element| Ok((blob_index, element)))
+                    .collect(),
+                Err(err) => vec![Err(err)],
+            })
     }
 
-    /// Creates a parallel iterator that yields [`ElementBlock`]
+    // Decodes blobs in parallel in batches of one blob per thread and yields their blocks in file order.
+    // An unreadable blob ends the iterator with its error after the blocks of the blobs before it.
+    pub(crate) fn ordered_blocks(
+        mut self,
+    ) -> impl Iterator<Item = std::io::Result<Vec<ElementBlock>>> {
+        let parse_options = self.parse_options();
+        let predicate = self.element_predicate();
+        let mut pending = Vec::new().into_iter();
+        let mut done = false;
+        std::iter::from_fn(move || loop {
+            if let Some(blocks) = pending.next() {
+                return Some(blocks);
+            }
+            if done {
+                return None;
+            }
+
+            // stop after the first error, the position in the file is unreliable then
+            let batch_size = self.config.worker_pool.num_threads();
+            let mut blobs = Vec::with_capacity(batch_size);
+            let mut read_error = None;
+            while blobs.len() < batch_size {
+                match self.next_blob_with_hint() {
+                    Ok(Some((_, blob, bbox_hint))) => blobs.push((blob, bbox_hint)),
+                    Ok(None) => break,
+                    Err(err) => {
+                        read_error = Some(err);
+                        break;
+                    }
+                }
+            }
+            done = blobs.len() < batch_size;
+
+            let decompressor = &*self.config.decompressor;
+            let predicate = predicate.as_deref();
+            let parse_options = &parse_options;
+            let mut decoded: Vec<std::io::Result<Vec<ElementBlock>>> =
+                self.config.worker_pool.install(|| {
+                    blobs
+                        .into_par_iter()
+                        .map_init(Vec::new, |decompressed_blob, (blob, bbox_hint)| {
+                            let mut blocks = OsmParser::deserialize_blob(
+                                &blob,
+                                decompressor,
+                                parse_options,
+                                decompressed_blob,
+                            )?;
+                            for block in &mut blocks {
+                                block.set_bbox_hint(bbox_hint);
+                            }
+                            if let Some(predicate) = predicate {
+                                blocks.retain_mut(|block| block.apply_predicate(predicate));
+                            }
+                            Ok(blocks)
+                        })
+                        .collect()
+                });
+            decoded.extend(read_error.map(Err));
+            if let Some(pos) = decoded.iter().position(Result::is_err) {
+                decoded.truncate(pos + 1);
+                done = true;
+            }
+            pending = decoded.into_iter();
+        })
+    }
+
+    /// Creates a parallel iterator that yields [`ElementBlock`].
+    /// Broken blobs are skipped and a read error ends it like the end of the file, without a way to get
+    /// the error, use [`OsmReader::par_for_each_block`] to find out whether the whole file was read.
     pub fn par_blocks(self) -> impl ParallelIterator<Item = ElementBlock> {
         self.blocks().par_bridge()
     }
 
-    /// Creates an iterator that yields [`ElementBlock`]
+    /// Calls `f` with every [`ElementBlock`] in parallel like [`OsmReader::par_blocks`], then waits for the
+    /// background threads and returns the first error they ran into, see [`ElementBlockIter::finish`].
+    /// A read error in the middle of the file (e.g. an [`OsmError::Truncated`]) is returned instead of
+    /// looking like a clean end of the file, after `f` has seen the blocks read up to then.
+    pub fn par_for_each_block<F>(self, f: F) -> std::io::Result<()>
+    where
+        F: Fn(ElementBlock) + Send + Sync,
+    {
+        let mut blocks = self.blocks();
+        blocks.by_ref().par_bridge().for_each(f);
+        blocks.finish()
+    }
+
+    /// Calls `f` with a reference to every element, in parallel at the block level.
+    /// Unlike [`OsmReader::elements_matching_all_filter_keys`] no owned element is allocated,
+    /// which suits scans that only read. Elements of one block are visited in order on the same thread.
+    pub fn par_for_each_element<F>(self, f: F)
+    where
+        F: Fn(ElementRef<'_>) + Send + Sync,
+    {
+        self.par_blocks().for_each(|block| match &block {
+            ElementBlock::DenseNodeBlock(block) => {
+                block.iter().for_each(|node| f(ElementRef::DenseNode(node)))
+            }
+            ElementBlock::NodeBlock(block) => {
+                block.iter().for_each(|node| f(ElementRef::Node(node)))
+            }
+            ElementBlock::WayBlock(block) => block.iter().for_each(|way| f(ElementRef::Way(way))),
+            ElementBlock::RelationBlock(block) => block
+                .iter()
+                .for_each(|relation| f(ElementRef::Relation(relation))),
+        });
+    }
+
+    /// Creates an iterator that yields [`ElementBlock`].
+    /// Broken blobs are skipped, use [`ElementBlockIter::finish`] to get the error.
     pub fn blocks(self) -> ElementBlockIter {
-        let num_threads = rayon::current_num_threads();
-        let (blob_tx, blob_rx) = crossbeam_channel::bounded::<Arc<[u8]>>(num_threads);
+        self.spawn_blocks(|block| block)
+    }
+
+    /// Same as [`OsmReader::blocks`], but merges consecutive blocks of the same type into blocks of
+    /// up to `target_elements` elements (e.g. 8000, the usual size of a full block), for files that
+    /// come in many tiny blocks. Opt-in because merging copies the elements and remaps their string
+    /// ids, see [`CoalescedBlocks`] for the tradeoff. Blocks at or above the target are unchanged.
+    pub fn coalesced_blocks(self, target_elements: usize) -> CoalescedBlocks {
+        CoalescedBlocks::new(self.blocks(), target_elements)
+    }
+
+    /// Applies `f` to every block on the worker threads right after parsing, so the transformation
+    /// (e.g. into a domain type or a count) runs in parallel with decoding and only its results pass
+    /// the channel, e.g. `reader.map_blocks(|block| block.metadata().count()).sum::<usize>()`.
+    /// The in flight limits count the results like blocks. Broken blobs are skipped.
+    pub fn map_blocks<T, F>(self, f: F) -> impl ParallelIterator<Item = T>
+    where
+        T: Send + 'static,
+        F: Fn(ElementBlock) -> T + Send + Sync + 'static,
+    {
+        self.spawn_blocks(f).par_bridge()
+    }
+
+    // Starts the reader thread and the workers, which parse blobs and map every block with f
+    fn spawn_blocks<T, F>(self, f: F) -> ElementBlockIter<T>
+    where
+        T: Send + 'static,
+        F: Fn(ElementBlock) -> T + Send + Sync + 'static,
+    {
+        let num_threads = self.config.worker_pool.num_threads();
+        let read_ahead = self.config.read_ahead.unwrap_or(num_threads);
+        // every blob comes with its sequence number, offset and bbox hint
+        let (blob_tx, blob_rx) =
+            crossbeam_channel::bounded::<(u64, u64, Arc<[u8]>, Option<BBox>)>(read_ahead);
         let (element_block_tx, element_block_rx) =
-            crossbeam_channel::bounded::<ElementBlock>(MAX_Q_ELEMENTS);
+            crossbeam_channel::bounded::<(T, usize)>(self.config.max_in_flight_blocks);
+        let budget = self
+            .config
+            .max_in_flight_bytes
+            .map(|max| Arc::new(InFlightBudget::new(max)));
+        let worker_budget = budget.clone();
+        let decompressor = Arc::clone(&self.config.decompressor);
+        let blob_stats = self.config.blob_stats.clone();
+        let predicate = self.element_predicate();
+        let parse_options = self.parse_options();
+        let worker_pool = self.config.worker_pool.clone();
+        let stop = Arc::new(AtomicBool::new(false));
+        let reader_stop = Arc::clone(&stop);
+        let first_error = FirstError::default();
+        let blob_errors = self.config.blob_errors.clone();
+        #[cfg(feature = "stats")]
+        let stats = Arc::new(StatsCollector::new());
+        #[cfg(feature = "stats")]
+        let (reader_stats, worker_stats) = (Arc::clone(&stats), Arc::clone(&stats));
 
-        // Spawn a thread to continuously read blobs
-        std::thread::spawn(move || {
+        // Nodes only stay inside the region of the bbox filter if other elements cannot follow after it
+        let bbox = parse_options.bbox;
+        let nodes_only = parse_options
+            .element_filter
+            .is_some_and(|f| !f.ways && !f.relations);
+        let region_scan = (self.config.region_early_exit
+            && bbox.is_some()
+            && self.header().is_some_and(|h| h.is_geographically_sorted())
+            && nodes_only)
+            .then(|| Arc::new(RegionScan::default()));
+        let reader_region_scan = region_scan.clone();
+        let stopped_after_region = Arc::new(AtomicBool::new(false));
+        let reader_stopped_after_region = Arc::clone(&stopped_after_region);
+        let skipped_blobs = Arc::clone(&self.skipped_blobs);
+
+        // Spawn a thread to continuously read blobs, a read error ends it and is kept for finish
+        let reader_thread = std::thread::spawn(move || {
             let mut reader = self;
-            while let Ok(Some(blob)) = reader.next_blob() {
-                if blob_tx.send(blob).is_err() {
+            let mut next_blob = || {
+                #[cfg(feature = "stats")]
+                let read_start = Instant::now();
+                let blob = reader.next_blob_with_hint();
+                #[cfg(feature = "stats")]
+                reader_stats.record_read(read_start.elapsed());
+                blob
+            };
+            let mut index = 0;
+            while let Some((offset, blob, bbox_hint)) = next_blob()? {
+                if reader_stop.load(Ordering::Relaxed) {
+                    break;
+                }
+                // blobs are left unread, so the consumer is told about the early exit
+                if reader_region_scan.as_ref().is_some_and(|s| s.is_passed()) {
+                    reader_stopped_after_region.store(true, Ordering::Relaxed);
+                    break;
+                }
+                if blob_tx.send((index, offset, blob, bbox_hint)).is_err() {
                     break;
                 }
+                index += 1;
             }
+            Ok(())
         });
 
-        let pool = rayon::ThreadPoolBuilder::new()
-            .num_threads(num_threads)
-            .build()
-            .expect("Failed to create thread pool");
+        let pool = match worker_pool {
+            WorkerPool::Own => WorkerPool::Shared(Arc::new(
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(num_threads)
+                    .build()
+                    .expect("Failed to create thread pool"),
+            )),
+            pool => pool,
+        };
 
-        // Spawn parsing tasks inside the pool
-        std::thread::spawn(move || {
+        // Spawn parsing tasks inside the pool, broken blobs are skipped and the first error is kept
+        let worker_stop = Arc::clone(&stop);
+        let parser_thread = std::thread::spawn(move || {
+            // keeps the first error for finish and reports every one of them, if asked to
+            let report = |index, offset, error: std::io::Error| match &blob_errors {
+                Some(tx) => {
+                    first_error.record(copy_error(&error));
+                    let _ = tx.send(BlobError {
+                        index,
+                        offset,
+                        error,
+                    });
+                }
+                None => first_error.record(error),
+            };
             pool.install(|| {
-                blob_rx.into_iter().par_bridge().for_each(|blob| {
-                    if let Ok(element_blocks) = OsmParser::deserialize_blob(blob) {
-                        for block in element_blocks {
-                            if element_block_tx.send(block).is_err() {
+                // every worker decompresses into its own scratch buffer, which is cleared and reused
+                // for the next blob, the emitted blocks own their data and never borrow from it
+                let scratch = Vec::new;
+                blob_rx.into_iter().par_bridge().for_each_init(
+                    scratch,
+                    |decompressed_blob, (index, offset, blob, bbox_hint)| {
+                        if worker_stop.load(Ordering::Relaxed) {
+                            return;
+                        }
+                        // if only nodes pass, a blob hinted outside of the bbox filter is never decompressed
+                        let outside = |bbox: &BBox| bbox_hint.is_some_and(|h| !h.intersects(bbox));
+                        if nodes_only && bbox.as_ref().is_some_and(outside) {
+                            if let Some(region_scan) = &region_scan {
+                                region_scan.record(offset, false);
+                            }
+                            return;
+                        }
+                        #[cfg(feature = "stats")]
+                        let decompress_start = Instant::now();
+                        let stats = match OsmParser::decompress_blob_into(
+                            &blob,
+                            &*decompressor,
+                            offset,
+                            decompressed_blob,
+                        ) {
+                            Ok(stats) => stats,
+                            Err(err) => return report(index, offset, err),
+                        };
+                        #[cfg(feature = "stats")]
+                        worker_stats.record_blob(&stats, decompress_start.elapsed());
+                        if let Some(callback) = &blob_stats {
+                            callback(&stats);
+                        }
+                        #[cfg(feature = "stats")]
+                        let parse_start = Instant::now();
+                        let mut element_blocks =
+                            match OsmParser::parse_blob(decompressed_blob, &parse_options) {
+                                Ok(element_blocks) => element_blocks,
+                                Err(err) => {
+                                    let err = std::io::Error::new(ErrorKind::InvalidData, err);
+                                    return report(index, offset, err);
+                                }
+                            };
+                        for block in &mut element_blocks {
+                            block.set_source_compression(stats.compression);
+                            block.set_bbox_hint(bbox_hint);
+                        }
+                        if let Err(err) =
+                            OsmParser::validate_ids(&element_blocks, parse_options.id_policy)
+                        {
+                            return report(index, offset, err.into());
+                        }
+
+                        // drop node blocks without a single node inside the bbox filter
+                        if let Some(bbox) = &bbox {
+                            let mut has_nodes = false;
+                            let mut inside = false;
+                            element_blocks.retain(|block| {
+                                let keep = match block {
+                                    ElementBlock::DenseNodeBlock(_)
+                                    | ElementBlock::NodeBlock(_)
+                                        if outside(bbox) =>
+                                    {
+                                        false
+                                    }
+                                    ElementBlock::DenseNodeBlock(block) => {
+                                        block.has_filtered_nodes()
+                                    }
+                                    ElementBlock::NodeBlock(block) => block.has_filtered_nodes(),
+                                    _ => return true,
+                                };
+                                has_nodes = true;
+                                inside |= keep;
+                                keep
+                            });
+                            if let Some(region_scan) = region_scan.as_ref().filter(|_| has_nodes) {
+                                region_scan.record(offset, inside);
+                            }
+                        }
+
+                        // drop blocks without a single element passing the predicate
+                        if let Some(predicate) = &predicate {
+                            element_blocks.retain_mut(|block| block.apply_predicate(&**predicate));
+                        }
+                        #[cfg(feature = "stats")]
+                        worker_stats.record_parse(parse_start.elapsed());
+
+                        let count = element_blocks.len();
+                        if count == 0 {
+                            return;
+                        }
+
+                        // the decompressed size is charged to the budget and split evenly between the blocks
+                        let size = decompressed_blob.len();
+                        if worker_budget
+                            .as_ref()
+                            .is_some_and(|budget| !budget.acquire(size))
+                        {
+                            return;
+                        }
+                        let sizes =
+                            (0..count).map(|i| size / count + usize::from(i < size % count));
+                        for (block, size) in element_blocks.into_iter().zip(sizes) {
+                            if element_block_tx.send((f(block), size)).is_err() {
                                 return;
                             }
                         }
-                    }
-                });
+                    },
+                );
             });
+            #[cfg(feature = "stats")]
+            worker_stats.finish();
+            first_error.take()
         });
 
         ElementBlockIter {
             rx: element_block_rx,
+            skipped_blobs: Arc::clone(&skipped_blobs),
+            budget,
+            stop,
+            stopped_after_region,
+            reader_thread: Some(reader_thread),
+            parser_thread: Some(parser_thread),
+            #[cfg(feature = "stats")]
+            stats,
         }
     }
 
-    // Sequential operation - raw blobs have different sizes, need to look at length prefix and blob header first to know exact size
-    fn next_blob(&mut self) -> std::io::Result<Option<Arc<[u8]>>> {
-        let mut prefix = [0u8; 4];
+    /// Low-level diagnostics: yields the [`BlobHeader`] and still compressed [`Blob`] of every blob,
+    /// starting at the beginning of the file and including the OSMHeader and unknown blob types.
+    /// Useful to inspect the framing of a broken file, e.g. which `*_data` field holds the data
+    /// of a blob with an unsupported codec. Filters are not applied, nothing is decompressed.
+    /// Stops after the first error, a truncated file yields an [`OsmError::Truncated`](crate::OsmError::Truncated).
+    pub fn raw_blobs(
+        mut self,
+    ) -> impl Iterator<Item = std::io::Result<(BlobHeader<'static>, Blob<'static>)>> {
+        // stop reading after the first error, the position in the file is unreliable then
+        let mut rewind = self.seek_to(0);
+        let mut failed = false;
+        std::iter::from_fn(move || {
+            if failed {
+                return None;
+            }
+            let blob = match std::mem::replace(&mut rewind, Ok(())) {
+                Ok(()) => self.next_raw_blob().transpose(),
+                Err(err) => Some(Err(err)),
+            };
+            failed = matches!(blob, Some(Err(_)));
+            blob
+        })
+    }
+
+    // Reads the next BlobHeader and Blob regardless of the blob type, copied out of the buffers
+    fn next_raw_blob(&mut self) -> std::io::Result<Option<(BlobHeader<'static>, Blob<'static>)>> {
+        let offset = self.offset;
+        let header_size = match self.read_blob_header() {
+            Ok(Some(header_size)) => header_size,
+            Ok(None) => return Ok(None), // EOF
+            Err(err) => return self.on_truncation(offset, err),
+        };
+        let header = Self::parse_blob_header(&self.header[..header_size])?;
+        let header = BlobHeader {
+            type_pb: Cow::Owned(header.type_pb.into_owned()),
+            indexdata: owned_bytes(header.indexdata),
+            datasize: header.datasize,
+        };
+
+        let blob_slice = match self.read_blob(header.datasize as usize) {
+            Ok(blob_slice) => blob_slice,
+            Err(err) => return self.on_truncation(offset, err),
+        };
+        let mut reader = BytesReader::from_bytes(&blob_slice);
+        let blob = Blob::from_reader(&mut reader, &blob_slice)
+            .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e))?;
+        let blob = Blob {
+            raw: owned_bytes(blob.raw),
+            raw_size: blob.raw_size,
+            zlib_data: owned_bytes(blob.zlib_data),
+            lzma_data: owned_bytes(blob.lzma_data),
+            bzip2_data: owned_bytes(blob.bzip2_data),
+            lz4_data: owned_bytes(blob.lz4_data),
+            zstd_data: owned_bytes(blob.zstd_data),
+        };
+        Ok(Some((header, blob)))
+    }
+
+    // Collects the options and filters the blobs of this reader are parsed with
+    // The predicate of with_predicate, combined with the mode of the tag filter and the id ranges
+    fn element_predicate(&self) -> Option<Arc<ElementPredicate>> {
+        let predicate = self.config.predicate.clone();
+        let mode = self.config.tag_filter_mode.get().copied();
+        let id_filter = self.config.id_filter.clone().map(OnceLock::into_inner);
+        if mode.is_none() && id_filter.iter().all(Option::is_none) {
+            return predicate;
+        }
+        Some(Arc::new(move |element: &ElementRef<'_>| {
+            id_filter[element.element_type() as usize]
+                .as_ref()
+                .is_none_or(|ids| ids.contains(&element.id()))
+                && mode.is_none_or(|mode| mode.matches(element))
+                && predicate
+                    .as_ref()
+                    .is_none_or(|predicate| predicate(element))
+        }))
+    }
+
+    pub(crate) fn parse_options(&self) -> ParseOptions {
+        ParseOptions {
+            skip_tags: self.config.skip_tags,
+            metadata_only: self.config.metadata_only,
+            skip_degenerate_ways: self.config.skip_degenerate_ways,
+            raw_string_table: self.config.raw_string_table,
+            element_filter: self.config.element_filter.get().copied(),
+            tag_keys: self.config.tag_filter.get().cloned(),
+            bbox: self.config.bbox_filter.get().copied(),
+            id_policy: self.id_policy(),
+            interner: self.config.interner.clone(),
+        }
+    }
+
+    // The id policy, relaxed for files of producers known to write negative ids
+    fn id_policy(&self) -> IdPolicy {
+        let negative_ids = self.config.producer_workarounds
+            && self.header().is_some_and(|header| {
+                header
+                    .producer_quirks()
+                    .contains(&ProducerQuirk::NegativeIds)
+            });
+        match self.config.id_policy {
+            IdPolicy::Strict if negative_ids => IdPolicy::AllowNegative,
+            policy => policy,
+        }
+    }
+
+    // Sequential operation - raw blobs have different sizes, need to look at length prefix and blob header first to know exact size.
+    // Also returns the byte offset in the file where the blob starts
+    pub(crate) fn next_blob_with_offset(&mut self) -> std::io::Result<Option<(u64, Arc<[u8]>)>> {
+        let blob = self.next_blob_with_hint()?;
+        Ok(blob.map(|(offset, blob, _)| (offset, blob)))
+    }
+
+    // Same as next_blob_with_offset, but also yields the bbox hint of the blob, see ElementBlock::bbox
+    fn next_blob_with_hint(&mut self) -> std::io::Result<Option<HintedBlob>> {
+        loop {
+            let offset = self.offset;
+            if self
+                .config
+                .range
+                .as_ref()
+                .is_some_and(|range| offset >= range.end)
+            {
+                return Ok(None); // end of the shard
+            }
+            let header = match self.next_blob_header() {
+                Ok(header) => header,
+                Err(err) => return self.on_truncation(offset, err),
+            };
+            let Some((blob_type, blob_size, bbox_hint)) = header else {
+                return Ok(None); // EOF
+            };
+
+            // Skip everything that is not actual relevant data
+            if blob_type != BlobType::Data {
+                // the OSMHeader the file starts with is expected, not skipped
+                if offset != 0 || blob_type != BlobType::Header {
+                    let name = match blob_type {
+                        BlobType::Unknown(name) => name.into(),
+                        _ => String::from("OSMHeader"),
+                    };
+                    *self.skipped_blobs.lock().unwrap().entry(name).or_insert(0) += 1;
+                }
+                if self.offset + blob_size as u64 > self.reader.get_ref().size()? {
+                    return self.on_truncation(offset, ErrorKind::UnexpectedEof.into());
+                }
+                self.reader.seek_relative(blob_size as i64)?;
+                self.advance(blob_size as u64);
+                continue;
+            }
+
+            return match self.read_blob(blob_size) {
+                Ok(blob) => Ok(Some((offset, blob, bbox_hint))),
+                Err(err) => self.on_truncation(offset, err),
+            };
+        }
+    }
+
+    // Turns an unexpected EOF inside of the blob at offset into OsmError::Truncated,
+    // or into a regular EOF with truncation_as_eof
+    fn on_truncation<T>(&self, offset: u64, err: std::io::Error) -> std::io::Result<Option<T>> {
+        if err.kind() != ErrorKind::UnexpectedEof {
+            return Err(err);
+        }
+        if self.config.truncation_as_eof {
+            return Ok(None);
+        }
+        Err(OsmError::Truncated { offset }.into())
+    }
 
-        // Read length prefix (always 4 bytes)
-        if self.reader.read_exact(&mut prefix).is_err() {
+    // Moves the reader to a byte offset previously returned by next_blob_with_offset
+    pub(crate) fn seek_to(&mut self, offset: u64) -> std::io::Result<()> {
+        self.reader.seek(SeekFrom::Start(offset))?;
+        self.offset = offset;
+        self.progress.bytes_read.store(offset, Ordering::Relaxed);
+        Ok(())
+    }
+
+    // Seeks over the blobs starting before offset, only their BlobHeaders are read
+    fn skip_to(&mut self, offset: u64) -> std::io::Result<()> {
+        while self.offset < offset {
+            let blob_offset = self.offset;
+            let blob_size = match self.next_blob_header() {
+                Ok(Some((_, blob_size, _))) => blob_size as u64,
+                Ok(None) => break, // EOF
+                Err(err) => return self.on_truncation::<()>(blob_offset, err).map(|_| ()),
+            };
+            if self.offset + blob_size > self.reader.get_ref().size()? {
+                let err = ErrorKind::UnexpectedEof.into();
+                return self.on_truncation::<()>(blob_offset, err).map(|_| ());
+            }
+            self.reader.seek_relative(blob_size as i64)?;
+            self.advance(blob_size);
+        }
+        Ok(())
+    }
+
+    // Moves the offset past bytes that were read or skipped
+    fn advance(&mut self, bytes: u64) {
+        self.offset += bytes;
+        self.progress
+            .bytes_read
+            .store(self.offset, Ordering::Relaxed);
+    }
+
+    // Reads the length prefix and BlobHeader, returns the type, size and bbox hint of the following blob
+    fn next_blob_header(&mut self) -> std::io::Result<Option<(BlobType, usize, Option<BBox>)>> {
+        let Some(header_size) = self.read_blob_header()? else {
             return Ok(None); // EOF
+        };
+
+        // Deserialize blob header to get size of blob
+        let header = Self::parse_blob_header(&self.header[..header_size])?;
+        let bbox_hint = header.indexdata.as_deref().and_then(bbox_hint);
+        let size = header.datasize as usize;
+        Ok(Some((BlobType::of(&header), size, bbox_hint)))
+    }
+
+    pub(crate) fn parse_blob_header(bytes: &[u8]) -> std::io::Result<BlobHeader<'_>> {
+        let mut reader = BytesReader::from_bytes(bytes);
+        BlobHeader::from_reader(&mut reader, bytes)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+
+    // Reads the length prefix and the BlobHeader bytes into the header buffer, returns their size
+    fn read_blob_header(&mut self) -> std::io::Result<Option<usize>> {
+        let mut prefix = [0u8; 4];
+
+        // Read length prefix (always 4 bytes), the file may only end right before it
+        match read_until_eof(&mut self.reader, &mut prefix)? {
+            0 => return Ok(None), // EOF
+            4 => (),
+            _ => return Err(ErrorKind::UnexpectedEof.into()),
         }
 
         let header_size = u32::from_be_bytes(prefix) as usize;
-        if header_size > self.header.capacity() {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "BlobHeader size exceeds limit of 64KB. File corrupt?",
-            ));
+        if header_size > MAX_HEADER_SIZE {
+            let (offset, size) = (self.offset, header_size);
+            return Err(OsmError::HeaderTooLarge { offset, size }.into());
         }
 
         if self.header.len() < header_size {
             self.header.resize(header_size, 0);
         }
         self.reader.read_exact(&mut self.header[..header_size])?;
+        self.advance(4 + header_size as u64);
+        Ok(Some(header_size))
+    }
 
-        // Deserialize blob header to get size of blob
-        let mut reader = BytesReader::from_bytes(&self.header[..header_size]);
-        let header = BlobHeader::from_reader(&mut reader, &self.header[..header_size])
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
-        let blob_size = header.datasize as usize;
-
-        // Skip everything that is not actual relevant data
-        if header.type_pb != "OSMData" {
-            self.reader.seek_relative(blob_size as i64)?;
-            return self.next_blob();
-        }
-
+    // Reads the blob of the given size that follows a BlobHeader
+    fn read_blob(&mut self, blob_size: usize) -> std::io::Result<Arc<[u8]>> {
         if self.blob.len() < blob_size {
             // grow buffer slightly larger to reduce repeated reallocs
-            let new_capacity = (blob_size * 2) as usize;
+            let new_capacity = blob_size * 2;
             self.blob.resize(new_capacity, 0);
         }
 
@@ -183,8 +1747,475 @@ impl OsmReader {
             self.blob.resize(blob_size, 0);
         }
         self.reader.read_exact(&mut self.blob[..blob_size])?;
+        self.advance(blob_size as u64);
         let blob_slice: Arc<[u8]> = Arc::from(&self.blob[..blob_size]);
 
-        return Ok(Some(blob_slice));
+        Ok(blob_slice)
+    }
+
+    // The OSMHeader is the first blob of a file. If the file does not start with one, rewind.
+    fn read_osm_header(&mut self) -> std::io::Result<Option<OsmHeader>> {
+        match self.next_blob_header()? {
+            Some((BlobType::Header, blob_size, _)) => {
+                let blob = self.read_blob(blob_size)?;
+                let header = OsmParser::decompress_blob(&blob, &*self.config.decompressor)?;
+                OsmHeader::from_bytes(&header)
+                    .map(Some)
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+            }
+            Some(_) => {
+                self.seek_to(0)?;
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+// Bytes of decoded blocks that are queued but not consumed yet.
+// Workers block in acquire until enough bytes were released by the consumer.
+#[derive(Debug)]
+pub(crate) struct InFlightBudget {
+    max: usize,
+    // (bytes in flight, closed)
+    state: Mutex<(usize, bool)>,
+    released: Condvar,
+}
+
+impl InFlightBudget {
+    fn new(max: usize) -> Self {
+        Self {
+            max,
+            state: Mutex::new((0, false)),
+            released: Condvar::new(),
+        }
+    }
+
+    // Waits until bytes fit into the budget. A blob larger than the whole budget is let through
+    // once nothing else is in flight. Returns false if the consumer is gone.
+    fn acquire(&self, bytes: usize) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        while !state.1 && state.0 > 0 && state.0 + bytes > self.max {
+            state = self.released.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+        state.0 += bytes;
+        !state.1
+    }
+
+    pub(crate) fn release(&self, bytes: usize) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.0 = state.0.saturating_sub(bytes);
+        self.released.notify_all();
+    }
+
+    // Wakes up all waiting workers so they can shut down
+    pub(crate) fn close(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.1 = true;
+        self.released.notify_all();
+    }
+}
+
+// The rayon pool the blobs are parsed on
+#[derive(Debug, Clone)]
+enum WorkerPool {
+    // a pool built for every call of blocks
+    Own,
+    Global,
+    Shared(Arc<rayon::ThreadPool>),
+}
+
+impl WorkerPool {
+    fn num_threads(&self) -> usize {
+        match self {
+            Self::Shared(pool) => pool.current_num_threads(),
+            Self::Own | Self::Global => rayon::current_num_threads(),
+        }
+    }
+
+    // Runs op inside the shared pool, otherwise on the global pool (or the pool of the caller)
+    fn install<R: Send>(&self, op: impl FnOnce() -> R + Send) -> R {
+        match self {
+            Self::Shared(pool) => pool.install(op),
+            Self::Own | Self::Global => op(),
+        }
+    }
+}
+
+// Copy of an error for finish, whose original goes to the blob errors. Errors of the crate stay
+// matchable, others keep their kind and message
+fn copy_error(err: &std::io::Error) -> std::io::Error {
+    match err
+        .get_ref()
+        .and_then(|inner| inner.downcast_ref::<OsmError>())
+    {
+        Some(osm_error) => std::io::Error::new(err.kind(), osm_error.clone()),
+        None => std::io::Error::new(err.kind(), err.to_string()),
+    }
+}
+
+// The first error of the parsing workers, later ones are dropped
+#[derive(Default)]
+struct FirstError(Mutex<Option<std::io::Error>>);
+
+impl FirstError {
+    fn record(&self, err: std::io::Error) {
+        let mut first = self.0.lock().unwrap();
+        if first.is_none() {
+            *first = Some(err);
+        }
+    }
+
+    fn take(&self) -> std::io::Result<()> {
+        match self.0.lock().unwrap().take() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+}
+
+// The bbox hint in the indexdata of a BlobHeader. Its encoding is unspecified, so it is only trusted
+// if it is exactly an encoded HeaderBBox (all four fields in order, nothing else) of a valid box
+fn bbox_hint(indexdata: &[u8]) -> Option<BBox> {
+    let mut reader = BytesReader::from_bytes(indexdata);
+    let hint = HeaderBBox::from_reader(&mut reader, indexdata).ok()?;
+    if encode_message(&hint) != indexdata {
+        return None;
+    }
+    let degrees = |nanodegrees: i64| nanodegrees as f64 * 1e-9;
+    let bbox = BBox {
+        left: degrees(hint.left),
+        bottom: degrees(hint.bottom),
+        right: degrees(hint.right),
+        top: degrees(hint.top),
+    };
+    let valid = -90.0 <= bbox.bottom
+        && bbox.bottom <= bbox.top
+        && bbox.top <= 90.0
+        && -180.0 <= bbox.left
+        && bbox.left <= bbox.right
+        && bbox.right <= 180.0;
+    valid.then_some(bbox)
+}
+
+// Tracks whether reading a geographically sorted file has passed the region of the bbox filter.
+// Blobs are decoded out of order, so the offset of the first blob inside is kept.
+#[derive(Debug, Default)]
+struct RegionScan {
+    first_inside: Mutex<Option<u64>>,
+    passed: AtomicBool,
+}
+
+impl RegionScan {
+    fn record(&self, offset: u64, inside: bool) {
+        let mut first_inside = self.first_inside.lock().unwrap_or_else(|e| e.into_inner());
+        if inside {
+            *first_inside = Some(first_inside.map_or(offset, |first| first.min(offset)));
+        } else if first_inside.is_some_and(|first| first < offset) {
+            self.passed.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn is_passed(&self) -> bool {
+        self.passed.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OwnedNode, PbfFixture};
+    use std::sync::Weak;
+
+    fn nodes(count: i64) -> PbfFixture {
+        let nodes = (1..=count).map(|id| {
+            let tags = vec![("name".to_owned(), id.to_string())];
+            OwnedNode {
+                id,
+                lat: 0.0,
+                lon: 0.0,
+                tags,
+            }
+            .into()
+        });
+        PbfFixture::new(nodes).with_block_size(1)
+    }
+
+    // The keys of the tag filter, only kept alive by the reader and its threads
+    fn tag_filter_keys(reader: &OsmReader) -> Weak<[Box<str>]> {
+        Arc::downgrade(reader.config.tag_filter.get().unwrap())
+    }
+
+    #[test]
+    fn dropping_a_reader_frees_its_tag_filter() {
+        let file = nodes(1).to_temp_file("drop-reader");
+        for _ in 0..100 {
+            let reader = OsmReader::from_path(&*file).unwrap();
+            reader.apply_tag_filter(&["name".to_owned()]).unwrap();
+            let keys = tag_filter_keys(&reader);
+            drop(reader);
+            assert!(keys.upgrade().is_none());
+        }
+    }
+
+    #[test]
+    fn dropping_blocks_mid_stream_joins_the_threads() {
+        let file = nodes(100).to_temp_file("drop-blocks");
+        for _ in 0..10 {
+            let reader = OsmReader::from_path(&*file)
+                .unwrap()
+                .with_max_in_flight_blocks(1);
+            reader.apply_tag_filter(&["name"]).unwrap();
+            let keys = tag_filter_keys(&reader);
+            let mut blocks = reader.blocks();
+            drop(blocks.next().unwrap());
+            drop(blocks);
+            // the reader moved into the reader thread and the filter into the workers,
+            // both are gone once the threads are joined
+            assert!(keys.upgrade().is_none());
+        }
+    }
+
+    // The bytes of a file that fail to read past fail_at, like a disk error in the middle of a file
+    struct FailingSource {
+        bytes: std::io::Cursor<Vec<u8>>,
+        fail_at: u64,
+    }
+
+    impl Read for FailingSource {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let left = self.fail_at.saturating_sub(self.bytes.position()) as usize;
+            if left == 0 {
+                return Err(std::io::Error::other("injected read error"));
+            }
+            let len = buf.len().min(left);
+            self.bytes.read(&mut buf[..len])
+        }
+    }
+
+    impl Seek for FailingSource {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            self.bytes.seek(pos)
+        }
+    }
+
+    impl Source for FailingSource {
+        fn size(&self) -> std::io::Result<u64> {
+            Ok(self.bytes.get_ref().len() as u64)
+        }
+    }
+
+    // A reader of the fixture whose reads fail halfway through the file
+    fn failing_halfway(fixture: &PbfFixture) -> OsmReader {
+        let file = fixture.to_temp_file("failing-reader");
+        let mut reader = OsmReader::from_path(&*file).unwrap();
+        let bytes = fixture.to_bytes();
+        let fail_at = bytes.len() as u64 / 2;
+        let mut source = FailingSource {
+            bytes: std::io::Cursor::new(bytes),
+            fail_at,
+        };
+        source.seek(SeekFrom::Start(reader.offset)).unwrap();
+        reader.reader = BufReader::new(Box::new(source));
+        reader
+    }
+
+    fn is_injected(err: &std::io::Error) -> bool {
+        err.kind() == ErrorKind::Other && err.to_string() == "injected read error"
+    }
+
+    #[test]
+    fn read_errors_of_blocks_come_out_of_finish() {
+        let fixture = nodes(100);
+        let mut blocks = failing_halfway(&fixture).blocks();
+        let count = blocks.by_ref().count();
+        assert!(count > 0 && count < 100, "{count} blocks");
+        let err = blocks.finish().unwrap_err();
+        assert!(is_injected(&err), "{err}");
+    }
+
+    #[test]
+    fn read_errors_of_par_for_each_block_are_returned() {
+        let fixture = nodes(100);
+        let count = AtomicU64::new(0);
+        let err = failing_halfway(&fixture)
+            .par_for_each_block(|_| {
+                count.fetch_add(1, Ordering::Relaxed);
+            })
+            .unwrap_err();
+        assert!(is_injected(&err), "{err}");
+        let count = count.into_inner();
+        assert!(count > 0 && count < 100, "{count} blocks");
+    }
+
+    // Where the BlobHeader of the frame at start ends and where its blob ends
+    fn frame_ends(bytes: &[u8], start: usize) -> (usize, usize) {
+        let header_size = u32::from_be_bytes(bytes[start..start + 4].try_into().unwrap());
+        let header_end = start + 4 + header_size as usize;
+        let header = OsmReader::parse_blob_header(&bytes[start + 4..header_end]).unwrap();
+        (header_end, header_end + header.datasize as usize)
+    }
+
+    // The bytes of the fixture with the data blob at index overwritten, so it fails to parse.
+    // Returns the byte offset of the blob too.
+    fn corrupt_data_blob(fixture: &PbfFixture, index: usize) -> (Vec<u8>, u64) {
+        let mut bytes = fixture.to_bytes();
+        // the OSMHeader comes first
+        let mut start = 0;
+        for _ in 0..=index {
+            start = frame_ends(&bytes, start).1;
+        }
+        let (header_end, blob_end) = frame_ends(&bytes, start);
+        bytes[header_end..blob_end].fill(0xff);
+        (bytes, start as u64)
+    }
+
+    #[test]
+    fn corrupt_blobs_are_skipped_and_reported() {
+        let fixture = nodes(10);
+        let file = fixture.to_temp_file("corrupt-blob");
+        let (bytes, offset) = corrupt_data_blob(&fixture, 3);
+        std::fs::write(&*file, bytes).unwrap();
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut blocks = OsmReader::from_path(&*file)
+            .unwrap()
+            .with_blob_errors(sender)
+            .blocks();
+        let mut ids: Vec<i64> = blocks
+            .by_ref()
+            .flat_map(|block| match block {
+                ElementBlock::DenseNodeBlock(block) => {
+                    block.iter().map(|mut node| node.id()).collect()
+                }
+                _ => Vec::new(),
+            })
+            .collect();
+        ids.sort_unstable();
+        assert_eq!(ids, [1, 2, 3, 5, 6, 7, 8, 9, 10]);
+
+        let err = blocks.finish().unwrap_err();
+        let blob_errors: Vec<_> = receiver.try_iter().collect();
+        assert_eq!(blob_errors.len(), 1);
+        let blob_error = &blob_errors[0];
+        assert_eq!((blob_error.index, blob_error.offset), (3, offset));
+        assert_eq!(err.kind(), blob_error.error.kind());
+        assert_eq!(err.to_string(), blob_error.error.to_string());
+    }
+
+    #[test]
+    fn files_without_nodes_yield_their_blocks() {
+        for (fixture, name, ways) in [
+            (PbfFixture::ways_only(), "ways-only", true),
+            (PbfFixture::relations_only(), "relations-only", false),
+        ] {
+            let file = fixture.to_temp_file(name);
+            let mut blocks = OsmReader::from_path(&*file).unwrap().blocks();
+            let mut elements = 0;
+            for block in blocks.by_ref() {
+                elements += match block {
+                    ElementBlock::WayBlock(block) if ways => block.iter().count(),
+                    ElementBlock::RelationBlock(block) if !ways => block.iter().count(),
+                    _ => panic!("{name} yielded a block of another element type"),
+                };
+            }
+            blocks.finish().unwrap();
+            assert_eq!(elements, 5, "{name}");
+        }
+    }
+
+    // Nodes 1 and 3 near null island and node 2 far from it, one blob each with a bbox hint
+    fn hinted_nodes() -> PbfFixture {
+        let coordinates = [(0.5, 0.5), (50.0, 50.0), (-0.5, 0.25)];
+        let nodes = coordinates.into_iter().zip(1..).map(|((lat, lon), id)| {
+            let tags = vec![("name".to_owned(), id.to_string())];
+            OwnedNode { id, lat, lon, tags }.into()
+        });
+        PbfFixture::new(nodes).with_block_size(1).with_bbox_hints()
+    }
+
+    #[test]
+    fn blobs_outside_the_bbox_filter_are_dropped_by_their_hint() {
+        let file = hinted_nodes().to_temp_file("bbox-hints");
+        let bbox = BBox {
+            left: -1.0,
+            bottom: -1.0,
+            right: 1.0,
+            top: 1.0,
+        };
+        let nodes_only = ElementFilter {
+            nodes: true,
+            ways: false,
+            relations: false,
+        };
+        for element_filter in [None, Some(nodes_only)] {
+            let decompressed = Arc::new(AtomicU64::new(0));
+            let counter = Arc::clone(&decompressed);
+            let mut reader = OsmReader::from_path(&*file)
+                .unwrap()
+                .with_blob_stats(move |_| {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                })
+                .with_bbox_filter(bbox)
+                .unwrap();
+            if let Some(filter) = element_filter {
+                reader = reader.with_element_filter(filter).unwrap();
+            }
+            let mut ids = Vec::new();
+            for block in reader.blocks() {
+                let hint = block.bbox().expect("every blob of the fixture has a hint");
+                for node in block.nodes() {
+                    assert!(hint.contains(node.lat(), node.lon()));
+                    ids.push(node.id());
+                }
+            }
+            ids.sort();
+            assert_eq!(ids, [1, 3]);
+            // the blob of node 2 is only decompressed if ways could be in it
+            let expected = if element_filter.is_some() { 2 } else { 3 };
+            assert_eq!(decompressed.load(Ordering::Relaxed), expected);
+        }
+    }
+
+    #[test]
+    fn only_exact_header_bboxes_are_taken_as_hints() {
+        let encoded = |left, right, top, bottom| {
+            encode_message(&HeaderBBox {
+                left,
+                right,
+                top,
+                bottom,
+            })
+        };
+        let hint = bbox_hint(&encoded(
+            -1_000_000_000,
+            2_000_000_000,
+            4_000_000_000,
+            3_000_000_000,
+        ));
+        assert_eq!(
+            hint,
+            Some(BBox {
+                left: -1.0,
+                bottom: 3.0,
+                right: 2.0,
+                top: 4.0
+            })
+        );
+        // left of the box is east of its right
+        assert_eq!(
+            bbox_hint(&encoded(
+                2_000_000_000,
+                1_000_000_000,
+                4_000_000_000,
+                3_000_000_000
+            )),
+            None
+        );
+        // trailing bytes after the box, or no box at all
+        let mut trailing = encoded(0, 0, 0, 0);
+        trailing.extend([0x28, 0x01]);
+        assert_eq!(bbox_hint(&trailing), None);
+        assert_eq!(bbox_hint(b"not a bbox"), None);
     }
 }
@@ -0,0 +1,135 @@
+use crate::{ElementBlock, MemberType, OsmReader};
+use rayon::{iter::ParallelIterator, slice::ParallelSliceMut};
+use std::collections::HashMap;
+
+/// Maps ids to the sorted ids of the elements referencing them, stored compactly like a CSR matrix:
+/// one sorted list of referenced ids and the referrers of all of them in one contiguous list.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Adjacency {
+    ids: Vec<i64>,
+    // referrers of ids[i] are referrers[offsets[i]..offsets[i + 1]]
+    offsets: Vec<usize>,
+    referrers: Vec<i64>,
+}
+
+impl Adjacency {
+    // Builds the adjacency from (referenced id, referrer) pairs, duplicate pairs are kept once
+    fn from_pairs(mut pairs: Vec<(i64, i64)>) -> Self {
+        pairs.par_sort_unstable();
+        pairs.dedup();
+
+        let mut adjacency = Self {
+            ids: Vec::new(),
+            offsets: vec![0],
+            referrers: Vec::with_capacity(pairs.len()),
+        };
+        for (id, referrer) in pairs {
+            if adjacency.ids.last() != Some(&id) {
+                if !adjacency.ids.is_empty() {
+                    adjacency.offsets.push(adjacency.referrers.len());
+                }
+                adjacency.ids.push(id);
+            }
+            adjacency.referrers.push(referrer);
+        }
+        if !adjacency.ids.is_empty() {
+            adjacency.offsets.push(adjacency.referrers.len());
+        }
+        adjacency
+    }
+
+    /// Get the sorted ids of the elements referencing `id`, empty if there are none
+    pub fn get(&self, id: i64) -> &[i64] {
+        match self.ids.binary_search(&id) {
+            Ok(pos) => &self.referrers[self.offsets[pos]..self.offsets[pos + 1]],
+            Err(_) => &[],
+        }
+    }
+
+    /// Get the number of referenced ids
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Check if no id is referenced
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Creates an iterator over the referenced ids in ascending order and their referrers
+    pub fn iter(&self) -> impl Iterator<Item = (i64, &[i64])> {
+        self.ids
+            .iter()
+            .zip(self.offsets.windows(2))
+            .map(|(&id, range)| (id, &self.referrers[range[0]..range[1]]))
+    }
+
+    /// Converts into a map, which needs considerably more memory than the compact form
+    pub fn into_hash_map(self) -> HashMap<i64, Vec<i64>> {
+        self.iter()
+            .map(|(id, referrers)| (id, referrers.to_vec()))
+            .collect()
+    }
+}
+
+/// The result of [`OsmReader::build_reverse_index`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReverseIndex {
+    /// Maps node ids to the ways referencing them
+    pub node_ways: Adjacency,
+    /// Maps way ids to the relations that have them as member
+    pub way_relations: Adjacency,
+}
+
+impl OsmReader {
+    /// Scans the file once and builds the reverse references node → ways and way → relations,
+    /// e.g. to find the intersections of a routing graph. Blocks are scanned in parallel, filters
+    /// applied to the reader are respected, so apply an element filter without nodes to skip them.
+    ///
+    /// While building, every reference is held as an (id, referrer) pair of 16 bytes, the result is
+    /// stored in the compact [`Adjacency`] form with 8 bytes per reference and 16 bytes per referenced id.
+    pub fn build_reverse_index(self) -> ReverseIndex {
+        let (node_ways, way_relations) = self
+            .map_blocks(|block| {
+                let (mut node_ways, mut way_relations) = (Vec::new(), Vec::new());
+                match block {
+                    ElementBlock::WayBlock(block) => {
+                        for way in block.iter() {
+                            node_ways.extend(way.node_ids().map(|node_id| (node_id, way.id())));
+                        }
+                    }
+                    ElementBlock::RelationBlock(block) => {
+                        for relation in block.iter() {
+                            let ways = relation
+                                .members()
+                                .filter(|member| member.member_type() == MemberType::WAY);
+                            way_relations.extend(ways.map(|member| (member.id(), relation.id())));
+                        }
+                    }
+                    _ => (),
+                }
+                (node_ways, way_relations)
+            })
+            .reduce(
+                || (Vec::new(), Vec::new()),
+                |(mut node_ways, mut way_relations), (other_node_ways, other_way_relations)| {
+                    append_pairs(&mut node_ways, other_node_ways);
+                    append_pairs(&mut way_relations, other_way_relations);
+                    (node_ways, way_relations)
+                },
+            );
+
+        ReverseIndex {
+            node_ways: Adjacency::from_pairs(node_ways),
+            way_relations: Adjacency::from_pairs(way_relations),
+        }
+    }
+}
+
+// Appends the smaller list to the larger one, so merging copies as little as possible
+fn append_pairs(pairs: &mut Vec<(i64, i64)>, mut other: Vec<(i64, i64)>) {
+    if pairs.len() < other.len() {
+        std::mem::swap(pairs, &mut other);
+    }
+    pairs.extend(other);
+}
@@ -0,0 +1,376 @@
+use crate::{ElementBlock, ElementFilter, ElementRef, FilterMode, OsmReader};
+use std::{
+    collections::{HashMap, HashSet},
+    io::ErrorKind,
+    sync::Arc,
+};
+
+// Mean earth radius in meters (IUGG)
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+// Tags of ways read by the way pass, sorted like the tag filter stores them
+const WAY_KEYS: [&str; 4] = ["highway", "junction", "oneway", "route"];
+
+// (lat, lon) of nodes by id
+type Coords = HashMap<i64, (f64, f64)>;
+
+/// A directed edge of a [`RoutingGraph`], the part of a way between two graph nodes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RoutingEdge {
+    /// Index of the graph node the edge starts at
+    pub from: u32,
+    /// Index of the graph node the edge ends at
+    pub to: u32,
+    /// Id of the way the edge is a part of
+    pub way_id: i64,
+    /// Length along all nodes of the way in between, in meters (on a sphere)
+    pub length: f64,
+    /// Whether the edge is part of a ferry route (`route=ferry`)
+    pub ferry: bool,
+}
+
+/// A graph of the routable ways of a file, see [`RoutingGraphBuilder`].
+///
+/// Graph nodes are the OSM nodes where ways end, meet or cross, numbered by ascending node id.
+/// The nodes in between only contribute to the length of the edges, their coordinates are not kept.
+/// Edges are directed, a way usable in both directions yields an edge for each. They are stored
+/// sorted by their start node, so the outgoing edges of a node are one contiguous slice.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RoutingGraph {
+    node_ids: Vec<i64>,
+    coords: Vec<(f64, f64)>,
+    // outgoing edges of node i are edges[offsets[i]..offsets[i + 1]]
+    offsets: Vec<usize>,
+    edges: Vec<RoutingEdge>,
+}
+
+impl RoutingGraph {
+    /// Get the number of graph nodes
+    pub fn node_count(&self) -> usize {
+        self.node_ids.len()
+    }
+
+    /// Get the number of directed edges
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    /// Get the index of the graph node of an OSM node, [`None`] if it is not a graph node
+    pub fn node_index(&self, node_id: i64) -> Option<u32> {
+        self.node_ids
+            .binary_search(&node_id)
+            .ok()
+            .map(|index| index as u32)
+    }
+
+    /// Get the OSM node id of a graph node
+    pub fn node_id(&self, index: u32) -> i64 {
+        self.node_ids[index as usize]
+    }
+
+    /// Get the coordinates of a graph node as `(lat, lon)`
+    pub fn coords(&self, index: u32) -> (f64, f64) {
+        self.coords[index as usize]
+    }
+
+    /// Get the edges starting at a graph node
+    pub fn outgoing(&self, index: u32) -> &[RoutingEdge] {
+        let index = index as usize;
+        &self.edges[self.offsets[index]..self.offsets[index + 1]]
+    }
+
+    /// Get all edges, sorted by their start node
+    pub fn edges(&self) -> &[RoutingEdge] {
+        &self.edges
+    }
+}
+
+/// Extracts a [`RoutingGraph`] out of a file in two passes, one over the ways and one over the nodes.
+///
+/// Ways are routable if their `highway` value is one of the configured ones (or if they are ferry routes,
+/// see [`RoutingGraphBuilder::with_ferries`]). They are split into edges at every node shared with
+/// another routable way and at their ends. Directions follow `oneway` (`yes`, `true`, `1`, or `-1` and
+/// `reverse` for the opposite direction), roundabouts (`junction=roundabout` or `circular`) and
+/// motorways are oneway unless tagged `oneway=no`. Access tags and turn restrictions are not evaluated.
+///
+/// Parts of ways referencing nodes missing from the file (e.g. at the border of an extract) are left out.
+/// While building, the ids of all nodes of routable ways and their coordinates are held in memory.
+#[derive(Debug, Clone)]
+pub struct RoutingGraphBuilder {
+    highways: HashSet<String>,
+    ferries: bool,
+    barriers: HashSet<String>,
+}
+
+impl RoutingGraphBuilder {
+    /// Creates a builder for ways with one of the `highway` values, e.g. `["primary", "residential"]`
+    pub fn new<S: Into<String>>(highways: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            highways: highways.into_iter().map(Into::into).collect(),
+            ferries: false,
+            barriers: HashSet::new(),
+        }
+    }
+
+    /// Also routes along ferry routes (`route=ferry`), their edges are marked as [`RoutingEdge::ferry`]
+    pub fn with_ferries(mut self) -> Self {
+        self.ferries = true;
+        self
+    }
+
+    /// Treats nodes with one of the `barrier` values (e.g. `["wall", "fence"]`) as impassable:
+    /// the edges touching them are left out, so ways are cut at the barrier.
+    /// By default no barrier blocks, since most (gates, bollards) only block some vehicles.
+    pub fn with_blocking_barriers<S: Into<String>>(
+        mut self,
+        barriers: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.barriers = barriers.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Reads the routable ways and their nodes and builds the graph.
+    /// The reader is read twice (the second pass opens the file again),
+    /// so it needs to be free of element and tag filters, which the passes apply themselves.
+    /// Returns an error if a filter was already applied or a pass fails
+    /// (see [`ElementBlockIter::finish`](crate::ElementBlockIter::finish)).
+    pub fn build(&self, reader: OsmReader) -> std::io::Result<RoutingGraph> {
+        let nodes_reader = reader.reopen()?;
+        let ways = self.routable_ways(reader)?;
+
+        // nodes where ways end or that are shared by several ways split edges
+        let mut uses: HashMap<i64, u32> = HashMap::new();
+        for way in &ways {
+            for &id in &way.node_ids {
+                *uses.entry(id).or_default() += 1;
+            }
+        }
+        let mut splits: HashSet<i64> = uses
+            .iter()
+            .filter(|&(_, &count)| count > 1)
+            .map(|(&id, _)| id)
+            .collect();
+        for way in &ways {
+            splits.extend(way.node_ids.first());
+            splits.extend(way.node_ids.last());
+        }
+
+        let (coords, barriers) = self.node_locations(nodes_reader, &uses)?;
+        splits.extend(&barriers);
+        drop(uses);
+
+        let mut node_ids: Vec<i64> = splits
+            .into_iter()
+            .filter(|id| coords.contains_key(id) && !barriers.contains(id))
+            .collect();
+        node_ids.sort_unstable();
+        let index_of = |id: i64| node_ids.binary_search(&id).ok().map(|index| index as u32);
+
+        let mut edges = Vec::new();
+        for way in &ways {
+            let mut start = 0;
+            let mut length = 0.0;
+            for i in 1..way.node_ids.len() {
+                let (prev, id) = (way.node_ids[i - 1], way.node_ids[i]);
+                match (coords.get(&prev), coords.get(&id)) {
+                    (Some(&a), Some(&b)) => length += haversine(a, b),
+                    // the segment is unknown, skip ahead to the next graph node
+                    _ => length = f64::NAN,
+                }
+                let Some(to) = index_of(id) else {
+                    // a missing node already made the length NaN
+                    if barriers.contains(&id) {
+                        length = f64::NAN;
+                    }
+                    continue;
+                };
+                if let Some(from) = index_of(way.node_ids[start]).filter(|_| !length.is_nan()) {
+                    let edge = RoutingEdge {
+                        from,
+                        to,
+                        way_id: way.id,
+                        length,
+                        ferry: way.ferry,
+                    };
+                    if way.direction != Direction::Backward {
+                        edges.push(edge);
+                    }
+                    if way.direction != Direction::Forward {
+                        edges.push(RoutingEdge {
+                            from: to,
+                            to: from,
+                            ..edge
+                        });
+                    }
+                }
+                start = i;
+                length = 0.0;
+            }
+        }
+        edges.sort_unstable_by_key(|edge| (edge.from, edge.to, edge.way_id));
+
+        let mut offsets = Vec::with_capacity(node_ids.len() + 1);
+        let mut starts = edges.iter().map(|edge| edge.from).peekable();
+        let mut offset = 0;
+        for index in 0..node_ids.len() as u32 {
+            offsets.push(offset);
+            while starts.next_if_eq(&index).is_some() {
+                offset += 1;
+            }
+        }
+        offsets.push(offset);
+
+        let coords = node_ids.iter().map(|id| coords[id]).collect();
+        Ok(RoutingGraph {
+            node_ids,
+            coords,
+            offsets,
+            edges,
+        })
+    }
+
+    // First pass: the routable ways, other ways are dropped on the worker threads
+    fn routable_ways(&self, reader: OsmReader) -> std::io::Result<Vec<RoutableWay>> {
+        let config = Arc::new(self.clone());
+        let predicate_config = Arc::clone(&config);
+        let reader = reader
+            .with_element_filter(ElementFilter {
+                nodes: false,
+                ways: true,
+                relations: false,
+            })
+            .and_then(|reader| reader.with_tag_filter_mode(&WAY_KEYS, FilterMode::Any))
+            .map_err(|err| std::io::Error::new(ErrorKind::InvalidInput, err))?
+            .with_predicate(move |element| match element {
+                ElementRef::Way(way) => predicate_config.is_routable(way.tags()).is_some(),
+                _ => false,
+            });
+
+        let mut ways = Vec::new();
+        let mut blocks = reader.blocks();
+        for block in blocks.by_ref() {
+            if let ElementBlock::WayBlock(block) = block {
+                for way in block.iter() {
+                    let Some(ferry) = config.is_routable(way.tags()) else {
+                        continue;
+                    };
+                    ways.push(RoutableWay {
+                        id: way.id(),
+                        node_ids: way.node_ids_vec(),
+                        direction: Direction::of(way.tags()),
+                        ferry,
+                    });
+                }
+            }
+        }
+        blocks.finish()?;
+        Ok(ways)
+    }
+
+    // Second pass: coordinates of the nodes of routable ways and which of them are blocking barriers
+    fn node_locations(
+        &self,
+        reader: OsmReader,
+        uses: &HashMap<i64, u32>,
+    ) -> std::io::Result<(Coords, HashSet<i64>)> {
+        let reader = reader
+            .with_element_filter(ElementFilter {
+                nodes: true,
+                ways: false,
+                relations: false,
+            })
+            .and_then(|reader| reader.with_tag_filter(&["barrier"]))
+            .map_err(|err| std::io::Error::new(ErrorKind::InvalidInput, err))?;
+
+        let mut coords = HashMap::with_capacity(uses.len());
+        let mut barriers = HashSet::new();
+        let mut add = |id: i64, lat: f64, lon: f64, barrier: Option<&str>| {
+            coords.insert(id, (lat, lon));
+            if barrier.is_some_and(|value| self.barriers.contains(value)) {
+                barriers.insert(id);
+            }
+        };
+        let mut blocks = reader.blocks();
+        for block in blocks.by_ref() {
+            match block {
+                ElementBlock::DenseNodeBlock(block) => {
+                    for mut node in block.iter() {
+                        let id = node.id();
+                        let (lat, lon) = (node.lat(), node.lon());
+                        if uses.contains_key(&id) {
+                            add(id, lat, lon, node.tags().next().map(|(_, value)| value));
+                        }
+                    }
+                }
+                ElementBlock::NodeBlock(block) => {
+                    for node in block.iter().filter(|node| uses.contains_key(&node.id())) {
+                        let barrier = node.tags().next().map(|(_, value)| value);
+                        add(node.id(), node.lat(), node.lon(), barrier);
+                    }
+                }
+                _ => (),
+            }
+        }
+        blocks.finish()?;
+        Ok((coords, barriers))
+    }
+
+    // Whether a way with these tags is routable, and if so whether it is a ferry route
+    fn is_routable<'a>(&self, tags: impl Iterator<Item = (&'a str, &'a str)>) -> Option<bool> {
+        let mut ferry = false;
+        for (key, value) in tags {
+            match key {
+                "highway" if self.highways.contains(value) => return Some(false),
+                "route" if value == "ferry" => ferry = self.ferries,
+                _ => (),
+            }
+        }
+        ferry.then_some(true)
+    }
+}
+
+struct RoutableWay {
+    id: i64,
+    node_ids: Vec<i64>,
+    direction: Direction,
+    ferry: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Both,
+    Forward,
+    Backward,
+}
+
+impl Direction {
+    fn of<'a>(tags: impl Iterator<Item = (&'a str, &'a str)>) -> Self {
+        let mut implied_oneway = false;
+        let mut oneway = None;
+        for (key, value) in tags {
+            match (key, value) {
+                ("oneway", "yes" | "true" | "1") => oneway = Some(Self::Forward),
+                ("oneway", "-1" | "reverse") => oneway = Some(Self::Backward),
+                ("oneway", "no" | "false" | "0") => oneway = Some(Self::Both),
+                ("junction", "roundabout" | "circular") | ("highway", "motorway") => {
+                    implied_oneway = true
+                }
+                _ => (),
+            }
+        }
+        oneway.unwrap_or(if implied_oneway {
+            Self::Forward
+        } else {
+            Self::Both
+        })
+    }
+}
+
+// Great circle distance of two (lat, lon) coordinates in meters
+fn haversine((lat1, lon1): (f64, f64), (lat2, lon2): (f64, f64)) -> f64 {
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let half_dlat = (lat2 - lat1) / 2.0;
+    let half_dlon = (lon2 - lon1).to_radians() / 2.0;
+    let a = half_dlat.sin().powi(2) + lat1.cos() * lat2.cos() * half_dlon.sin().powi(2);
+    2.0 * EARTH_RADIUS * a.sqrt().asin()
+}
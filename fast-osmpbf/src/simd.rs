@@ -0,0 +1,401 @@
+// Bulk kernels for delta encoded columns and tag filters.
+// Every public function dispatches at runtime to an AVX2 kernel if the CPU supports it
+// and falls back to a scalar loop otherwise. Both paths produce bit-identical results.
+
+use alloc::vec::Vec;
+
+// Without `std` there is no runtime detection, so AVX2 is only used if it is enabled at compile time
+#[cfg(target_arch = "x86_64")]
+#[inline]
+fn has_avx2() -> bool {
+    #[cfg(feature = "std")]
+    let detected = std::is_x86_feature_detected!("avx2");
+    #[cfg(not(feature = "std"))]
+    let detected = cfg!(target_feature = "avx2");
+    detected
+}
+
+/// Delta decodes `deltas` into absolute values (running prefix sum).
+pub fn delta_decode_i64(deltas: &[i64]) -> Vec<i64> {
+    #[cfg(target_arch = "x86_64")]
+    if has_avx2() {
+        // SAFETY: AVX2 support was checked above
+        return unsafe { avx2::delta_decode_i64(deltas) };
+    }
+    scalar::delta_decode_i64(deltas)
+}
+
+/// Delta decodes coordinates and converts them to degrees in one pass.
+/// Every value is computed as `(sum(deltas) * granularity + offset) * 1e-9`,
+/// which is exactly what [`DenseNodeRef::lat`](crate::DenseNodeRef::lat) does per node.
+pub fn decode_coords(deltas: &[i64], granularity: i64, offset: i64) -> Vec<f64> {
+    #[cfg(target_arch = "x86_64")]
+    if has_avx2() {
+        // SAFETY: AVX2 support was checked above
+        return unsafe { avx2::decode_coords(deltas, granularity, offset) };
+    }
+    scalar::decode_coords(deltas, granularity, offset)
+}
+
+/// Appends the (key, value) pairs whose key id is one of `filter` to `out_keys` and `out_vals`,
+/// e.g. to apply a tag filter to the tags of an element in bulk. `keys` and `vals` are zipped,
+/// so the longer one is cut to the length of the shorter one. Meant for small filters (a few keys),
+/// every key is compared against every filter id.
+pub fn retain_tags(
+    keys: &[u32],
+    vals: &[u32],
+    filter: &[u32],
+    out_keys: &mut Vec<u32>,
+    out_vals: &mut Vec<u32>,
+) {
+    #[cfg(target_arch = "x86_64")]
+    if has_avx2() {
+        // SAFETY: AVX2 support was checked above
+        return unsafe { avx2::retain_tags(keys, vals, filter, out_keys, out_vals) };
+    }
+    scalar::retain_tags(keys, vals, filter, out_keys, out_vals)
+}
+
+/// Same as [`retain_tags`] for interleaved `key, value, key, value, ...` ids as stored by
+/// [`DenseNodes`](crate::DenseNodes), a trailing odd id is ignored.
+pub fn retain_tags_interleaved(
+    keys_vals: &[i32],
+    filter: &[u32],
+    out_keys: &mut Vec<u32>,
+    out_vals: &mut Vec<u32>,
+) {
+    #[cfg(target_arch = "x86_64")]
+    if has_avx2() {
+        // SAFETY: AVX2 support was checked above
+        return unsafe { avx2::retain_tags_interleaved(keys_vals, filter, out_keys, out_vals) };
+    }
+    scalar::retain_tags_interleaved(keys_vals, filter, out_keys, out_vals)
+}
+
+mod scalar {
+    use alloc::vec::Vec;
+
+    pub(super) fn delta_decode_i64(deltas: &[i64]) -> Vec<i64> {
+        let mut acc = 0i64;
+        deltas
+            .iter()
+            .map(|delta| {
+                acc += delta;
+                acc
+            })
+            .collect()
+    }
+
+    pub(super) fn decode_coords(deltas: &[i64], granularity: i64, offset: i64) -> Vec<f64> {
+        let mut acc = 0i64;
+        deltas
+            .iter()
+            .map(|delta| {
+                acc += delta;
+                ((acc * granularity + offset) as f64) * 1e-9
+            })
+            .collect()
+    }
+
+    pub(super) fn retain_tags(
+        keys: &[u32],
+        vals: &[u32],
+        filter: &[u32],
+        out_keys: &mut Vec<u32>,
+        out_vals: &mut Vec<u32>,
+    ) {
+        for (&key, &val) in keys.iter().zip(vals) {
+            if filter.contains(&key) {
+                out_keys.push(key);
+                out_vals.push(val);
+            }
+        }
+    }
+
+    pub(super) fn retain_tags_interleaved(
+        keys_vals: &[i32],
+        filter: &[u32],
+        out_keys: &mut Vec<u32>,
+        out_vals: &mut Vec<u32>,
+    ) {
+        for pair in keys_vals.chunks_exact(2) {
+            let key = pair[0] as u32;
+            if filter.contains(&key) {
+                out_keys.push(key);
+                out_vals.push(pair[1] as u32);
+            }
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    use alloc::{vec, vec::Vec};
+    use core::arch::x86_64::*;
+
+    // Bitmask of the 8 lanes of v that equal one of the filter ids
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    fn filter_mask(v: __m256i, filter: &[u32]) -> u32 {
+        let mut hits = _mm256_setzero_si256();
+        for &id in filter {
+            hits = _mm256_or_si256(hits, _mm256_cmpeq_epi32(v, _mm256_set1_epi32(id as i32)));
+        }
+        _mm256_movemask_ps(_mm256_castsi256_ps(hits)) as u32
+    }
+
+    // Bit pattern of 2^52 + 2^51 as f64. Adding an integer |x| < 2^51 to these bits and
+    // subtracting the float again converts i64 -> f64 exactly (AVX2 has no native conversion).
+    const MAGIC_BITS: i64 = 0x4338_0000_0000_0000;
+    const MAGIC: f64 = 6_755_399_441_055_744.0;
+
+    // Inclusive prefix sum over the 4 lanes of v, plus the running total of previous chunks
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    fn prefix_sum(v: __m256i, carry: __m256i) -> __m256i {
+        let zero = _mm256_setzero_si256();
+        // [a, b, c, d] + [0, a, b, c]
+        let shifted =
+            _mm256_blend_epi32::<0b0000_0011>(_mm256_permute4x64_epi64::<0b10_01_00_00>(v), zero);
+        let v = _mm256_add_epi64(v, shifted);
+        // [a, ab, bc, cd] + [0, 0, a, ab]
+        let shifted =
+            _mm256_blend_epi32::<0b0000_1111>(_mm256_permute4x64_epi64::<0b01_00_00_00>(v), zero);
+        let v = _mm256_add_epi64(v, shifted);
+        _mm256_add_epi64(v, carry)
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) fn delta_decode_i64(deltas: &[i64]) -> Vec<i64> {
+        let mut out = vec![0i64; deltas.len()];
+        let mut carry = _mm256_setzero_si256();
+
+        let mut chunks = deltas.chunks_exact(4);
+        let mut out_chunks = out.chunks_exact_mut(4);
+        for (chunk, out_chunk) in (&mut chunks).zip(&mut out_chunks) {
+            // SAFETY: both chunks hold exactly 4 i64 values, unaligned load/store is used
+            let v = unsafe { _mm256_loadu_si256(chunk.as_ptr() as *const __m256i) };
+            let v = prefix_sum(v, carry);
+            carry = _mm256_permute4x64_epi64::<0b11_11_11_11>(v);
+            unsafe { _mm256_storeu_si256(out_chunk.as_mut_ptr() as *mut __m256i, v) };
+        }
+
+        let mut acc = _mm256_extract_epi64::<3>(carry);
+        for (delta, out) in chunks.remainder().iter().zip(out_chunks.into_remainder()) {
+            acc += delta;
+            *out = acc;
+        }
+        out
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) fn decode_coords(deltas: &[i64], granularity: i64, offset: i64) -> Vec<f64> {
+        let mut out = vec![0f64; deltas.len()];
+        let mut carry = _mm256_setzero_si256();
+
+        let magic_bits = _mm256_set1_epi64x(MAGIC_BITS);
+        let magic = _mm256_set1_pd(MAGIC);
+        let granularity_pd = _mm256_set1_pd(granularity as f64);
+        let offset_pd = _mm256_set1_pd(offset as f64);
+        let scale = _mm256_set1_pd(1e-9);
+
+        let mut chunks = deltas.chunks_exact(4);
+        let mut out_chunks = out.chunks_exact_mut(4);
+        for (chunk, out_chunk) in (&mut chunks).zip(&mut out_chunks) {
+            // SAFETY: both chunks hold exactly 4 values, unaligned load/store is used
+            let v = unsafe { _mm256_loadu_si256(chunk.as_ptr() as *const __m256i) };
+            let v = prefix_sum(v, carry);
+            carry = _mm256_permute4x64_epi64::<0b11_11_11_11>(v);
+
+            // acc * granularity + offset stays far below 2^53 for valid coordinates, so doing
+            // the affine transform in f64 is exact and matches the scalar integer math
+            let acc = _mm256_sub_pd(_mm256_castsi256_pd(_mm256_add_epi64(v, magic_bits)), magic);
+            let nano = _mm256_add_pd(_mm256_mul_pd(acc, granularity_pd), offset_pd);
+            let degrees = _mm256_mul_pd(nano, scale);
+            unsafe { _mm256_storeu_pd(out_chunk.as_mut_ptr(), degrees) };
+        }
+
+        let mut acc = _mm256_extract_epi64::<3>(carry);
+        for (delta, out) in chunks.remainder().iter().zip(out_chunks.into_remainder()) {
+            acc += delta;
+            *out = ((acc * granularity + offset) as f64) * 1e-9;
+        }
+        out
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) fn retain_tags(
+        keys: &[u32],
+        vals: &[u32],
+        filter: &[u32],
+        out_keys: &mut Vec<u32>,
+        out_vals: &mut Vec<u32>,
+    ) {
+        let len = keys.len().min(vals.len());
+        let (keys, vals) = (&keys[..len], &vals[..len]);
+
+        let mut chunks = keys.chunks_exact(8);
+        let mut start = 0;
+        for chunk in &mut chunks {
+            // SAFETY: the chunk holds exactly 8 u32 values, unaligned load is used
+            let v = unsafe { _mm256_loadu_si256(chunk.as_ptr() as *const __m256i) };
+            let mut mask = filter_mask(v, filter);
+            // most tags do not pass a filter, so most masks are empty
+            while mask != 0 {
+                let i = start + mask.trailing_zeros() as usize;
+                out_keys.push(keys[i]);
+                out_vals.push(vals[i]);
+                mask &= mask - 1;
+            }
+            start += 8;
+        }
+        super::scalar::retain_tags(&keys[start..], &vals[start..], filter, out_keys, out_vals);
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) fn retain_tags_interleaved(
+        keys_vals: &[i32],
+        filter: &[u32],
+        out_keys: &mut Vec<u32>,
+        out_vals: &mut Vec<u32>,
+    ) {
+        let mut chunks = keys_vals.chunks_exact(8);
+        let mut start = 0;
+        for chunk in &mut chunks {
+            // SAFETY: the chunk holds exactly 8 i32 values, unaligned load is used
+            let v = unsafe { _mm256_loadu_si256(chunk.as_ptr() as *const __m256i) };
+            // only the even lanes hold keys
+            let mut mask = filter_mask(v, filter) & 0b0101_0101;
+            while mask != 0 {
+                let i = start + mask.trailing_zeros() as usize;
+                out_keys.push(keys_vals[i] as u32);
+                out_vals.push(keys_vals[i + 1] as u32);
+                mask &= mask - 1;
+            }
+            start += 8;
+        }
+        let rest = &keys_vals[start..];
+        super::scalar::retain_tags_interleaved(rest, filter, out_keys, out_vals);
+    }
+}
+
+#[cfg(all(test, target_arch = "x86_64"))]
+mod tests {
+    use super::*;
+
+    // xorshift64*, enough to vary the inputs without a dev-dependency
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+        }
+
+        // uniform in [-range, range]
+        fn signed(&mut self, range: i64) -> i64 {
+            (self.next() % (2 * range as u64 + 1)) as i64 - range
+        }
+
+        fn deltas(&mut self, len: usize, range: i64) -> Vec<i64> {
+            (0..len).map(|_| self.signed(range)).collect()
+        }
+    }
+
+    #[test]
+    fn avx2_delta_decode_matches_scalar() {
+        if !has_avx2() {
+            return;
+        }
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for len in 0..=17 {
+            for range in [1, 1 << 20, 1 << 40] {
+                let deltas = rng.deltas(len, range);
+                let simd = unsafe { avx2::delta_decode_i64(&deltas) };
+                assert_eq!(simd, scalar::delta_decode_i64(&deltas), "deltas {deltas:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn avx2_decode_coords_matches_scalar() {
+        if !has_avx2() {
+            return;
+        }
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        let params = [
+            (100, 0),
+            (1, 0),
+            (1000, 0),
+            (100, 5_000_000_000),
+            (100, -5_000_000_000),
+            (1000, -123_456_789),
+        ];
+        for len in 0..=17 {
+            for (granularity, offset) in params {
+                // first delta anywhere on the globe, the rest small and of both signs
+                let mut deltas = rng.deltas(len, 10_000);
+                if let Some(first) = deltas.first_mut() {
+                    *first = rng.signed(1_800_000_000 / granularity);
+                }
+                let simd = unsafe { avx2::decode_coords(&deltas, granularity, offset) };
+                let expected = scalar::decode_coords(&deltas, granularity, offset);
+                let bits = |v: &[f64]| v.iter().map(|x| x.to_bits()).collect::<Vec<_>>();
+                assert_eq!(bits(&simd), bits(&expected), "deltas {deltas:?}");
+            }
+        }
+    }
+
+    type RetainTags = unsafe fn(&[u32], &[u32], &[u32], &mut Vec<u32>, &mut Vec<u32>);
+    type RetainTagsInterleaved = unsafe fn(&[i32], &[u32], &mut Vec<u32>, &mut Vec<u32>);
+
+    fn retain(kernel: RetainTags, keys: &[u32], vals: &[u32], filter: &[u32]) -> [Vec<u32>; 2] {
+        let (mut out_keys, mut out_vals) = (Vec::new(), Vec::new());
+        unsafe { kernel(keys, vals, filter, &mut out_keys, &mut out_vals) };
+        [out_keys, out_vals]
+    }
+
+    fn retain_interleaved(
+        kernel: RetainTagsInterleaved,
+        keys_vals: &[i32],
+        filter: &[u32],
+    ) -> [Vec<u32>; 2] {
+        let (mut out_keys, mut out_vals) = (Vec::new(), Vec::new());
+        unsafe { kernel(keys_vals, filter, &mut out_keys, &mut out_vals) };
+        [out_keys, out_vals]
+    }
+
+    #[test]
+    fn avx2_retain_tags_matches_scalar() {
+        if !has_avx2() {
+            return;
+        }
+        let mut rng = Rng(0xd1b5_4a32_d192_ed03);
+        let filters: [&[u32]; 4] = [&[], &[3], &[1, 4, 7], &[1, 2, 3, 4, 5, 6, 7, 8]];
+        for len in 0..=17 {
+            // ids of 0 to 11, so some keys hit the filters and some do not
+            let keys: Vec<u32> = (0..len).map(|_| (rng.next() % 12) as u32).collect();
+            let vals: Vec<u32> = (0..len).map(|_| rng.next() as u32).collect();
+            // an odd length leaves a trailing key without value
+            let keys_vals: Vec<i32> = (0..len).map(|_| (rng.next() % 12) as i32).collect();
+            for filter in filters {
+                let expected = retain(scalar::retain_tags, &keys, &vals, filter);
+                let simd = retain(avx2::retain_tags, &keys, &vals, filter);
+                assert_eq!(simd, expected, "keys {keys:?}, filter {filter:?}");
+
+                // keys and vals of different length are cut to the shorter one
+                let vals = &vals[..len / 2];
+                let expected = retain(scalar::retain_tags, &keys, vals, filter);
+                let simd = retain(avx2::retain_tags, &keys, vals, filter);
+                assert_eq!(simd, expected, "keys {keys:?}, filter {filter:?}");
+
+                let expected =
+                    retain_interleaved(scalar::retain_tags_interleaved, &keys_vals, filter);
+                let simd = retain_interleaved(avx2::retain_tags_interleaved, &keys_vals, filter);
+                assert_eq!(simd, expected, "keys_vals {keys_vals:?}, filter {filter:?}");
+            }
+        }
+    }
+}
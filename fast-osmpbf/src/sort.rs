@@ -0,0 +1,294 @@
+use crate::{
+    Element, ElementId, MemberType, OwnedNode, OwnedRelation, OwnedRelationMember, OwnedWay,
+};
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    fs::{File, OpenOptions},
+    io::{BufReader, BufWriter, ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Sorts owned elements by (element type, id), nodes first, then ways, then relations,
+/// which is the order of files with `Sort.Type_then_ID`.
+/// At most `max_in_memory` elements are buffered, every full buffer is sorted and spilled
+/// into a temporary file in [`std::env::temp_dir`], and the files are merged while iterating
+/// (external merge sort). If the input fits into the buffer, nothing touches the disk.
+///
+/// The sort is stable, entries with the same id keep their input order.
+/// The temporary files are removed when the iterator is dropped.
+pub fn sort_elements<I>(
+    elements: I,
+    max_in_memory: usize,
+) -> std::io::Result<impl Iterator<Item = std::io::Result<Element>>>
+where
+    I: IntoIterator<Item = Element>,
+{
+    sort_elements_in(elements, max_in_memory, std::env::temp_dir())
+}
+
+/// Same as [`sort_elements`], but spills into the directory `dir`, e.g. a disk with enough space
+/// for planet-scale data.
+pub fn sort_elements_in<I, P>(
+    elements: I,
+    max_in_memory: usize,
+    dir: P,
+) -> std::io::Result<impl Iterator<Item = std::io::Result<Element>>>
+where
+    I: IntoIterator<Item = Element>,
+    P: AsRef<Path>,
+{
+    let max_in_memory = max_in_memory.max(1);
+    let mut spill_files = SpillFiles(Vec::new());
+    let mut runs = Vec::new();
+    let mut buffer = Vec::new();
+    for element in elements {
+        buffer.push(element);
+        if buffer.len() == max_in_memory {
+            buffer.sort_by_key(sort_key);
+            let path = spill_files.create(dir.as_ref(), &buffer)?;
+            runs.push(Run::File(BufReader::new(File::open(path)?)));
+            buffer.clear();
+        }
+    }
+    // the last buffer is merged straight from memory
+    buffer.sort_by_key(sort_key);
+    runs.push(Run::Memory(buffer.into_iter()));
+
+    let mut sorted = SortedElements {
+        heads: Vec::with_capacity(runs.len()),
+        heap: BinaryHeap::with_capacity(runs.len()),
+        runs,
+        failed: false,
+        _spill_files: spill_files,
+    };
+    for run in 0..sorted.runs.len() {
+        let head = sorted.runs[run].next()?;
+        sorted.push_head(run, head);
+    }
+    Ok(sorted)
+}
+
+// element type as number so it can be ordered
+fn sort_key(element: &Element) -> (i32, i64) {
+    let (member_type, id) = element.element_id();
+    (member_type as i32, id)
+}
+
+// Removes the temporary files once the merge is done or dropped
+struct SpillFiles(Vec<PathBuf>);
+
+// distinguishes the files of concurrent sorts within one process
+static SORT_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+impl SpillFiles {
+    // Writes a sorted run into a new temporary file
+    fn create(&mut self, dir: &Path, elements: &[Element]) -> std::io::Result<&Path> {
+        let path = dir.join(format!(
+            "fast-osmpbf-sort-{}-{}.tmp",
+            std::process::id(),
+            SORT_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        self.0.push(path);
+        let mut writer = BufWriter::new(file);
+        for element in elements {
+            write_element(&mut writer, element)?;
+        }
+        writer.flush()?;
+        Ok(self.0.last().unwrap())
+    }
+}
+
+impl Drop for SpillFiles {
+    fn drop(&mut self) {
+        for path in &self.0 {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+// A sorted sequence of elements, either spilled to disk or the last buffer
+enum Run {
+    File(BufReader<File>),
+    Memory(std::vec::IntoIter<Element>),
+}
+
+impl Run {
+    fn next(&mut self) -> std::io::Result<Option<Element>> {
+        match self {
+            Run::File(reader) => read_element(reader),
+            Run::Memory(elements) => Ok(elements.next()),
+        }
+    }
+}
+
+struct SortedElements {
+    runs: Vec<Run>,
+    // the next element of every run
+    heads: Vec<Option<Element>>,
+    // (sort key, run) of all heads, the run breaks ties so the sort stays stable
+    heap: BinaryHeap<Reverse<((i32, i64), usize)>>,
+    failed: bool,
+    _spill_files: SpillFiles,
+}
+
+impl SortedElements {
+    fn push_head(&mut self, run: usize, head: Option<Element>) {
+        if let Some(element) = &head {
+            self.heap.push(Reverse((sort_key(element), run)));
+        }
+        if run == self.heads.len() {
+            self.heads.push(head);
+        } else {
+            self.heads[run] = head;
+        }
+    }
+}
+
+impl Iterator for SortedElements {
+    type Item = std::io::Result<Element>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let Reverse((_, run)) = self.heap.pop()?;
+        let element = self.heads[run].take()?;
+        match self.runs[run].next() {
+            Ok(head) => self.push_head(run, head),
+            Err(err) => {
+                // stop after the first error, elements of the broken run would be missing
+                self.failed = true;
+                return Some(Err(err));
+            }
+        }
+        Some(Ok(element))
+    }
+}
+
+// Spill format per element: type (u8), id, then the fields of the type, then the tags.
+// Numbers are little endian, strings and lists are prefixed with their length as u32.
+fn write_element(writer: &mut impl Write, element: &Element) -> std::io::Result<()> {
+    let (member_type, id) = element.element_id();
+    writer.write_all(&[member_type as u8])?;
+    writer.write_all(&id.to_le_bytes())?;
+    let tags = match element {
+        Element::Node(node) => {
+            writer.write_all(&node.lat.to_le_bytes())?;
+            writer.write_all(&node.lon.to_le_bytes())?;
+            &node.tags
+        }
+        Element::Way(way) => {
+            write_len(writer, way.node_ids.len())?;
+            for node_id in &way.node_ids {
+                writer.write_all(&node_id.to_le_bytes())?;
+            }
+            &way.tags
+        }
+        Element::Relation(relation) => {
+            write_len(writer, relation.members.len())?;
+            for member in &relation.members {
+                writer.write_all(&member.id.to_le_bytes())?;
+                writer.write_all(&[member.member_type as u8])?;
+                write_str(writer, &member.role)?;
+            }
+            &relation.tags
+        }
+    };
+    write_len(writer, tags.len())?;
+    for (key, value) in tags {
+        write_str(writer, key)?;
+        write_str(writer, value)?;
+    }
+    Ok(())
+}
+
+fn write_len(writer: &mut impl Write, len: usize) -> std::io::Result<()> {
+    let len = u32::try_from(len).map_err(|_| {
+        std::io::Error::new(ErrorKind::InvalidInput, "Element too large to be sorted")
+    })?;
+    writer.write_all(&len.to_le_bytes())
+}
+
+fn write_str(writer: &mut impl Write, s: &str) -> std::io::Result<()> {
+    write_len(writer, s.len())?;
+    writer.write_all(s.as_bytes())
+}
+
+// Reads the next element of a spilled run, None at the end of the file
+fn read_element(reader: &mut impl Read) -> std::io::Result<Option<Element>> {
+    let mut member_type = [0u8];
+    if reader.read(&mut member_type)? == 0 {
+        return Ok(None);
+    }
+    let id = read_i64(reader)?;
+    let element = match MemberType::from(member_type[0] as i32) {
+        MemberType::NODE => Element::Node(OwnedNode {
+            id,
+            lat: f64::from_le_bytes(read_array(reader)?),
+            lon: f64::from_le_bytes(read_array(reader)?),
+            tags: read_tags(reader)?,
+        }),
+        MemberType::WAY => {
+            let len = read_len(reader)?;
+            let node_ids = (0..len)
+                .map(|_| read_i64(reader))
+                .collect::<std::io::Result<_>>()?;
+            Element::Way(OwnedWay {
+                id,
+                node_ids,
+                tags: read_tags(reader)?,
+            })
+        }
+        MemberType::RELATION => {
+            let len = read_len(reader)?;
+            let members = (0..len)
+                .map(|_| {
+                    Ok(OwnedRelationMember {
+                        id: read_i64(reader)?,
+                        member_type: MemberType::from(read_array::<1>(reader)?[0] as i32),
+                        role: read_string(reader)?,
+                    })
+                })
+                .collect::<std::io::Result<_>>()?;
+            Element::Relation(OwnedRelation {
+                id,
+                members,
+                tags: read_tags(reader)?,
+            })
+        }
+    };
+    Ok(Some(element))
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> std::io::Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_i64(reader: &mut impl Read) -> std::io::Result<i64> {
+    Ok(i64::from_le_bytes(read_array(reader)?))
+}
+
+fn read_len(reader: &mut impl Read) -> std::io::Result<usize> {
+    Ok(u32::from_le_bytes(read_array(reader)?) as usize)
+}
+
+fn read_string(reader: &mut impl Read) -> std::io::Result<String> {
+    let mut bytes = vec![0u8; read_len(reader)?];
+    reader.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e))
+}
+
+fn read_tags(reader: &mut impl Read) -> std::io::Result<Vec<(String, String)>> {
+    let len = read_len(reader)?;
+    (0..len)
+        .map(|_| Ok((read_string(reader)?, read_string(reader)?)))
+        .collect()
+}
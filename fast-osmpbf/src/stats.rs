@@ -0,0 +1,129 @@
+use crate::{BlobStats, Compression};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
+    time::{Duration, Instant},
+};
+
+// Codecs in the order of the counters of StatsCollector
+const CODECS: [Compression; 6] = [
+    Compression::Raw,
+    Compression::Zlib,
+    Compression::Lzma,
+    Compression::Lz4,
+    Compression::Zstd,
+    Compression::Bzip2,
+];
+
+/// Counters and timings of one run of [`OsmReader::blocks`](crate::OsmReader::blocks) (or
+/// [`OsmReader::map_blocks`](crate::OsmReader::map_blocks)), see [`ElementBlockIter::stats`](crate::ElementBlockIter::stats).
+///
+/// The times of the workers are summed over all worker threads, so on a busy pool they add up to
+/// about `elapsed` times the number of threads. If `read_time` is close to `elapsed` the run is
+/// I/O bound, if the worker times come close to their maximum it is CPU bound.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParseStats {
+    /// Number of OSMData blobs decompressed
+    pub blobs: u64,
+    /// Stored (compressed) size of these blobs
+    pub compressed_bytes: u64,
+    /// Size of these blobs after decompression
+    pub decompressed_bytes: u64,
+    /// Number of decompressed blobs per codec, codecs that did not occur are left out
+    pub blobs_per_codec: HashMap<Compression, u64>,
+    /// Time the reader thread spent reading blob headers and blobs, without waiting for free slots
+    /// of the read-ahead
+    pub read_time: Duration,
+    /// Time the workers spent decompressing, summed over all threads
+    pub decompress_time: Duration,
+    /// Time the workers spent parsing and filtering the decompressed blobs, summed over all threads
+    pub parse_time: Duration,
+    /// Wall-clock time from the call of `blocks` until the last blob was parsed,
+    /// or until now while blobs are still parsed
+    pub elapsed: Duration,
+}
+
+// Shared by the reader thread, the workers and the iterator, every update is a relaxed atomic add
+#[derive(Debug)]
+pub(crate) struct StatsCollector {
+    started: Instant,
+    finished: OnceLock<Duration>,
+    blobs: AtomicU64,
+    compressed_bytes: AtomicU64,
+    decompressed_bytes: AtomicU64,
+    blobs_per_codec: [AtomicU64; CODECS.len()],
+    read_nanos: AtomicU64,
+    decompress_nanos: AtomicU64,
+    parse_nanos: AtomicU64,
+}
+
+impl StatsCollector {
+    pub(crate) fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            finished: OnceLock::new(),
+            blobs: AtomicU64::new(0),
+            compressed_bytes: AtomicU64::new(0),
+            decompressed_bytes: AtomicU64::new(0),
+            blobs_per_codec: Default::default(),
+            read_nanos: AtomicU64::new(0),
+            decompress_nanos: AtomicU64::new(0),
+            parse_nanos: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn record_read(&self, time: Duration) {
+        add_time(&self.read_nanos, time);
+    }
+
+    pub(crate) fn record_blob(&self, stats: &BlobStats, time: Duration) {
+        self.blobs.fetch_add(1, Ordering::Relaxed);
+        self.compressed_bytes
+            .fetch_add(stats.compressed_len as u64, Ordering::Relaxed);
+        self.decompressed_bytes
+            .fetch_add(stats.decompressed_len as u64, Ordering::Relaxed);
+        if let Some(i) = CODECS.iter().position(|&c| c == stats.compression) {
+            self.blobs_per_codec[i].fetch_add(1, Ordering::Relaxed);
+        }
+        add_time(&self.decompress_nanos, time);
+    }
+
+    pub(crate) fn record_parse(&self, time: Duration) {
+        add_time(&self.parse_nanos, time);
+    }
+
+    // Stops the clock once all blobs are parsed, later calls keep the first time
+    pub(crate) fn finish(&self) {
+        let _ = self.finished.set(self.started.elapsed());
+    }
+
+    pub(crate) fn snapshot(&self) -> ParseStats {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        ParseStats {
+            blobs: load(&self.blobs),
+            compressed_bytes: load(&self.compressed_bytes),
+            decompressed_bytes: load(&self.decompressed_bytes),
+            blobs_per_codec: CODECS
+                .into_iter()
+                .zip(&self.blobs_per_codec)
+                .map(|(codec, count)| (codec, load(count)))
+                .filter(|&(_, count)| count > 0)
+                .collect(),
+            read_time: Duration::from_nanos(load(&self.read_nanos)),
+            decompress_time: Duration::from_nanos(load(&self.decompress_nanos)),
+            parse_time: Duration::from_nanos(load(&self.parse_nanos)),
+            elapsed: self
+                .finished
+                .get()
+                .copied()
+                .unwrap_or_else(|| self.started.elapsed()),
+        }
+    }
+}
+
+fn add_time(counter: &AtomicU64, time: Duration) {
+    counter.fetch_add(time.as_nanos() as u64, Ordering::Relaxed);
+}
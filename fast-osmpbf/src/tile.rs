@@ -0,0 +1,127 @@
+use crate::{BBox, Element, ElementBlock, ElementFilter, MemberType, OsmReader};
+use std::{collections::HashSet, f64::consts::PI, io::ErrorKind};
+
+/// Converts the web mercator tile `z/x/y` (as used by slippy maps and vector tiles)
+/// into its bounding box in degrees.
+/// Returns [`None`] if `z` is above 32 or `x`/`y` lie outside of the zoom level.
+pub fn tile_bbox(z: u8, x: u32, y: u32) -> Option<BBox> {
+    if z > 32 {
+        return None;
+    }
+    let tiles = 1u64 << z;
+    if u64::from(x) >= tiles || u64::from(y) >= tiles {
+        return None;
+    }
+    let tiles = tiles as f64;
+    let lon = |x: f64| x / tiles * 360.0 - 180.0;
+    let lat = |y: f64| (PI * (1.0 - 2.0 * y / tiles)).sinh().atan().to_degrees();
+    Some(BBox {
+        left: lon(x as f64),
+        bottom: lat(y as f64 + 1.0),
+        right: lon(x as f64 + 1.0),
+        top: lat(y as f64),
+    })
+}
+
+impl OsmReader {
+    /// Extracts all elements intersecting the web mercator tile `z/x/y` as owned elements:
+    /// the nodes inside of [`tile_bbox`], the ways referencing at least one of them and
+    /// the relations with one of these nodes or ways as member
+    /// (relations only referencing relations are left out).
+    /// Elements are ordered nodes, ways, relations, each by id.
+    ///
+    /// The file is read three times (nodes through the bbox filter, then ways, then relations),
+    /// the element and tag filters of the reader are respected. Returns an error if a bbox filter
+    /// was applied already, the tile is invalid, or a pass fails
+    /// (see [`ElementBlockIter::finish`](crate::ElementBlockIter::finish)).
+    pub fn extract_tile(self, z: u8, x: u32, y: u32) -> std::io::Result<Vec<Element>> {
+        let Some(bbox) = tile_bbox(z, x, y) else {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "The tile lies outside of its zoom level",
+            ));
+        };
+        let ways_reader = self.reopen()?;
+        let relations_reader = self.reopen()?;
+        self.apply_bbox_filter(bbox)
+            .map_err(|err| std::io::Error::new(ErrorKind::InvalidInput, err))?;
+
+        // nodes inside of the tile
+        let _ = self.apply_element_filter(ElementFilter {
+            nodes: true,
+            ways: false,
+            relations: false,
+        });
+        let mut nodes = Vec::new();
+        let mut blocks = self.blocks();
+        for block in blocks.by_ref() {
+            match block {
+                ElementBlock::DenseNodeBlock(block) => {
+                    nodes.extend(block.iter().map(|node| node.to_owned()))
+                }
+                ElementBlock::NodeBlock(block) => {
+                    nodes.extend(block.iter().map(|node| node.to_owned()))
+                }
+                _ => (),
+            }
+        }
+        blocks.finish()?;
+        let node_ids: HashSet<i64> = nodes.iter().map(|node| node.id).collect();
+
+        // ways referencing one of the nodes
+        let _ = ways_reader.apply_element_filter(ElementFilter {
+            nodes: false,
+            ways: true,
+            relations: false,
+        });
+        let mut ways = Vec::new();
+        let mut blocks = ways_reader.blocks();
+        for block in blocks.by_ref() {
+            if let ElementBlock::WayBlock(block) = block {
+                ways.extend(
+                    block
+                        .iter()
+                        .filter(|way| way.node_ids().any(|id| node_ids.contains(&id)))
+                        .map(|way| way.to_owned()),
+                );
+            }
+        }
+        blocks.finish()?;
+        let way_ids: HashSet<i64> = ways.iter().map(|way| way.id).collect();
+
+        // relations with one of the nodes or ways as member
+        let _ = relations_reader.apply_element_filter(ElementFilter {
+            nodes: false,
+            ways: false,
+            relations: true,
+        });
+        let mut relations = Vec::new();
+        let mut blocks = relations_reader.blocks();
+        for block in blocks.by_ref() {
+            if let ElementBlock::RelationBlock(block) = block {
+                relations.extend(
+                    block
+                        .iter()
+                        .filter(|relation| {
+                            relation.members().any(|member| match member.member_type() {
+                                MemberType::NODE => node_ids.contains(&member.id()),
+                                MemberType::WAY => way_ids.contains(&member.id()),
+                                MemberType::RELATION => false,
+                            })
+                        })
+                        .map(|relation| relation.to_owned()),
+                );
+            }
+        }
+        blocks.finish()?;
+
+        nodes.sort_unstable_by_key(|node| node.id);
+        ways.sort_unstable_by_key(|way| way.id);
+        relations.sort_unstable_by_key(|relation| relation.id);
+        let mut elements = Vec::with_capacity(nodes.len() + ways.len() + relations.len());
+        elements.extend(nodes.into_iter().map(Element::from));
+        elements.extend(ways.into_iter().map(Element::from));
+        elements.extend(relations.into_iter().map(Element::from));
+        Ok(elements)
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{parser::OsmParser, ElementBlock, OsmError, OsmReader};
+use rayon::iter::{ParallelBridge, ParallelIterator};
+use std::sync::Arc;
+
+/// The result of [`OsmReader::validate`]
+#[derive(Debug, Default)]
+pub struct ValidationReport {
+    /// Number of data blobs read
+    pub blobs: u64,
+    /// Number of nodes (dense or plain) in the valid blobs
+    pub nodes: u64,
+    /// Number of ways in the valid blobs
+    pub ways: u64,
+    /// Number of relations in the valid blobs
+    pub relations: u64,
+    /// Byte offsets of the blobs that could not be decompressed, parsed or failed the
+    /// [`IdPolicy`](crate::IdPolicy), in file order
+    pub corrupt_blobs: Vec<u64>,
+    /// Byte offset of the blob the file ends in, if it is truncated
+    pub truncated_at: Option<u64>,
+    /// The error of the first corrupt blob
+    pub first_error: Option<std::io::Error>,
+}
+
+impl ValidationReport {
+    /// Check that no blob is corrupt and the file is not truncated
+    pub fn is_valid(&self) -> bool {
+        self.corrupt_blobs.is_empty() && self.truncated_at.is_none()
+    }
+
+    fn merge(mut self, other: Self) -> Self {
+        let first_offset = self.corrupt_blobs.iter().min().copied();
+        let other_first_offset = other.corrupt_blobs.iter().min().copied();
+        if other_first_offset.is_some_and(|other| first_offset.is_none_or(|first| other < first)) {
+            self.first_error = other.first_error;
+        }
+        self.blobs += other.blobs;
+        self.nodes += other.nodes;
+        self.ways += other.ways;
+        self.relations += other.relations;
+        self.corrupt_blobs.extend(other.corrupt_blobs);
+        self
+    }
+}
+
+impl OsmReader {
+    /// Checks the integrity of the file (e.g. a fresh download) by decompressing and parsing
+    /// every blob in parallel, as fast as a counting pass. Nothing is kept but the counts of
+    /// elements and the corrupt blobs, which are skipped and recorded in the [`ValidationReport`].
+    /// A truncated file ends the check at the incomplete blob. The element filter is respected,
+    /// so apply none to check everything.
+    ///
+    /// Returns an error only if reading the file fails for other reasons than its content.
+    pub fn validate(mut self) -> std::io::Result<ValidationReport> {
+        // stop reading after the first error, the position in the file is unreliable then
+        let decompressor = Arc::clone(&self.config.decompressor);
+        let parse_options = self.parse_options();
+        let mut read_error = None;
+        let blobs = std::iter::from_fn(|| {
+            if read_error.is_some() {
+                return None;
+            }
+            match self.next_blob_with_offset() {
+                Ok(blob) => blob,
+                Err(err) => {
+                    read_error = Some(err);
+                    None
+                }
+            }
+        });
+
+        let mut report = blobs
+            .par_bridge()
+            .map_init(Vec::new, |decompressed_blob, (offset, blob)| {
+                let mut report = ValidationReport {
+                    blobs: 1,
+                    ..ValidationReport::default()
+                };
+                match OsmParser::deserialize_blob(
+                    &blob,
+                    &*decompressor,
+                    &parse_options,
+                    decompressed_blob,
+                ) {
+                    Ok(blocks) => {
+                        for block in blocks {
+                            match block {
+                                ElementBlock::DenseNodeBlock(block) => {
+                                    report.nodes += block.len() as u64
+                                }
+                                ElementBlock::NodeBlock(block) => {
+                                    report.nodes += block.len() as u64
+                                }
+                                ElementBlock::WayBlock(block) => report.ways += block.len() as u64,
+                                ElementBlock::RelationBlock(block) => {
+                                    report.relations += block.len() as u64
+                                }
+                            }
+                        }
+                    }
+                    Err(err) => {
+                        report.corrupt_blobs.push(offset);
+                        report.first_error = Some(err);
+                    }
+                }
+                report
+            })
+            .reduce(ValidationReport::default, ValidationReport::merge);
+        report.corrupt_blobs.sort_unstable();
+
+        if let Some(err) = read_error {
+            match err.get_ref().and_then(|err| err.downcast_ref::<OsmError>()) {
+                Some(OsmError::Truncated { offset }) => report.truncated_at = Some(*offset),
+                _ => return Err(err),
+            }
+        }
+        Ok(report)
+    }
+}